@@ -20,4 +20,8 @@
 
 cargo build --release --target wasm32-unknown-unknown
 cp target/wasm32-unknown-unknown/release/layered.wasm web/layered.wasm
+
+# Serve the level pack and other assets fetched by path.
+rm -rf web/assets
+cp -r assets web/assets
 cargo server --path web/
\ No newline at end of file
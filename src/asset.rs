@@ -1,5 +1,9 @@
 use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, Rgba};
 
+pub mod manager;
+
+pub use manager::{Asset, Assets, Handle, Kind, LoadState};
+
 /// Lighter color used by [`quantize_binary`].
 pub const COLOR_LIGHT: Rgba<u8> = Rgba([255, 255, 255, 0]);
 
@@ -0,0 +1,477 @@
+//! Shared store of loaded assets.
+//!
+//! [`Assets`] loads textures, sprite sheet atlases,
+//! sounds, level packs and fonts, handing out typed
+//! [`Handle`]s to them. Loads are queued and finished
+//! one at a time by [`Assets::load_next`], so callers
+//! can report progress between frames.
+use std::{
+    any::Any,
+    cell::OnceCell,
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    marker::PhantomData,
+    path::Path,
+    rc::Rc,
+};
+
+use macroquad::{
+    audio::Sound,
+    text::Font,
+    texture::{FilterMode, Texture2D},
+};
+
+use crate::{audio::Synth, level::LevelPack, sprite::SpriteSheet};
+
+/// Kinds of assets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Texture,
+
+    /// A [`SpriteSheet`], loaded from its descriptor's path
+    /// and the `.png` image beside the descriptor.
+    Atlas,
+    Sound,
+
+    /// A [`LevelPack`], loaded from its manifest's path.
+    Level,
+
+    /// A TrueType font.
+    Font,
+}
+
+/// A value an [`Assets`] can load.
+pub trait Asset: Any {
+    /// Kind of asset the value is.
+    const KIND: Kind;
+}
+
+impl Asset for Texture2D {
+    const KIND: Kind = Kind::Texture;
+}
+
+impl Asset for Rc<SpriteSheet> {
+    const KIND: Kind = Kind::Atlas;
+}
+
+impl Asset for Sound {
+    const KIND: Kind = Kind::Sound;
+}
+
+impl Asset for LevelPack {
+    const KIND: Kind = Kind::Level;
+}
+
+impl Asset for Font {
+    const KIND: Kind = Kind::Font;
+}
+
+/// State of an asset's load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    /// Waiting to load.
+    Queued,
+
+    Loaded,
+
+    /// Failed to load, for the described reason.
+    Failed(String),
+}
+
+/// Reference to an asset of type `T` in an [`Assets`].
+///
+/// Assets stay loaded while any of their handles exist.
+pub struct Handle<T> {
+    id: u64,
+
+    /// Shared by every handle of the asset.
+    refs: Rc<()>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            refs: self.refs.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Handle").field(&self.id).finish()
+    }
+}
+
+/// Where an asset loads from.
+enum Source {
+    Path,
+    Bytes(&'static [u8]),
+
+    /// Sound rendered by a synth.
+    Synth(Synth),
+}
+
+/// An asset in an [`Assets`].
+struct Entry {
+    kind: Kind,
+
+    /// Path or ID the asset is deduplicated by.
+    key: String,
+    source: Source,
+    state: LoadState,
+    value: Option<Box<dyn Any>>,
+
+    /// Shared by every handle of the asset.
+    refs: Rc<()>,
+}
+
+/// Store of loaded assets, deduplicated by path or ID.
+#[derive(Default)]
+pub struct Assets {
+    entries: HashMap<u64, Entry>,
+
+    /// IDs of entries by kind and key.
+    ids: HashMap<(Kind, String), u64>,
+
+    /// IDs of entries waiting to load, first-queued first.
+    queue: VecDeque<u64>,
+
+    /// Assets finished, and queued, since the queue was last empty.
+    batch: (usize, usize),
+    next_id: u64,
+
+    /// Texture standing in for textures that failed
+    /// (or haven't finished) loading.
+    placeholder: OnceCell<Texture2D>,
+}
+
+impl Assets {
+    /// Returns a handle to the asset at `path`, queueing
+    /// it to load if it isn't already loaded or queued.
+    ///
+    /// Native builds read from disk, while
+    /// web builds fetch over HTTP.
+    pub fn load<T: Asset>(&mut self, path: &str) -> Handle<T> {
+        self.entry(path, Source::Path)
+    }
+
+    /// Returns a handle to the asset with `id`, queueing it to
+    /// load from `bytes` if it isn't already loaded or queued.
+    ///
+    /// Atlases and levels span several files,
+    /// and only load from paths.
+    pub fn load_bytes<T: Asset>(&mut self, id: &str, bytes: &'static [u8]) -> Handle<T> {
+        self.entry(id, Source::Bytes(bytes))
+    }
+
+    /// Returns a handle to the sound with `id`, queueing it
+    /// to render from `synth` if it isn't already loaded or queued.
+    pub fn load_synth(&mut self, id: &str, synth: Synth) -> Handle<Sound> {
+        self.entry(id, Source::Synth(synth))
+    }
+
+    /// Returns a handle to `value` with `id`, replacing
+    /// any asset of the same kind with the same `id`.
+    pub fn insert<T: Asset>(&mut self, id: &str, value: T) -> Handle<T> {
+        let handle = self.entry::<T>(id, Source::Path);
+        self.dequeue(handle.id);
+
+        let entry = self.entries.get_mut(&handle.id).unwrap();
+        entry.value = Some(Box::new(value));
+        entry.state = LoadState::Loaded;
+        handle
+    }
+
+    /// Returns the asset of `handle`, if it's loaded.
+    pub fn get<T: Asset>(&self, handle: &Handle<T>) -> Option<&T> {
+        self.entries.get(&handle.id)?.value.as_ref()?.downcast_ref()
+    }
+
+    /// Returns the texture of `handle`, or a placeholder
+    /// texture if it failed (or hasn't finished) loading.
+    pub fn texture(&self, handle: &Handle<Texture2D>) -> &Texture2D {
+        self.get(handle).unwrap_or_else(|| {
+            self.placeholder.get_or_init(|| {
+                // Checkerboard of magenta and black.
+                const M: [u8; 4] = [255, 0, 255, 255];
+                const K: [u8; 4] = [0, 0, 0, 255];
+                let texture = Texture2D::from_rgba8(2, 2, &[M, K, K, M].concat());
+                texture.set_filter(FilterMode::Nearest);
+                texture
+            })
+        })
+    }
+
+    /// Returns the path or ID `handle`'s asset is deduplicated by.
+    pub fn key<T: Asset>(&self, handle: &Handle<T>) -> &str {
+        &self.entries[&handle.id].key
+    }
+
+    /// Reloads `handle`'s asset from the file at its path, keeping
+    /// the previous asset if the file fails to load.
+    ///
+    /// Textures reload in place, so their copies pick up the change.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn reload<T: Asset>(&mut self, handle: &Handle<T>) -> Result<(), String> {
+        let entry = &self.entries[&handle.id];
+        let key = entry.key.clone();
+        let texture = entry
+            .value
+            .as_ref()
+            .and_then(|v| v.downcast_ref::<Texture2D>());
+        if let Some(texture) = texture {
+            let bytes = read(&key).await.map_err(|e| format!("{key}: {e}"))?;
+            return crate::reload::reload_texture(texture, &bytes)
+                .map_err(|e| format!("{key}: {e}"));
+        }
+
+        let value = decode(entry.kind, &key, &Source::Path)
+            .await
+            .map_err(|e| format!("{key}: {e}"))?;
+        let entry = self.entries.get_mut(&handle.id).unwrap();
+        entry.value = Some(value);
+        entry.state = LoadState::Loaded;
+        Ok(())
+    }
+
+    /// Returns the load state of `handle`'s asset.
+    pub fn state<T: Asset>(&self, handle: &Handle<T>) -> &LoadState {
+        &self.entries[&handle.id].state
+    }
+
+    /// Returns true while any assets are queued to load.
+    pub fn is_loading(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Returns the fraction (`0.0..=1.0`) of assets finished
+    /// loading since the queue was last empty.
+    pub fn progress(&self) -> f32 {
+        match self.batch {
+            (_, 0) => 1.0,
+            (done, total) => done as f32 / total as f32,
+        }
+    }
+
+    /// Loads the next queued asset, returning
+    /// false if no assets were queued.
+    pub async fn load_next(&mut self) -> bool {
+        let Some(id) = self.queue.pop_front() else {
+            return false;
+        };
+
+        let entry = &self.entries[&id];
+        let result = decode(entry.kind, &entry.key, &entry.source).await;
+
+        let entry = self.entries.get_mut(&id).unwrap();
+        match result {
+            Ok(value) => {
+                entry.value = Some(value);
+                entry.state = LoadState::Loaded;
+            }
+            Err(error) => entry.state = LoadState::Failed(error),
+        }
+
+        self.finish();
+        true
+    }
+
+    /// Loads every queued asset.
+    pub async fn load_all(&mut self) {
+        while self.load_next().await {}
+    }
+
+    /// Unloads every asset without any handles,
+    /// returning the number of assets unloaded.
+    pub fn unload_unused(&mut self) -> usize {
+        let unused: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| Rc::strong_count(&entry.refs) == 1)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &unused {
+            let entry = self.entries.remove(id).unwrap();
+            self.ids.remove(&(entry.kind, entry.key));
+            self.dequeue(*id);
+        }
+
+        unused.len()
+    }
+
+    /// Returns the number of assets loaded or queued.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no assets are loaded or queued.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes the entry with `id` from the queue, if it's queued.
+    fn dequeue(&mut self, id: u64) {
+        if let Some(index) = self.queue.iter().position(|queued| *queued == id) {
+            self.queue.remove(index);
+            self.finish();
+        }
+    }
+
+    /// Counts a queued asset as finished.
+    fn finish(&mut self) {
+        self.batch.0 += 1;
+        if self.queue.is_empty() {
+            self.batch = (0, 0);
+        }
+    }
+
+    /// Returns a handle to the entry of kind `T` with
+    /// `key`, adding and queueing it if it's new.
+    fn entry<T: Asset>(&mut self, key: &str, source: Source) -> Handle<T> {
+        let id = match self.ids.get(&(T::KIND, key.to_string())) {
+            Some(id) => *id,
+            None => {
+                let id = self.next_id;
+                self.next_id += 1;
+
+                self.entries.insert(
+                    id,
+                    Entry {
+                        kind: T::KIND,
+                        key: key.to_string(),
+                        source,
+                        state: LoadState::Queued,
+                        value: None,
+                        refs: Rc::new(()),
+                    },
+                );
+                self.ids.insert((T::KIND, key.to_string()), id);
+                self.queue.push_back(id);
+                self.batch.1 += 1;
+                id
+            }
+        };
+
+        Handle {
+            id,
+            refs: self.entries[&id].refs.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Returns the asset of `kind` loaded from `source`.
+async fn decode(kind: Kind, key: &str, source: &Source) -> Result<Box<dyn Any>, String> {
+    let bytes = match source {
+        Source::Bytes(bytes) => bytes.to_vec(),
+        Source::Synth(synth) => match kind {
+            Kind::Sound => synth.to_wav(),
+            _ => return Err("synths only render sounds".into()),
+        },
+        Source::Path => match kind {
+            Kind::Level => {
+                let pack = LevelPack::load(key).await.map_err(|e| e.to_string())?;
+                return Ok(Box::new(pack));
+            }
+            _ => read(key).await?,
+        },
+    };
+
+    let value: Box<dyn Any> = match kind {
+        Kind::Texture => Box::new(decode_texture(&bytes)?),
+        Kind::Atlas => {
+            let Source::Path = source else {
+                return Err("atlases only load from paths".into());
+            };
+            let image_path = Path::new(key).with_extension("png");
+            let image = read(&image_path.to_string_lossy()).await?;
+
+            let descriptor = String::from_utf8_lossy(&bytes);
+            let sheet = SpriteSheet::from_bytes(&image, &descriptor).map_err(|e| e.to_string())?;
+            Box::new(Rc::new(sheet))
+        }
+        Kind::Sound => Box::new(
+            macroquad::audio::load_sound_from_bytes(&bytes)
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        Kind::Level => return Err("levels only load from paths".into()),
+        Kind::Font => {
+            Box::new(macroquad::text::load_ttf_font_from_bytes(&bytes).map_err(|e| e.to_string())?)
+        }
+    };
+
+    Ok(value)
+}
+
+/// Returns the bytes of the file at `path`.
+async fn read(path: &str) -> Result<Vec<u8>, String> {
+    macroquad::file::load_file(path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Returns a texture of the image in `bytes`.
+fn decode_texture(bytes: &[u8]) -> Result<Texture2D, String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let (Ok(width), Ok(height)) = (u16::try_from(image.width()), u16::try_from(image.height()))
+    else {
+        return Err(format!(
+            "{}x{} image is too large",
+            image.width(),
+            image.height()
+        ));
+    };
+
+    Ok(Texture2D::from_rgba8(width, height, image.as_raw()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(name: &str) -> LevelPack {
+        LevelPack {
+            name: name.into(),
+            levels: vec![],
+        }
+    }
+
+    #[test]
+    fn deduplicates_and_unloads_by_handle() {
+        let mut assets = Assets::default();
+
+        let first = assets.load::<LevelPack>("levels.pack");
+        let second = assets.load::<LevelPack>("levels.pack");
+        assert_eq!(first, second);
+        assert_eq!(1, assets.len());
+        assert_eq!(&LoadState::Queued, assets.state(&first));
+        assert_eq!(0.0, assets.progress());
+
+        // Inserting values finishes their loads.
+        assets.insert("levels.pack", pack("Layered"));
+        assert_eq!("Layered", assets.get(&first).unwrap().name);
+        assert!(!assets.is_loading());
+        assert_eq!(1.0, assets.progress());
+
+        drop(first);
+        assert_eq!(0, assets.unload_unused());
+        drop(second);
+        assert_eq!(1, assets.unload_unused());
+        assert!(assets.is_empty());
+    }
+}
@@ -0,0 +1,446 @@
+//! Mixing of sounds and music across volume buses.
+//!
+//! [`Audio`] plays named [`Clip`]s on [`Bus`]es with their
+//! own volumes and mutes, limiting how many voices play
+//! on each bus at once. Looping clips play as music
+//! tracks, which fade in and out independently so
+//! they can crossfade or layer as adaptive stems.
+use std::collections::HashMap;
+
+use macroquad::audio::{play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound};
+
+use crate::tween::{Animation, Tween};
+
+pub mod emitter;
+pub mod synth;
+
+pub use emitter::{Emitter, Spatial};
+pub use synth::{Preset, Synth, Waveform};
+
+/// A group of sounds sharing a volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+impl Bus {
+    /// Every bus.
+    pub const ALL: [Bus; 3] = [Bus::Music, Bus::Sfx, Bus::Ui];
+
+    /// Returns the bus's name, like `sfx`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Music => "music",
+            Self::Sfx => "sfx",
+            Self::Ui => "ui",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// A sound, and how it plays.
+#[derive(Clone, Debug)]
+pub struct Clip {
+    sound: Sound,
+    bus: Bus,
+
+    /// Volume (`0.0..=1.0`) before bus and master volumes.
+    volume: f32,
+
+    /// Priority of the clip's voices over
+    /// other voices; higher is more important.
+    priority: u8,
+
+    /// Duration, in seconds, the clip's voices occupy.
+    seconds: f32,
+}
+
+impl Clip {
+    /// Returns a clip playing `sound` on `bus`, at full
+    /// volume and lowest priority, occupying a voice
+    /// for one second.
+    pub fn new(sound: Sound, bus: Bus) -> Self {
+        Self {
+            sound,
+            bus,
+            volume: 1.0,
+            priority: 0,
+            seconds: 1.0,
+        }
+    }
+
+    /// Plays the clip at `volume`.
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Plays the clip with `priority` over other voices.
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Counts the clip's voices as playing for `seconds`.
+    pub fn seconds(mut self, seconds: f32) -> Self {
+        self.seconds = seconds;
+        self
+    }
+}
+
+/// Volume and voice limit of a [`Bus`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BusMix {
+    volume: f32,
+    muted: bool,
+    max_voices: usize,
+}
+
+/// Master and bus volumes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Mix {
+    master_volume: f32,
+    muted: bool,
+    buses: [BusMix; 3],
+}
+
+impl Mix {
+    /// Returns the volume a sound on `bus` plays at, at `volume`.
+    fn volume(&self, bus: Bus, volume: f32) -> f32 {
+        let bus = self.buses[bus.index()];
+        if self.muted || bus.muted {
+            return 0.0;
+        }
+
+        self.master_volume * bus.volume * volume
+    }
+}
+
+/// A playing one-shot clip.
+#[derive(Clone, Debug, PartialEq)]
+struct Voice {
+    clip: String,
+    bus: Bus,
+    priority: u8,
+
+    /// Time, in seconds, the voice stops playing.
+    ends_at: f64,
+}
+
+/// Result of asking for a voice on a full bus.
+#[derive(Clone, Debug, PartialEq)]
+enum Admission {
+    /// Plays without stopping any voices.
+    Free,
+
+    /// Plays after stopping the voices of a clip.
+    Steal(String),
+
+    /// Doesn't play.
+    Refused,
+}
+
+/// Plays clips and music tracks, mixing their volumes.
+pub struct Audio {
+    clips: HashMap<String, Clip>,
+    mix: Mix,
+    voices: Vec<Voice>,
+
+    /// Levels (`0.0..=1.0`) of playing music tracks, by clip.
+    tracks: HashMap<String, Tween<(), f32>>,
+
+    /// Clips of the tracks played by emitters.
+    emitting: Vec<String>,
+
+    /// Seconds since the audio started.
+    time: f64,
+
+    /// True if volumes changed since they were last applied.
+    dirty: bool,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        let bus = |max_voices| BusMix {
+            volume: 1.0,
+            muted: false,
+            max_voices,
+        };
+
+        Self {
+            clips: HashMap::new(),
+            mix: Mix {
+                master_volume: 1.0,
+                muted: false,
+                buses: [bus(4), bus(8), bus(4)],
+            },
+            voices: vec![],
+            tracks: HashMap::new(),
+            emitting: vec![],
+            time: 0.0,
+            dirty: false,
+        }
+    }
+}
+
+impl Audio {
+    /// Adds `clip` named `name`, replacing any clip with the same name.
+    pub fn add(&mut self, name: &str, clip: Clip) {
+        self.clips.insert(name.to_string(), clip);
+    }
+
+    /// Plays the clip named `name` once, returning false if it isn't
+    /// added or its bus is full of voices with a higher priority.
+    ///
+    /// Full buses stop the lowest-priority (and then
+    /// oldest) voice to make room for a new voice.
+    pub fn play(&mut self, name: &str) -> bool {
+        let Some(clip) = self.clips.get(name) else {
+            return false;
+        };
+        let (bus, priority, seconds) = (clip.bus, clip.priority, clip.seconds);
+
+        match self.admit(bus, priority) {
+            Admission::Refused => return false,
+            Admission::Steal(stolen) => {
+                stop_sound(&self.clips[&stolen].sound);
+                self.voices.retain(|voice| voice.clip != stolen);
+            }
+            Admission::Free => {}
+        }
+
+        let clip = &self.clips[name];
+        play_sound(
+            &clip.sound,
+            PlaySoundParams {
+                looped: false,
+                volume: self.mix.volume(clip.bus, clip.volume),
+            },
+        );
+        self.voices.push(Voice {
+            clip: name.to_string(),
+            bus,
+            priority,
+            ends_at: self.time + seconds as f64,
+        });
+
+        true
+    }
+
+    /// Starts every clip in `names` as a looping music track at
+    /// once, silently, so they stay in sync as adaptive stems.
+    ///
+    /// Tracks already playing are left unchanged.
+    pub fn start_tracks(&mut self, names: &[&str]) {
+        for name in names {
+            if self.tracks.contains_key(*name) {
+                continue;
+            }
+
+            let Some(clip) = self.clips.get(*name) else {
+                continue;
+            };
+            play_sound(
+                &clip.sound,
+                PlaySoundParams {
+                    looped: true,
+                    volume: 0.0,
+                },
+            );
+            self.tracks
+                .insert(name.to_string(), Tween::new(0.0, 0.0, 0.0));
+        }
+    }
+
+    /// Fades the music track named `name` to `level`
+    /// (`0.0..=1.0`) over `seconds`, starting it if needed.
+    pub fn fade_track(&mut self, name: &str, level: f32, seconds: f32) {
+        self.start_tracks(&[name]);
+        if let Some(track) = self.tracks.get_mut(name) {
+            *track = Tween::new(track.value(), level.clamp(0.0, 1.0), seconds);
+        }
+    }
+
+    /// Fades in the music track named `name` over `seconds`,
+    /// while fading out every other music track.
+    pub fn crossfade(&mut self, name: &str, seconds: f32) {
+        let others: Vec<String> = self.tracks.keys().filter(|n| *n != name).cloned().collect();
+        for other in others {
+            self.fade_track(&other, 0.0, seconds);
+        }
+        self.fade_track(name, 1.0, seconds);
+    }
+
+    /// Stops the music track named `name`.
+    pub fn stop_track(&mut self, name: &str) {
+        if self.tracks.remove(name).is_some() {
+            stop_sound(&self.clips[name].sound);
+        }
+    }
+
+    /// Returns the master volume (`0.0..=1.0`).
+    pub fn master_volume(&self) -> f32 {
+        self.mix.master_volume
+    }
+
+    /// Sets the master volume (`0.0..=1.0`).
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.mix.master_volume = volume.clamp(0.0, 1.0);
+        self.dirty = true;
+    }
+
+    /// Returns true if all audio is muted.
+    pub fn is_muted(&self) -> bool {
+        self.mix.muted
+    }
+
+    /// Mutes (or unmutes) all audio.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.mix.muted = muted;
+        self.dirty = true;
+    }
+
+    /// Returns the volume (`0.0..=1.0`) of `bus`.
+    pub fn bus_volume(&self, bus: Bus) -> f32 {
+        self.mix.buses[bus.index()].volume
+    }
+
+    /// Sets the volume (`0.0..=1.0`) of `bus`.
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.mix.buses[bus.index()].volume = volume.clamp(0.0, 1.0);
+        self.dirty = true;
+    }
+
+    /// Returns true if `bus` is muted.
+    pub fn is_bus_muted(&self, bus: Bus) -> bool {
+        self.mix.buses[bus.index()].muted
+    }
+
+    /// Mutes (or unmutes) `bus`.
+    pub fn set_bus_muted(&mut self, bus: Bus, muted: bool) {
+        self.mix.buses[bus.index()].muted = muted;
+        self.dirty = true;
+    }
+
+    /// Limits `bus` to `max_voices` one-shot voices at once.
+    pub fn set_max_voices(&mut self, bus: Bus, max_voices: usize) {
+        self.mix.buses[bus.index()].max_voices = max_voices;
+    }
+
+    /// Advances fades and voices by `frame_time` seconds.
+    pub fn update(&mut self, frame_time: f32) {
+        self.time += frame_time as f64;
+        let time = self.time;
+        self.voices.retain(|voice| voice.ends_at > time);
+
+        for (name, track) in &mut self.tracks {
+            if !track.is_complete() || self.dirty {
+                track.advance(&mut (), frame_time);
+                let clip = &self.clips[name];
+                set_sound_volume(
+                    &clip.sound,
+                    self.mix.volume(clip.bus, clip.volume * track.value()),
+                );
+            }
+        }
+
+        if self.dirty {
+            for (name, clip) in &self.clips {
+                if !self.tracks.contains_key(name) {
+                    set_sound_volume(&clip.sound, self.mix.volume(clip.bus, clip.volume));
+                }
+            }
+            self.dirty = false;
+        }
+    }
+
+    /// Returns whether a voice with `priority` may play on `bus`.
+    fn admit(&self, bus: Bus, priority: u8) -> Admission {
+        let voices: Vec<&Voice> = self.voices.iter().filter(|v| v.bus == bus).collect();
+        if voices.len() < self.mix.buses[bus.index()].max_voices {
+            return Admission::Free;
+        }
+
+        // Voices are in the order they started, so the
+        // first lowest-priority voice is the oldest.
+        match voices.iter().min_by_key(|voice| voice.priority) {
+            Some(voice) if voice.priority <= priority => Admission::Steal(voice.clip.clone()),
+            _ => Admission::Refused,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(clip: &str, bus: Bus, priority: u8) -> Voice {
+        Voice {
+            clip: clip.into(),
+            bus,
+            priority,
+            ends_at: f64::INFINITY,
+        }
+    }
+
+    #[test]
+    fn full_buses_steal_lower_priority_voices() {
+        let mut audio = Audio::default();
+        audio.set_max_voices(Bus::Sfx, 2);
+        audio.voices = vec![
+            voice("step", Bus::Sfx, 0),
+            voice("chime", Bus::Sfx, 2),
+            voice("click", Bus::Ui, 0),
+        ];
+
+        assert_eq!(Admission::Free, audio.admit(Bus::Ui, 0));
+        assert_eq!(Admission::Steal("step".into()), audio.admit(Bus::Sfx, 0));
+        audio.voices.remove(0);
+        audio.voices.push(voice("chime", Bus::Sfx, 2));
+        assert_eq!(Admission::Refused, audio.admit(Bus::Sfx, 1));
+    }
+
+    #[test]
+    fn mutes_silence_their_buses() {
+        let mut audio = Audio::default();
+        audio.set_master_volume(0.5);
+        audio.set_bus_volume(Bus::Sfx, 0.5);
+        assert_eq!(0.125, audio.mix.volume(Bus::Sfx, 0.5));
+
+        audio.set_bus_muted(Bus::Sfx, true);
+        assert_eq!(0.0, audio.mix.volume(Bus::Sfx, 0.5));
+        assert_eq!(0.25, audio.mix.volume(Bus::Music, 0.5));
+
+        audio.set_muted(true);
+        assert_eq!(0.0, audio.mix.volume(Bus::Music, 0.5));
+    }
+
+    #[test]
+    fn tracks_crossfade() {
+        let mut audio = Audio::default();
+        for name in ["calm", "tense"] {
+            audio.tracks.insert(name.into(), Tween::new(0.0, 0.0, 0.0));
+        }
+        let advance = |audio: &mut Audio, seconds| {
+            for track in audio.tracks.values_mut() {
+                track.advance(&mut (), seconds);
+            }
+        };
+
+        audio.fade_track("calm", 0.8, 1.0);
+        advance(&mut audio, 0.5);
+        assert!(audio.tracks["calm"].value() > 0.0 && audio.tracks["calm"].value() < 0.8);
+        advance(&mut audio, 0.5);
+        assert_eq!(0.8, audio.tracks["calm"].value());
+
+        audio.crossfade("tense", 2.0);
+        advance(&mut audio, 2.0);
+        assert_eq!(0.0, audio.tracks["calm"].value());
+        assert_eq!(1.0, audio.tracks["tense"].value());
+    }
+}
@@ -0,0 +1,183 @@
+//! Sounds emitted from positions on a [`TileMap`].
+//!
+//! An [`Emitter`] is heard louder the closer it is to a
+//! listener, panned towards the side of the screen it's on,
+//! and damped by every solid tile between them.
+//!
+//! Macroquad can't pan sounds as they play, so emitters play
+//! two variants of their clip at once, rendered [`SIDES`]
+//! apart, and pan by balancing their volumes.
+use std::{collections::HashMap, f32::consts::FRAC_PI_4};
+
+use glam::Vec2;
+
+use super::Audio;
+use crate::tile::{shape, Tile, TileMap};
+
+/// Fraction of an emitter's volume passing
+/// through each solid tile between it and a listener.
+const OCCLUSION: f32 = 0.5;
+
+/// Seconds over which emitters' volumes follow their listeners.
+const SMOOTHING_SECONDS: f32 = 0.1;
+
+/// Sides emitters are heard from, and the
+/// pans of their clips' variants on each side.
+pub const SIDES: [(&str, f32); 2] = [("left", -1.0), ("right", 1.0)];
+
+/// Returns the name of the variant of `clip` heard from `side`.
+pub fn panned(clip: &str, side: &str) -> String {
+    format!("{clip}.{side}")
+}
+
+/// A looping clip heard from a position on a [`TileMap`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emitter {
+    /// Name of the clip the emitter plays.
+    pub clip: String,
+
+    /// Grid position of the emitter.
+    pub position: Vec2,
+
+    /// Distance, in tiles, beyond which
+    /// the emitter can't be heard.
+    pub radius: f32,
+}
+
+impl Emitter {
+    /// Returns an emitter playing the clip named `clip` at
+    /// `position`, heard from up to `radius` tiles away.
+    pub fn new(clip: &str, position: Vec2, radius: f32) -> Self {
+        Self {
+            clip: clip.to_string(),
+            position,
+            radius,
+        }
+    }
+}
+
+/// How loud, and from which side, an [`Emitter`] is heard.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Spatial {
+    /// Volume (`0.0..=1.0`) of the emitter.
+    pub volume: f32,
+
+    /// Stereo pan (`-1.0..=1.0`) of the emitter,
+    /// from the left of the screen to the right.
+    pub pan: f32,
+}
+
+impl Spatial {
+    /// Returns how an emitter at grid `position` is heard by a
+    /// listener at grid `listener`, from up to `radius` tiles
+    /// away with `occluders` solid tiles between them.
+    pub fn new(position: Vec2, listener: Vec2, radius: f32, occluders: usize) -> Self {
+        let delta = position - listener;
+        let distance = delta.length();
+        if radius <= 0.0 || distance >= radius {
+            return Self::default();
+        }
+
+        let rolloff = 1.0 - distance / radius;
+        let volume = rolloff * rolloff * OCCLUSION.powi(occluders as i32);
+
+        // Grid directions lean diagonally on screen.
+        let screen = Vec2::new(delta.x - delta.y, (delta.x + delta.y) / 2.0);
+        let pan = screen.normalize_or_zero().x;
+
+        Self { volume, pan }
+    }
+
+    /// Returns the volumes of the emitter on each of
+    /// the [`SIDES`], as loud across every pan.
+    pub fn sides(&self) -> [f32; 2] {
+        let angle = (self.pan + 1.0) * FRAC_PI_4;
+        [self.volume * angle.cos(), self.volume * angle.sin()]
+    }
+}
+
+impl TileMap {
+    /// Returns how `emitter` is heard by a listener at grid
+    /// `listener` on `layer`, damped by tiles for which
+    /// `is_solid` returns true.
+    pub fn hear(
+        &self,
+        emitter: &Emitter,
+        listener: Vec2,
+        layer: i8,
+        is_solid: impl Fn(&Tile) -> bool,
+    ) -> Spatial {
+        let (from, to) = (emitter.position, listener);
+        let line: Vec<_> = shape::supercover_line(from.x, from.y, to.x, to.y).collect();
+
+        // Emitters and listeners don't occlude themselves.
+        let occluders = line
+            .iter()
+            .skip(1)
+            .take(line.len().saturating_sub(2))
+            .filter(|(x, y)| {
+                let (Ok(x), Ok(y)) = (usize::try_from(*x), usize::try_from(*y)) else {
+                    return false;
+                };
+                self.tile(x, y, layer).is_some_and(&is_solid)
+            })
+            .count();
+
+        Spatial::new(emitter.position, listener, emitter.radius, occluders)
+    }
+}
+
+impl Audio {
+    /// Plays the [`panned`] variants of every emitted clip in
+    /// `heard` as looping tracks, each at the volume of its
+    /// loudest emitter on its side, fading out clips no
+    /// longer emitted.
+    pub fn play_emitters<'a>(&mut self, heard: impl IntoIterator<Item = (&'a str, Spatial)>) {
+        let mut loudest: HashMap<String, f32> = HashMap::new();
+        for (clip, spatial) in heard {
+            for ((side, _), side_volume) in SIDES.into_iter().zip(spatial.sides()) {
+                let volume = loudest.entry(panned(clip, side)).or_default();
+                *volume = volume.max(side_volume);
+            }
+        }
+
+        for clip in std::mem::take(&mut self.emitting) {
+            if !loudest.contains_key(&clip) {
+                self.fade_track(&clip, 0.0, SMOOTHING_SECONDS);
+            }
+        }
+
+        for (clip, volume) in loudest {
+            self.fade_track(&clip, volume, SMOOTHING_SECONDS);
+            self.emitting.push(clip);
+        }
+    }
+
+    /// Fades out every emitted clip, until emitters play again.
+    pub fn silence_emitters(&mut self) {
+        self.play_emitters([]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emitters_fade_pan_and_occlude() {
+        let listener = Vec2::new(5.0, 5.0);
+
+        let near = Spatial::new(Vec2::new(6.0, 4.0), listener, 10.0, 0);
+        let far = Spatial::new(Vec2::new(12.0, 5.0), listener, 10.0, 0);
+        assert!(near.volume > far.volume);
+        assert_eq!(1.0, near.pan);
+        assert!(near.sides()[0] < 1e-6);
+        assert_eq!(near.volume, near.sides()[1]);
+
+        let occluded = Spatial::new(Vec2::new(6.0, 4.0), listener, 10.0, 2);
+        assert_eq!(near.volume / 4.0, occluded.volume);
+
+        let silent = Spatial::new(Vec2::new(20.0, 5.0), listener, 10.0, 0);
+        assert_eq!(Spatial::default(), silent);
+    }
+}
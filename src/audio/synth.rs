@@ -0,0 +1,409 @@
+//! Retro sound effects synthesized from parameters.
+//!
+//! Like [sfxr](https://www.drpetter.se/project_sfxr.html),
+//! a [`Synth`] shapes a single oscillator with an envelope,
+//! pitch slides, vibrato, arpeggios and filters. [`Preset`]s
+//! randomize synths for common effects from a seed, so
+//! the same seed always sounds the same.
+use std::f32::consts::TAU;
+
+/// Sample rate, in hertz, of rendered sounds.
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// Noise samples per oscillator period.
+const NOISE_SAMPLES: usize = 32;
+
+/// Shape of a synth's oscillator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sawtooth,
+    Sine,
+    Noise,
+}
+
+/// Kinds of randomized synths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Bright chime, like collecting a coin.
+    Pickup,
+
+    /// Short, falling crunch.
+    Hit,
+
+    /// Simple menu beep.
+    Blip,
+
+    /// Long, rumbling noise.
+    Explosion,
+}
+
+/// Parameters of a synthesized sound.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Synth {
+    pub waveform: Waveform,
+
+    /// Seconds the sound rises to full volume over.
+    pub attack: f32,
+
+    /// Seconds the sound holds at full volume.
+    pub sustain: f32,
+
+    /// Extra volume (`0.0..=1.0`) at the start
+    /// of the sustain, fading over the sustain.
+    pub punch: f32,
+
+    /// Seconds the sound falls silent over.
+    pub decay: f32,
+
+    /// Starting pitch, in hertz.
+    pub frequency: f32,
+
+    /// Pitch, in hertz, below which the sound cuts off.
+    pub min_frequency: f32,
+
+    /// Pitch slide, in octaves per second.
+    pub slide: f32,
+
+    /// Change in `slide`, in octaves per second squared.
+    pub delta_slide: f32,
+
+    /// Depth of vibrato, as a fraction of the pitch.
+    pub vibrato_depth: f32,
+
+    /// Speed of vibrato, in hertz.
+    pub vibrato_speed: f32,
+
+    /// Factor the pitch jumps by after `arpeggio_time`.
+    pub arpeggio: f32,
+
+    /// Seconds before the pitch jumps by `arpeggio`.
+    pub arpeggio_time: f32,
+
+    /// Fraction (`0.0..=1.0`) of each square wave period spent high.
+    pub duty: f32,
+
+    /// Change in `duty`, per second.
+    pub duty_sweep: f32,
+
+    /// Low-pass filter strength (`0.0..=1.0`); zero is unfiltered.
+    pub low_pass: f32,
+
+    /// High-pass filter strength (`0.0..=1.0`); zero is unfiltered.
+    pub high_pass: f32,
+
+    /// Volume (`0.0..=1.0`) of the sound.
+    pub volume: f32,
+
+    /// Stereo pan (`-1.0..=1.0`), from left to right;
+    /// unpanned sounds render in mono.
+    pub pan: f32,
+
+    /// Seed of the noise waveform.
+    pub seed: u64,
+}
+
+impl Default for Synth {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            attack: 0.0,
+            sustain: 0.1,
+            punch: 0.0,
+            decay: 0.2,
+            frequency: 440.0,
+            min_frequency: 20.0,
+            slide: 0.0,
+            delta_slide: 0.0,
+            vibrato_depth: 0.0,
+            vibrato_speed: 0.0,
+            arpeggio: 1.0,
+            arpeggio_time: 0.0,
+            duty: 0.5,
+            duty_sweep: 0.0,
+            low_pass: 0.0,
+            high_pass: 0.0,
+            volume: 0.5,
+            pan: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+impl Synth {
+    /// Returns a synth randomized from `preset` with `seed`.
+    pub fn preset(preset: Preset, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let base = Self {
+            seed,
+            ..Default::default()
+        };
+
+        match preset {
+            Preset::Pickup => {
+                let arpeggiated = rng.chance(0.5);
+                Self {
+                    frequency: rng.range(700.0, 1500.0),
+                    sustain: rng.range(0.02, 0.1),
+                    punch: rng.range(0.3, 0.6),
+                    decay: rng.range(0.1, 0.3),
+                    arpeggio: if arpeggiated {
+                        rng.range(1.2, 1.6)
+                    } else {
+                        1.0
+                    },
+                    arpeggio_time: rng.range(0.04, 0.1),
+                    ..base
+                }
+            }
+            Preset::Hit => Self {
+                waveform: match rng.below(3) {
+                    0 => Waveform::Square,
+                    1 => Waveform::Sawtooth,
+                    _ => Waveform::Noise,
+                },
+                frequency: rng.range(200.0, 800.0),
+                slide: rng.range(-6.0, -2.0),
+                sustain: rng.range(0.02, 0.06),
+                decay: rng.range(0.1, 0.25),
+                duty: rng.range(0.2, 0.5),
+                high_pass: if rng.chance(0.5) {
+                    rng.range(0.0, 0.3)
+                } else {
+                    0.0
+                },
+                ..base
+            },
+            Preset::Blip => Self {
+                waveform: if rng.chance(0.5) {
+                    Waveform::Square
+                } else {
+                    Waveform::Sawtooth
+                },
+                frequency: rng.range(300.0, 900.0),
+                duty: rng.range(0.2, 0.5),
+                sustain: rng.range(0.03, 0.1),
+                decay: rng.range(0.02, 0.1),
+                high_pass: 0.1,
+                ..base
+            },
+            Preset::Explosion => Self {
+                waveform: Waveform::Noise,
+                frequency: rng.range(40.0, 200.0),
+                min_frequency: 5.0,
+                slide: rng.range(-1.0, 0.5),
+                sustain: rng.range(0.1, 0.4),
+                punch: rng.range(0.2, 0.6),
+                decay: rng.range(0.3, 0.8),
+                vibrato_depth: if rng.chance(0.5) {
+                    rng.range(0.0, 0.3)
+                } else {
+                    0.0
+                },
+                vibrato_speed: rng.range(5.0, 20.0),
+                ..base
+            },
+        }
+    }
+
+    /// Returns the sound's duration, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.attack + self.sustain + self.decay
+    }
+
+    /// Returns the sound's samples (`-1.0..=1.0`) at [`SAMPLE_RATE`].
+    pub fn render(&self) -> Vec<f32> {
+        let sample_rate = SAMPLE_RATE as f32;
+        let length = (self.duration() * sample_rate) as usize;
+        let mut samples = Vec::with_capacity(length);
+
+        let mut rng = Rng::new(self.seed);
+        let mut noise = [0.0; NOISE_SAMPLES];
+        noise.iter_mut().for_each(|n| *n = rng.range(-1.0, 1.0));
+
+        let mut phase = 0.0f32;
+        let (mut low, mut slow) = (0.0f32, 0.0f32);
+
+        for index in 0..length {
+            let t = index as f32 / sample_rate;
+
+            // Pitch, after slides, arpeggios and vibrato.
+            let octaves = self.slide * t + 0.5 * self.delta_slide * t * t;
+            let mut frequency = self.frequency * octaves.exp2();
+            if frequency < self.min_frequency {
+                break;
+            }
+            if self.arpeggio_time > 0.0 && t >= self.arpeggio_time {
+                frequency *= self.arpeggio;
+            }
+            frequency *= 1.0 + self.vibrato_depth * (TAU * self.vibrato_speed * t).sin();
+
+            // Oscillator, refreshing noise every period.
+            phase += frequency / sample_rate;
+            if phase >= 1.0 {
+                phase = phase.fract();
+                if self.waveform == Waveform::Noise {
+                    noise.iter_mut().for_each(|n| *n = rng.range(-1.0, 1.0));
+                }
+            }
+            let sample = match self.waveform {
+                Waveform::Square => {
+                    let duty = (self.duty + self.duty_sweep * t).clamp(0.05, 0.95);
+                    if phase < duty {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Waveform::Sawtooth => 1.0 - 2.0 * phase,
+                Waveform::Sine => (TAU * phase).sin(),
+                Waveform::Noise => noise[(phase * NOISE_SAMPLES as f32) as usize % NOISE_SAMPLES],
+            };
+
+            // One-pole filters, removing highs and lows.
+            low += (1.0 - self.low_pass) * (sample - low);
+            slow += self.high_pass * (low - slow);
+            let filtered = low - slow;
+
+            samples.push((filtered * self.envelope(t) * self.volume).clamp(-1.0, 1.0));
+        }
+
+        samples
+    }
+
+    /// Returns the sound as a 16-bit WAV file, in stereo
+    /// if it's panned, playable by [`macroquad::audio`].
+    pub fn to_wav(&self) -> Vec<u8> {
+        if self.pan == 0.0 {
+            wav_from_samples(&self.render())
+        } else {
+            stereo_wav_from_samples(&self.render(), self.pan)
+        }
+    }
+
+    /// Returns the volume envelope at `t` seconds.
+    fn envelope(&self, t: f32) -> f32 {
+        if t < self.attack {
+            t / self.attack
+        } else if t < self.attack + self.sustain {
+            let progress = (t - self.attack) / self.sustain;
+            1.0 + self.punch * (1.0 - progress)
+        } else if self.decay > 0.0 {
+            (1.0 - (t - self.attack - self.sustain) / self.decay).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Returns `samples` (`-1.0..=1.0`, at [`SAMPLE_RATE`])
+/// as a 16-bit mono WAV file.
+pub fn wav_from_samples(samples: &[f32]) -> Vec<u8> {
+    wav_from_frames(1, samples)
+}
+
+/// Returns `samples` (`-1.0..=1.0`, at [`SAMPLE_RATE`]) as a
+/// 16-bit stereo WAV file, panned by `pan` (`-1.0..=1.0`).
+pub fn stereo_wav_from_samples(samples: &[f32], pan: f32) -> Vec<u8> {
+    let pan = pan.clamp(-1.0, 1.0);
+    let (left, right) = ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0));
+    let frames: Vec<f32> = samples.iter().flat_map(|s| [s * left, s * right]).collect();
+    wav_from_frames(2, &frames)
+}
+
+/// Returns interleaved `samples` of `channels`
+/// channels as a 16-bit WAV file.
+fn wav_from_frames(channels: u16, samples: &[f32]) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM.
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2 * channels as u32).to_le_bytes());
+    wav.extend_from_slice(&(2 * channels).to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    wav
+}
+
+/// Small, seedable pseudo-random number generator (xorshift64*).
+#[derive(Clone, Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift can't leave the all-zero state.
+        Self(seed.max(1))
+    }
+
+    /// Returns a random number in `0.0..1.0`.
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let bits = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40;
+        bits as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns a random number in `min..max`.
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    /// Returns a random number in `0..n`.
+    fn below(&mut self, n: u32) -> u32 {
+        ((self.next() * n as f32) as u32).min(n - 1)
+    }
+
+    /// Returns true with `probability`.
+    fn chance(&mut self, probability: f32) -> bool {
+        self.next() < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_seeded() {
+        for preset in [Preset::Pickup, Preset::Hit, Preset::Blip, Preset::Explosion] {
+            let synth = Synth::preset(preset, 7);
+            assert_eq!(synth, Synth::preset(preset, 7));
+
+            let samples = synth.render();
+            assert_eq!(samples, Synth::preset(preset, 7).render());
+            assert!(samples.len() <= (synth.duration() * SAMPLE_RATE as f32) as usize);
+            assert!(samples.iter().any(|s| *s != 0.0), "{preset:?}");
+        }
+
+        assert_ne!(Synth::preset(Preset::Hit, 1), Synth::preset(Preset::Hit, 2));
+    }
+
+    #[test]
+    fn writes_wav_files() {
+        let wav = wav_from_samples(&[0.0, 1.0, -1.0]);
+        assert_eq!(b"RIFF", &wav[0..4]);
+        assert_eq!(44 + 6, wav.len());
+        assert_eq!(i16::MAX.to_le_bytes(), wav[46..48]);
+
+        // Panned sounds keep to their side.
+        let wav = stereo_wav_from_samples(&[1.0], -1.0);
+        assert_eq!(2u16.to_le_bytes(), wav[22..24]);
+        assert_eq!(i16::MAX.to_le_bytes(), wav[44..46]);
+        assert_eq!(0i16.to_le_bytes(), wav[46..48]);
+    }
+}
@@ -30,3 +30,21 @@ pub const fn as_macroquad_color(color: Color) -> macroquad::color::Color {
         color.alpha as f32 / 255.0,
     )
 }
+
+/// Returns true if `color` is near white, ignoring alpha.
+pub fn is_light(color: Color) -> bool {
+    color.red >= 230 && color.green >= 230 && color.blue >= 230
+}
+
+/// Multiplies `color` by `blend` channel-wise, scaling
+/// the resulting alpha by `opacity` (`0.0..=1.0`).
+pub fn blend(color: Color, blend: Color, opacity: f32) -> Color {
+    let channel = |a: u8, b: u8| ((a as u16 * b as u16) / 255) as u8;
+
+    Color::new(
+        channel(color.color.red, blend.color.red),
+        channel(color.color.green, blend.color.green),
+        channel(color.color.blue, blend.color.blue),
+        (channel(color.alpha, blend.alpha) as f32 * opacity.clamp(0.0, 1.0)) as u8,
+    )
+}
@@ -0,0 +1,171 @@
+//! Easing curves for animated values.
+//!
+//! Curves map linear progress (`0.0..=1.0`) to eased
+//! progress, following the common names from
+//! <https://easings.net>.
+use std::f32::consts::PI;
+
+/// An easing curve.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+
+    /// Jumps to the end once progress completes.
+    Step,
+
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+
+    /// Overshoots the start before accelerating.
+    BackIn,
+
+    /// Overshoots the end before settling.
+    BackOut,
+
+    /// Springs past the end before settling.
+    ElasticOut,
+
+    /// Bounces against the end before settling.
+    BounceOut,
+}
+
+impl Easing {
+    /// Returns the eased progress at linear progress `t`,
+    /// which is clamped to `0.0..=1.0`.
+    ///
+    /// Eased progress starts at `0.0` and ends at `1.0`,
+    /// but may overshoot in between.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        // Overshoot of the back curves.
+        const BACK: f32 = 1.70158;
+
+        match self {
+            Self::Linear => t,
+            Self::Step => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Self::QuadIn => t * t,
+            Self::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Self::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Self::CubicIn => t * t * t,
+            Self::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Self::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Self::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Self::SineOut => (t * PI / 2.0).sin(),
+            Self::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Self::ExpoIn => {
+                if t == 0.0 {
+                    0.0
+                } else {
+                    2f32.powf(10.0 * t - 10.0)
+                }
+            }
+            Self::ExpoOut => {
+                if t == 1.0 {
+                    1.0
+                } else {
+                    1.0 - 2f32.powf(-10.0 * t)
+                }
+            }
+            Self::ExpoInOut => match t {
+                0.0 => 0.0,
+                1.0 => 1.0,
+                t if t < 0.5 => 2f32.powf(20.0 * t - 10.0) / 2.0,
+                t => (2.0 - 2f32.powf(-20.0 * t + 10.0)) / 2.0,
+            },
+            Self::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Self::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Self::ElasticOut => match t {
+                0.0 => 0.0,
+                1.0 => 1.0,
+                t => 2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0,
+            },
+            Self::BounceOut => bounce_out(t),
+        }
+    }
+}
+
+/// Returns the [`Easing::BounceOut`] progress at `t`.
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_start_and_end() {
+        for easing in [
+            Easing::Linear,
+            Easing::Step,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::SineIn,
+            Easing::SineOut,
+            Easing::SineInOut,
+            Easing::ExpoIn,
+            Easing::ExpoOut,
+            Easing::ExpoInOut,
+            Easing::BackIn,
+            Easing::BackOut,
+            Easing::ElasticOut,
+            Easing::BounceOut,
+        ] {
+            assert!(easing.apply(0.0).abs() < 1e-3, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-3, "{easing:?}");
+            assert!((easing.apply(2.0) - 1.0).abs() < 1e-3, "{easing:?}");
+        }
+
+        assert_eq!(0.25, Easing::QuadIn.apply(0.5));
+        assert_eq!(0.5, Easing::QuadInOut.apply(0.5));
+    }
+}
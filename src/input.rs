@@ -0,0 +1,749 @@
+//! Named input actions bound to keys, buttons and sticks.
+//!
+//! Games ask an [`Input`] about actions (like `reset`)
+//! instead of devices, so bindings can be loaded from
+//! a config file and changed at runtime:
+//!
+//! ```text
+//! # <action> <binding>...
+//! reset key:R pad:Start
+//! move.up key:W key:Up pad:DPadUp stick:LeftY-
+//! move_to mouse:Left
+//! ```
+//!
+//! Bindings are `key:<KeyCode>`, `mouse:<MouseButton>`,
+//! `pad:<GamepadButton>` or `stick:<GamepadAxis><+|->`,
+//! named like their variants. Actions with `.up`, `.down`,
+//! `.left` and `.right` suffixes form [`Input::vector`]s.
+//!
+//! Macroquad doesn't poll gamepads, so their state is fed in
+//! through [`Input::set_gamepad_button`] and [`Input::set_gamepad_axis`]
+//! by whatever gamepad backend the platform offers, like the
+//! browser's (see [`gamepad`]). Touch
+//! [`Gestures`] are polled with the mouse, and their virtual
+//! joystick is read as the left gamepad stick. Synthetic
+//! input for tests is fed in through [`Input::inject`], and
+//! [`Sample`]s of devices are [`replay`]ed through [`Input::update_from`].
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use glam::Vec2;
+use macroquad::input::{KeyCode, MouseButton, Touch};
+
+pub mod gamepad;
+pub mod replay;
+pub mod touch;
+
+pub use touch::Gestures;
+
+/// Value above which a binding is considered held.
+const PRESS_THRESHOLD: f32 = 0.5;
+
+/// Buttons of a gamepad, named after
+/// their positions on the pad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog axes of a gamepad, from `-1.0` to `1.0`;
+/// Y axes are positive downwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+}
+
+/// A physical input an action can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    /// A keyboard key.
+    Key(KeyCode),
+
+    /// A mouse button.
+    Mouse(MouseButton),
+
+    /// A gamepad button.
+    Gamepad(GamepadButton),
+
+    /// One direction (positive if `true`) of a gamepad axis.
+    Stick(GamepadAxis, bool),
+}
+
+/// Generates name lookups for enums of unit variants.
+macro_rules! names {
+    ($name:ident, $ty:ty, [$($variant:ident),* $(,)?]) => {
+        fn $name(name: &str) -> Option<$ty> {
+            match name {
+                $(stringify!($variant) => Some(<$ty>::$variant),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+names!(
+    key_named,
+    KeyCode,
+    [
+        Space,
+        Apostrophe,
+        Comma,
+        Minus,
+        Period,
+        Slash,
+        Key0,
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        Semicolon,
+        Equal,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        LeftBracket,
+        Backslash,
+        RightBracket,
+        GraveAccent,
+        Escape,
+        Enter,
+        Tab,
+        Backspace,
+        Insert,
+        Delete,
+        Right,
+        Left,
+        Down,
+        Up,
+        PageUp,
+        PageDown,
+        Home,
+        End,
+        CapsLock,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        Kp0,
+        Kp1,
+        Kp2,
+        Kp3,
+        Kp4,
+        Kp5,
+        Kp6,
+        Kp7,
+        Kp8,
+        Kp9,
+        KpDecimal,
+        KpDivide,
+        KpMultiply,
+        KpSubtract,
+        KpAdd,
+        KpEnter,
+        LeftShift,
+        LeftControl,
+        LeftAlt,
+        LeftSuper,
+        RightShift,
+        RightControl,
+        RightAlt,
+        RightSuper,
+        Menu,
+        Back,
+    ]
+);
+
+names!(mouse_named, MouseButton, [Left, Middle, Right]);
+
+names!(
+    gamepad_button_named,
+    GamepadButton,
+    [
+        South,
+        East,
+        West,
+        North,
+        LeftBumper,
+        RightBumper,
+        LeftTrigger,
+        RightTrigger,
+        Select,
+        Start,
+        LeftStick,
+        RightStick,
+        DPadUp,
+        DPadDown,
+        DPadLeft,
+        DPadRight,
+    ]
+);
+
+names!(
+    gamepad_axis_named,
+    GamepadAxis,
+    [LeftX, LeftY, RightX, RightY]
+);
+
+impl Binding {
+    /// Parses a binding in the config format, like `key:W`.
+    pub fn parse(binding: &str) -> Option<Self> {
+        let (device, name) = binding.split_once(':')?;
+
+        match device {
+            "key" => key_named(name).map(Self::Key),
+            "mouse" => mouse_named(name).map(Self::Mouse),
+            "pad" => gamepad_button_named(name).map(Self::Gamepad),
+            "stick" => {
+                let positive = match name.chars().last()? {
+                    '+' => true,
+                    '-' => false,
+                    _ => return None,
+                };
+                gamepad_axis_named(&name[..name.len() - 1]).map(|axis| Self::Stick(axis, positive))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "key:{key:?}"),
+            Self::Mouse(button) => write!(f, "mouse:{button:?}"),
+            Self::Gamepad(button) => write!(f, "pad:{button:?}"),
+            Self::Stick(axis, positive) => {
+                write!(f, "stick:{axis:?}{}", if *positive { '+' } else { '-' })
+            }
+        }
+    }
+}
+
+/// Error in an input config.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Line (counting from one) the error is on.
+    pub line: usize,
+
+    /// Description of the error.
+    pub message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// State of the devices an [`Input`] polls, as of one update.
+#[derive(Clone, Debug, Default)]
+pub struct Sample {
+    /// Bound keys and mouse buttons held down.
+    pub held: Vec<Binding>,
+
+    /// Values of gamepad inputs which aren't at rest.
+    pub gamepad: Vec<(Binding, f32)>,
+
+    /// Mouse position, in screen pixels.
+    pub pointer: Vec2,
+
+    /// Scroll wheel movement.
+    pub wheel: f32,
+
+    /// Current touches.
+    pub touches: Vec<Touch>,
+
+    /// Screen size, in pixels.
+    pub screen: Vec2,
+
+    /// Time, in seconds, of the update.
+    pub time: f64,
+}
+
+/// Actions, their [`Binding`]s, and the state of every bound input.
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    /// Bindings of each action, by action name.
+    bindings: BTreeMap<String, Vec<Binding>>,
+
+    /// Values (`0.0..=1.0`) of bound inputs this update.
+    current: HashMap<Binding, f32>,
+
+    /// Values of bound inputs last update.
+    previous: HashMap<Binding, f32>,
+
+    /// Values of gamepad inputs fed in by the platform.
+    gamepad: HashMap<Binding, f32>,
+
+    /// Values of synthetic inputs, overriding the gamepad.
+    synthetic: HashMap<Binding, f32>,
+
+    /// Pointer position, in screen pixels.
+    pointer: Vec2,
+
+    /// Synthetic pointer position, overriding the mouse.
+    synthetic_pointer: Option<Vec2>,
+
+    /// Scroll wheel movement this update.
+    wheel: f32,
+
+    /// Synthetic scroll wheel movement for the next update.
+    synthetic_wheel: f32,
+
+    /// Touch gestures.
+    gestures: Gestures,
+
+    /// True if keyboard, mouse and touch state are polled
+    /// from macroquad; false for headless input.
+    pub poll_devices: bool,
+}
+
+impl Input {
+    /// Returns input with the bindings in `config`,
+    /// polling the keyboard and mouse.
+    pub fn from_config(config: &str) -> Result<Self, ConfigError> {
+        let mut input = Self {
+            poll_devices: true,
+            ..Default::default()
+        };
+
+        for (number, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(action) = words.next() else {
+                continue;
+            };
+
+            for word in words {
+                let binding = Binding::parse(word).ok_or_else(|| ConfigError {
+                    line: number + 1,
+                    message: format!("unknown binding `{word}`"),
+                })?;
+                input.bind(action, binding);
+            }
+        }
+
+        Ok(input)
+    }
+
+    /// Returns the bindings of every action in the
+    /// config format read by [`Self::from_config`].
+    pub fn to_config(&self) -> String {
+        let mut config = String::new();
+        for (action, bindings) in &self.bindings {
+            config.push_str(action);
+            for binding in bindings {
+                config.push_str(&format!(" {binding}"));
+            }
+            config.push('\n');
+        }
+
+        config
+    }
+
+    /// Binds `binding` to `action`, in addition
+    /// to any existing bindings.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.bindings.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Unbinds `binding` from `action`.
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Replaces every binding of `action` with `binding`.
+    pub fn rebind(&mut self, action: &str, binding: Binding) {
+        self.bindings.remove(action);
+        self.bind(action, binding);
+    }
+
+    /// Replaces every binding of `action` with `bindings`.
+    pub fn set_bindings(&mut self, action: &str, bindings: &[Binding]) {
+        self.bindings.remove(action);
+        for binding in bindings {
+            self.bind(action, *binding);
+        }
+    }
+
+    /// Returns the names of every bound action.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// Returns the bindings of `action`.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Sets the state of a gamepad `button`.
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, down: bool) {
+        self.gamepad
+            .insert(Binding::Gamepad(button), if down { 1.0 } else { 0.0 });
+    }
+
+    /// Sets the `value` (`-1.0..=1.0`) of a gamepad `axis`.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        let value = value.clamp(-1.0, 1.0);
+        self.gamepad
+            .insert(Binding::Stick(axis, true), value.max(0.0));
+        self.gamepad
+            .insert(Binding::Stick(axis, false), (-value).max(0.0));
+    }
+
+    /// Holds `binding` at (at least) `value` (`0.0..=1.0`)
+    /// from the next update, until injected again.
+    pub fn inject(&mut self, binding: Binding, value: f32) {
+        self.synthetic.insert(binding, value.clamp(0.0, 1.0));
+    }
+
+    /// Moves the pointer to `position` from the next
+    /// update, regardless of the mouse; `None` returns
+    /// control of the pointer to the mouse.
+    pub fn inject_pointer(&mut self, position: Option<Vec2>) {
+        self.synthetic_pointer = position;
+    }
+
+    /// Scrolls the wheel by `delta` during the next update.
+    pub fn inject_wheel(&mut self, delta: f32) {
+        self.synthetic_wheel += delta;
+    }
+
+    /// Stops injecting every synthetic input.
+    pub fn clear_injected(&mut self) {
+        self.synthetic.clear();
+        self.synthetic_pointer = None;
+        self.synthetic_wheel = 0.0;
+    }
+
+    /// Samples the state of every bound input;
+    /// call this once at the start of each frame.
+    pub fn update(&mut self) {
+        let sample = self
+            .poll_devices
+            .then(|| self.poll(macroquad::time::get_time()));
+        self.apply(sample.as_ref());
+    }
+
+    /// Samples the state of every bound input from `sample`
+    /// instead of polling devices; call this once at the
+    /// start of each frame in place of [`Self::update`].
+    pub fn update_from(&mut self, sample: &Sample) {
+        self.apply(Some(sample));
+    }
+
+    /// Returns the state of the keyboard, mouse and touches
+    /// at `time` seconds, as polled from macroquad, along
+    /// with the gamepad state fed in by the platform.
+    pub fn poll(&self, time: f64) -> Sample {
+        let mut held = vec![];
+        for binding in self.bindings.values().flatten() {
+            let down = match binding {
+                Binding::Key(key) => macroquad::input::is_key_down(*key),
+                Binding::Mouse(button) => macroquad::input::is_mouse_button_down(*button),
+                _ => false,
+            };
+            if down && !held.contains(binding) {
+                held.push(*binding);
+            }
+        }
+
+        let mut gamepad: Vec<(Binding, f32)> = self
+            .gamepad
+            .iter()
+            .filter(|(_, value)| **value != 0.0)
+            .map(|(binding, value)| (*binding, *value))
+            .collect();
+        gamepad.sort_by_key(|(binding, _)| binding.to_string());
+
+        Sample {
+            held,
+            gamepad,
+            pointer: Vec2::from(macroquad::input::mouse_position()),
+            wheel: macroquad::input::mouse_wheel().1,
+            touches: macroquad::input::touches(),
+            screen: Vec2::new(
+                macroquad::window::screen_width(),
+                macroquad::window::screen_height(),
+            ),
+            time,
+        }
+    }
+
+    /// Samples the state of every bound input from
+    /// `sample`, or from synthetic input alone.
+    fn apply(&mut self, sample: Option<&Sample>) {
+        self.previous = std::mem::take(&mut self.current);
+
+        if let Some(sample) = sample {
+            self.gestures
+                .update(&sample.touches, sample.time, sample.screen);
+        }
+        let stick = self.gestures.stick();
+
+        for binding in self.bindings.values().flatten() {
+            let polled = match binding {
+                Binding::Key(_) | Binding::Mouse(_) => {
+                    sample.is_some_and(|sample| sample.held.contains(binding)) as u8 as f32
+                }
+                Binding::Stick(GamepadAxis::LeftX, positive) => {
+                    (if *positive { stick.x } else { -stick.x }).max(0.0)
+                }
+                Binding::Stick(GamepadAxis::LeftY, positive) => {
+                    (if *positive { stick.y } else { -stick.y }).max(0.0)
+                }
+                _ => 0.0,
+            };
+
+            let gamepad = match sample {
+                Some(sample) => sample
+                    .gamepad
+                    .iter()
+                    .find(|(b, _)| b == binding)
+                    .map(|(_, value)| *value),
+                None => self.gamepad.get(binding).copied(),
+            };
+            let value = self
+                .synthetic
+                .get(binding)
+                .copied()
+                .or(gamepad)
+                .unwrap_or(0.0)
+                .max(polled);
+            self.current.insert(*binding, value);
+        }
+
+        self.pointer = match (self.synthetic_pointer, sample) {
+            (Some(pointer), _) => pointer,
+            (None, Some(sample)) => sample.pointer,
+            (None, None) => self.pointer,
+        };
+
+        self.wheel = std::mem::take(&mut self.synthetic_wheel);
+        if let Some(sample) = sample {
+            self.wheel += sample.wheel;
+        }
+    }
+
+    /// Returns the strongest value (`0.0..=1.0`)
+    /// of any input bound to `action`.
+    pub fn value(&self, action: &str) -> f32 {
+        Self::strongest(&self.current, self.bindings(action))
+    }
+
+    /// Returns true if `action` is held.
+    pub fn down(&self, action: &str) -> bool {
+        self.value(action) > PRESS_THRESHOLD
+    }
+
+    /// Returns true if `action` was pressed this update.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.down(action)
+            && Self::strongest(&self.previous, self.bindings(action)) <= PRESS_THRESHOLD
+    }
+
+    /// Returns true if `action` was released this update.
+    pub fn released(&self, action: &str) -> bool {
+        !self.down(action)
+            && Self::strongest(&self.previous, self.bindings(action)) > PRESS_THRESHOLD
+    }
+
+    /// Returns the direction of the `<action>.up`, `.down`,
+    /// `.left` and `.right` actions, with +Y pointing down
+    /// and a length of at most one.
+    pub fn vector(&self, action: &str) -> Vec2 {
+        let value = |direction: &str| self.value(&format!("{action}.{direction}"));
+        Vec2::new(value("right") - value("left"), value("down") - value("up")).clamp_length_max(1.0)
+    }
+
+    /// Returns the pointer position, in screen pixels.
+    pub fn pointer(&self) -> Vec2 {
+        self.pointer
+    }
+
+    /// Returns the touch gestures recognized this update.
+    pub fn gestures(&self) -> &Gestures {
+        &self.gestures
+    }
+
+    /// Returns the scroll wheel movement this update.
+    pub fn wheel(&self) -> f32 {
+        self.wheel
+    }
+
+    /// Returns a binding pressed this update, for capturing
+    /// a new binding while rebinding an action.
+    ///
+    /// Unbound keys and mouse buttons are only captured
+    /// while polling devices.
+    pub fn capture(&self) -> Option<Binding> {
+        let pressed = self
+            .synthetic
+            .iter()
+            .chain(&self.gamepad)
+            .find(|(binding, value)| {
+                **value > PRESS_THRESHOLD
+                    && self.previous.get(binding).copied().unwrap_or(0.0) <= PRESS_THRESHOLD
+            })
+            .map(|(binding, _)| *binding);
+        if pressed.is_some() || !self.poll_devices {
+            return pressed;
+        }
+
+        if let Some(key) = macroquad::input::get_last_key_pressed() {
+            return Some(Binding::Key(key));
+        }
+
+        [MouseButton::Left, MouseButton::Middle, MouseButton::Right]
+            .into_iter()
+            .find(|button| macroquad::input::is_mouse_button_pressed(*button))
+            .map(Binding::Mouse)
+    }
+
+    /// Returns the strongest value in `values` of any of `bindings`.
+    fn strongest(values: &HashMap<Binding, f32>, bindings: &[Binding]) -> f32 {
+        bindings
+            .iter()
+            .filter_map(|binding| values.get(binding))
+            .fold(0.0, |strongest, value| value.max(strongest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+        # Comments are ignored.
+        jump key:Space pad:South
+        move.up key:W stick:LeftY-
+        move.right key:D stick:LeftX+
+    ";
+
+    /// Returns headless input bound by [`CONFIG`].
+    fn input() -> Input {
+        let mut input = Input::from_config(CONFIG).unwrap();
+        input.poll_devices = false;
+        input
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let input = input();
+        assert_eq!(
+            &[
+                Binding::Key(KeyCode::Space),
+                Binding::Gamepad(GamepadButton::South)
+            ],
+            input.bindings("jump")
+        );
+
+        let config = input.to_config();
+        assert_eq!(
+            input.bindings,
+            Input::from_config(&config).unwrap().bindings
+        );
+
+        let error = Input::from_config("jump\njump key:Nope").unwrap_err();
+        assert_eq!(2, error.line);
+    }
+
+    #[test]
+    fn synthetic_presses() {
+        let mut input = input();
+
+        input.inject(Binding::Gamepad(GamepadButton::South), 1.0);
+        input.update();
+        assert!(input.pressed("jump") && input.down("jump"));
+        assert_eq!(
+            Some(Binding::Gamepad(GamepadButton::South)),
+            input.capture()
+        );
+
+        input.update();
+        assert!(!input.pressed("jump") && input.down("jump"));
+
+        input.inject(Binding::Gamepad(GamepadButton::South), 0.0);
+        input.update();
+        assert!(input.released("jump") && !input.down("jump"));
+
+        // Rebound actions ignore their old bindings.
+        input.rebind("jump", Binding::Key(KeyCode::J));
+        input.inject(Binding::Gamepad(GamepadButton::South), 1.0);
+        input.update();
+        assert!(!input.down("jump"));
+    }
+
+    #[test]
+    fn vectors_combine_bindings() {
+        let mut input = input();
+
+        input.set_gamepad_axis(GamepadAxis::LeftX, 0.5);
+        input.inject(Binding::Key(KeyCode::W), 1.0);
+        input.update();
+        assert_eq!(Vec2::new(0.5, -1.0).normalize(), input.vector("move"));
+    }
+}
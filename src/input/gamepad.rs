@@ -0,0 +1,222 @@
+//! Gamepads polled from the platform.
+//!
+//! Web builds poll the first connected gamepad, in the
+//! browser's standard layout, through the plugin in
+//! `web/gamepad.js`. Macroquad offers no gamepads on
+//! native builds, so Linux builds read the first joystick
+//! device (`/dev/input/js0`) themselves, and elsewhere
+//! gamepad bindings only follow state fed in through
+//! [`Input::set_gamepad_button`] and [`Input::set_gamepad_axis`].
+use super::{GamepadAxis, GamepadButton, Input, PRESS_THRESHOLD};
+
+/// Buttons of the standard layout, in layout order.
+const STANDARD_BUTTONS: [GamepadButton; 16] = [
+    GamepadButton::South,
+    GamepadButton::East,
+    GamepadButton::West,
+    GamepadButton::North,
+    GamepadButton::LeftBumper,
+    GamepadButton::RightBumper,
+    GamepadButton::LeftTrigger,
+    GamepadButton::RightTrigger,
+    GamepadButton::Select,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+];
+
+/// Axes of the standard layout, in layout order.
+const STANDARD_AXES: [GamepadAxis; 4] = [
+    GamepadAxis::LeftX,
+    GamepadAxis::LeftY,
+    GamepadAxis::RightX,
+    GamepadAxis::RightY,
+];
+
+/// Axis values nearer than this to zero are read as zero,
+/// so sticks resting slightly off-center don't drift.
+const STICK_DEAD_ZONE: f32 = 0.15;
+
+/// Feeds `input` the state of a gamepad in the standard layout,
+/// given the values of its buttons (`0.0..=1.0`) and axes
+/// (`-1.0..=1.0`) in layout order; missing values are released.
+pub fn set_standard(input: &mut Input, buttons: &[f32], axes: &[f32]) {
+    for (i, button) in STANDARD_BUTTONS.into_iter().enumerate() {
+        let value = buttons.get(i).copied().unwrap_or(0.0);
+        input.set_gamepad_button(button, value > PRESS_THRESHOLD);
+    }
+
+    for (i, axis) in STANDARD_AXES.into_iter().enumerate() {
+        let value = axes.get(i).copied().unwrap_or(0.0);
+        let value = if value.abs() < STICK_DEAD_ZONE {
+            0.0
+        } else {
+            value
+        };
+        input.set_gamepad_axis(axis, value);
+    }
+}
+
+/// Feeds `input` the state of the first connected gamepad,
+/// releasing every gamepad input if none is connected.
+#[cfg(target_arch = "wasm32")]
+pub fn poll(input: &mut Input) {
+    let mut buttons = [0.0; STANDARD_BUTTONS.len()];
+    let mut axes = [0.0; STANDARD_AXES.len()];
+    unsafe {
+        if web::layered_gamepad_buttons(buttons.as_mut_ptr(), buttons.len()) >= 0 {
+            web::layered_gamepad_axes(axes.as_mut_ptr(), axes.len());
+        }
+    }
+
+    set_standard(input, &buttons, &axes);
+}
+
+/// Feeds `input` the state of the first joystick device,
+/// releasing every gamepad input if none is connected.
+#[cfg(all(target_os = "linux", not(target_arch = "wasm32")))]
+pub fn poll(input: &mut Input) {
+    static READER: std::sync::Once = std::sync::Once::new();
+    READER.call_once(|| {
+        std::thread::spawn(linux::read);
+    });
+
+    let (buttons, axes) = *linux::STATE.lock().unwrap();
+    set_standard(input, &buttons, &axes);
+}
+
+/// Joysticks read through the Linux joystick API.
+#[cfg(all(target_os = "linux", not(target_arch = "wasm32")))]
+mod linux {
+    use std::{fs::File, io::Read, sync::Mutex, thread, time::Duration};
+
+    use super::{STANDARD_AXES, STANDARD_BUTTONS};
+
+    /// Joystick device to read.
+    const DEVICE: &str = "/dev/input/js0";
+
+    /// Event types, flagged with `EVENT_INIT` when
+    /// reporting the device's state on open.
+    const EVENT_BUTTON: u8 = 0x01;
+    const EVENT_AXIS: u8 = 0x02;
+    const EVENT_INIT: u8 = 0x80;
+
+    /// Standard layout indices of joystick buttons, in the
+    /// order Linux reports Xbox-style pads; the guide
+    /// button has no standard index.
+    const BUTTONS: [Option<usize>; 11] = [
+        Some(0),
+        Some(1),
+        Some(2),
+        Some(3),
+        Some(4),
+        Some(5),
+        Some(8),
+        Some(9),
+        None,
+        Some(10),
+        Some(11),
+    ];
+
+    /// Standard button and axis values of the joystick.
+    pub type State = ([f32; STANDARD_BUTTONS.len()], [f32; STANDARD_AXES.len()]);
+
+    /// Latest state of the joystick.
+    pub static STATE: Mutex<State> = Mutex::new(([0.0; 16], [0.0; 4]));
+
+    /// Reads joystick events into [`STATE`], reopening
+    /// the device whenever it's disconnected.
+    pub fn read() {
+        loop {
+            if let Ok(mut device) = File::open(DEVICE) {
+                // Events are a timestamp, value, type and number.
+                let mut event = [0; 8];
+                while device.read_exact(&mut event).is_ok() {
+                    let value = i16::from_ne_bytes([event[4], event[5]]);
+                    apply(&mut STATE.lock().unwrap(), event[6], event[7], value);
+                }
+            }
+
+            *STATE.lock().unwrap() = Default::default();
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+
+    /// Applies a joystick event to `state`, mapping
+    /// triggers to buttons and the hat to the D-pad.
+    pub fn apply(state: &mut State, kind: u8, number: u8, value: i16) {
+        let (buttons, axes) = state;
+        let axis = value as f32 / i16::MAX as f32;
+        match (kind & !EVENT_INIT, number) {
+            (EVENT_BUTTON, number) => {
+                if let Some(Some(i)) = BUTTONS.get(number as usize) {
+                    buttons[*i] = value as f32;
+                }
+            }
+            (EVENT_AXIS, 0 | 1) => axes[number as usize] = axis,
+            (EVENT_AXIS, 3 | 4) => axes[number as usize - 1] = axis,
+            (EVENT_AXIS, 2) => buttons[6] = (axis + 1.0) / 2.0,
+            (EVENT_AXIS, 5) => buttons[7] = (axis + 1.0) / 2.0,
+            (EVENT_AXIS, 6) => (buttons[14], buttons[15]) = ((-axis).max(0.0), axis.max(0.0)),
+            (EVENT_AXIS, 7) => (buttons[12], buttons[13]) = ((-axis).max(0.0), axis.max(0.0)),
+            _ => {}
+        }
+    }
+}
+
+/// Gamepad functions imported from `web/gamepad.js`.
+#[cfg(target_arch = "wasm32")]
+mod web {
+    extern "C" {
+        /// Copies the values of the first connected gamepad's
+        /// buttons into `buf`, returning the number of values
+        /// copied, or `-1` if no gamepad is connected.
+        pub fn layered_gamepad_buttons(buf: *mut f32, buf_len: usize) -> i32;
+
+        /// Copies the values of the first connected gamepad's
+        /// axes into `buf`, returning the number of values
+        /// copied, or `-1` if no gamepad is connected.
+        pub fn layered_gamepad_axes(buf: *mut f32, buf_len: usize) -> i32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_gamepads_drive_bindings() {
+        let mut input = Input::from_config("jump pad:South\nmove.right stick:LeftX+").unwrap();
+        input.poll_devices = false;
+
+        set_standard(&mut input, &[1.0], &[0.1]);
+        input.update();
+        assert!(input.pressed("jump"));
+        assert_eq!(0.0, input.value("move.right"));
+
+        set_standard(&mut input, &[], &[0.5]);
+        input.update();
+        assert!(input.released("jump"));
+        assert_eq!(0.5, input.value("move.right"));
+    }
+
+    #[cfg(all(target_os = "linux", not(target_arch = "wasm32")))]
+    #[test]
+    fn joysticks_map_to_standard_layout() {
+        let mut state = Default::default();
+        linux::apply(&mut state, 0x81, 7, 1);
+        linux::apply(&mut state, 0x02, 4, i16::MAX);
+        linux::apply(&mut state, 0x02, 5, i16::MAX);
+        linux::apply(&mut state, 0x02, 6, -i16::MAX);
+
+        let (buttons, axes) = state;
+        assert_eq!(1.0, buttons[9], "start");
+        assert_eq!(1.0, axes[3], "right stick y");
+        assert_eq!(1.0, buttons[7], "right trigger");
+        assert_eq!((1.0, 0.0), (buttons[14], buttons[15]), "d-pad left");
+    }
+}
@@ -0,0 +1,417 @@
+//! Recording and replaying of input, frame by frame.
+//!
+//! A [`Recorder`] writes each [`Frame`] a session runs, as
+//! the frame's duration followed by the device events
+//! which changed since the previous frame:
+//!
+//! ```text
+//! replay 1
+//! # <frame time> <event>...
+//! 0.016666668 screen=1280x720 @640,360
+//! 0.016666668 +key:W
+//! 0.017 -key:W ~1.5 @641,360
+//! 0.016 touch=1,Started,100,200
+//! 0.016 gamepad=stick:LeftX+,0.5
+//! ```
+//!
+//! Events are `+<binding>` and `-<binding>` for held and
+//! released keys and mouse buttons, `gamepad=<binding>,<value>`
+//! for changed gamepad inputs (`0` once at rest), `@x,y` for pointer moves,
+//! `~<delta>` for wheel movement, `screen=WxH` for resizes,
+//! and `touch=<id>,<phase>,x,y` for each current touch.
+//!
+//! [`Frames`] hands out each frame's time and input, from
+//! the devices or from a [`Replay`]. Games taking their time
+//! and input from [`Frames`] alone replay identically, given
+//! the same bindings and (for pointers) the same screen size.
+use std::{fmt::Display, io::Write};
+
+use glam::Vec2;
+use macroquad::input::{Touch, TouchPhase};
+
+use super::{Binding, Input, Sample};
+
+/// Version of the format replays are written in.
+const VERSION: u32 = 1;
+
+/// Duration and input of a frame.
+#[derive(Clone, Debug, Default)]
+pub struct Frame {
+    /// Duration, in seconds, of the frame.
+    pub frame_time: f32,
+
+    /// State of the devices during the frame.
+    pub sample: Sample,
+}
+
+/// Error in a replay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayError {
+    /// Line (counting from one) the error is on.
+    pub line: usize,
+
+    /// Description of the error.
+    pub message: String,
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Writes frames to a replay as they run.
+#[derive(Debug)]
+pub struct Recorder<W> {
+    writer: W,
+
+    /// Sample of the last recorded frame, if any.
+    previous: Option<Sample>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Returns a recorder writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            previous: None,
+        }
+    }
+
+    /// Writes `frame` to the replay, flushing it so
+    /// that replays survive crashes.
+    pub fn record(&mut self, frame: &Frame) -> std::io::Result<()> {
+        if self.previous.is_none() {
+            writeln!(self.writer, "replay {VERSION}")?;
+        }
+
+        let previous = self.previous.take().unwrap_or_default();
+        writeln!(self.writer, "{}", encode(&previous, frame))?;
+        self.previous = Some(frame.sample.clone());
+        self.writer.flush()
+    }
+}
+
+/// Frames read from a replay.
+#[derive(Clone, Debug, Default)]
+pub struct Replay {
+    frames: Vec<Frame>,
+
+    /// Index of the next frame.
+    next: usize,
+}
+
+impl Replay {
+    /// Returns the replay in `text`.
+    pub fn from_text(text: &str) -> Result<Self, ReplayError> {
+        let mut frames: Vec<Frame> = vec![];
+        let mut versioned = false;
+
+        for (number, line) in text.lines().enumerate() {
+            let error = |message: String| ReplayError {
+                line: number + 1,
+                message,
+            };
+
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next() else {
+                continue;
+            };
+
+            if !versioned {
+                match (first, words.next()) {
+                    ("replay", Some(version)) if version == VERSION.to_string() => {}
+                    ("replay", Some(version)) => {
+                        return Err(error(format!("unsupported version {version}")));
+                    }
+                    _ => return Err(error("replay doesn't start with a version".into())),
+                }
+                versioned = true;
+                continue;
+            }
+
+            let previous = frames.last().map(|frame| &frame.sample);
+            let mut frame = Frame {
+                frame_time: parse(first).map_err(error)?,
+                sample: Sample {
+                    held: previous.map(|s| s.held.clone()).unwrap_or_default(),
+                    gamepad: previous.map(|s| s.gamepad.clone()).unwrap_or_default(),
+                    pointer: previous.map_or(Vec2::ZERO, |s| s.pointer),
+                    screen: previous.map_or(Vec2::ZERO, |s| s.screen),
+                    ..Default::default()
+                },
+            };
+            for event in words {
+                decode(event, &mut frame.sample).map_err(error)?;
+            }
+            frames.push(frame);
+        }
+
+        Ok(Self { frames, next: 0 })
+    }
+
+    /// Returns the next frame of the replay, if any.
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        let frame = self.frames.get(self.next)?.clone();
+        self.next += 1;
+        Some(frame)
+    }
+
+    /// Returns the number of frames in the replay.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true if the replay has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+/// Source of each frame's time and input: the devices,
+/// until any replay runs out, optionally recording them.
+#[derive(Default)]
+pub struct Frames {
+    /// Seconds since the first frame.
+    time: f64,
+
+    replay: Option<Replay>,
+    recorder: Option<Recorder<Box<dyn Write>>>,
+
+    /// Why recording stopped, if it failed.
+    error: Option<String>,
+}
+
+impl Frames {
+    /// Takes frames from `replay` until it runs out.
+    pub fn replay(mut self, replay: Replay) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// Records every frame to `writer`.
+    pub fn record(mut self, writer: impl Write + 'static) -> Self {
+        self.recorder = Some(Recorder::new(Box::new(writer)));
+        self
+    }
+
+    /// Returns true while frames are taken from a replay.
+    pub fn is_replaying(&self) -> bool {
+        self.replay
+            .as_ref()
+            .is_some_and(|replay| replay.next < replay.len())
+    }
+
+    /// Returns the next frame, from any replay, or
+    /// else from the frame time and `input`'s devices.
+    ///
+    /// Recording stops if the frame can't be recorded,
+    /// reporting why through [`Self::take_error`].
+    pub fn next_frame(&mut self, input: &Input) -> Frame {
+        let mut frame = match self.replay.as_mut().and_then(Replay::next_frame) {
+            Some(frame) => frame,
+            None => {
+                let frame_time = macroquad::time::get_frame_time();
+                Frame {
+                    frame_time,
+                    sample: input.poll(0.0),
+                }
+            }
+        };
+        self.time += frame.frame_time as f64;
+        frame.sample.time = self.time;
+
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(error) = recorder.record(&frame) {
+                self.error = Some(format!("failed to record input: {error}"));
+                self.recorder = None;
+            }
+        }
+
+        frame
+    }
+
+    /// Returns why recording stopped, once, if it failed.
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+}
+
+/// Returns the line recording `frame`, following a
+/// frame whose devices were in the state `previous`.
+fn encode(previous: &Sample, frame: &Frame) -> String {
+    let sample = &frame.sample;
+    let mut line = frame.frame_time.to_string();
+
+    for binding in &sample.held {
+        if !previous.held.contains(binding) {
+            line.push_str(&format!(" +{binding}"));
+        }
+    }
+    for binding in &previous.held {
+        if !sample.held.contains(binding) {
+            line.push_str(&format!(" -{binding}"));
+        }
+    }
+    for (binding, value) in &sample.gamepad {
+        if !previous.gamepad.contains(&(*binding, *value)) {
+            line.push_str(&format!(" gamepad={binding},{value}"));
+        }
+    }
+    for (binding, _) in &previous.gamepad {
+        if !sample.gamepad.iter().any(|(b, _)| b == binding) {
+            line.push_str(&format!(" gamepad={binding},0"));
+        }
+    }
+    if sample.pointer != previous.pointer {
+        line.push_str(&format!(" @{},{}", sample.pointer.x, sample.pointer.y));
+    }
+    if sample.wheel != 0.0 {
+        line.push_str(&format!(" ~{}", sample.wheel));
+    }
+    if sample.screen != previous.screen {
+        line.push_str(&format!(" screen={}x{}", sample.screen.x, sample.screen.y));
+    }
+    for touch in &sample.touches {
+        let Vec2 { x, y } = touch.position;
+        line.push_str(&format!(" touch={},{:?},{x},{y}", touch.id, touch.phase));
+    }
+
+    line
+}
+
+/// Applies the recorded `event` to `sample`.
+fn decode(event: &str, sample: &mut Sample) -> Result<(), String> {
+    let binding =
+        |name: &str| Binding::parse(name).ok_or_else(|| format!("unknown binding `{name}`"));
+
+    if let Some(name) = event.strip_prefix('+') {
+        sample.held.push(binding(name)?);
+    } else if let Some(name) = event.strip_prefix('-') {
+        let released = binding(name)?;
+        sample.held.retain(|held| *held != released);
+    } else if let Some(pad) = event.strip_prefix("gamepad=") {
+        let (name, value) = pad.split_once(',').ok_or("gamepad isn't `binding,value`")?;
+        let (changed, value) = (binding(name)?, parse(value)?);
+        sample.gamepad.retain(|(held, _)| *held != changed);
+        if value != 0.0 {
+            sample.gamepad.push((changed, value));
+            sample
+                .gamepad
+                .sort_by_key(|(binding, _)| binding.to_string());
+        }
+    } else if let Some(position) = event.strip_prefix('@') {
+        let (x, y) = position.split_once(',').ok_or("pointer isn't `x,y`")?;
+        sample.pointer = Vec2::new(parse(x)?, parse(y)?);
+    } else if let Some(delta) = event.strip_prefix('~') {
+        sample.wheel = parse(delta)?;
+    } else if let Some(size) = event.strip_prefix("screen=") {
+        let (width, height) = size.split_once('x').ok_or("screen isn't `WxH`")?;
+        sample.screen = Vec2::new(parse(width)?, parse(height)?);
+    } else if let Some(touch) = event.strip_prefix("touch=") {
+        let [id, phase, x, y] = touch.split(',').collect::<Vec<_>>()[..] else {
+            return Err(format!("touch `{touch}` isn't `id,phase,x,y`"));
+        };
+        let phase = match phase {
+            "Started" => TouchPhase::Started,
+            "Stationary" => TouchPhase::Stationary,
+            "Moved" => TouchPhase::Moved,
+            "Ended" => TouchPhase::Ended,
+            "Cancelled" => TouchPhase::Cancelled,
+            _ => return Err(format!("unknown touch phase `{phase}`")),
+        };
+        sample.touches.push(Touch {
+            id: parse(id)?,
+            phase,
+            position: Vec2::new(parse(x)?, parse(y)?),
+        });
+    } else {
+        return Err(format!("unknown event `{event}`"));
+    }
+
+    Ok(())
+}
+
+/// Parses `value`, describing any error.
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::input::KeyCode;
+
+    use super::*;
+    use crate::input::GamepadAxis;
+
+    #[test]
+    fn replays_recorded_frames() {
+        let jump = Binding::Key(KeyCode::Space);
+        let stick = Binding::Stick(GamepadAxis::LeftX, true);
+        let sample = |held: Vec<Binding>, x: f32, wheel: f32| Sample {
+            held,
+            pointer: Vec2::new(x, 10.0),
+            wheel,
+            screen: Vec2::new(640.0, 480.0),
+            ..Default::default()
+        };
+        let mut frames = vec![
+            Frame {
+                frame_time: 1.0 / 60.0,
+                sample: sample(vec![], 5.0, 0.0),
+            },
+            Frame {
+                frame_time: 0.02,
+                sample: sample(vec![jump], 5.5, 1.5),
+            },
+            Frame {
+                frame_time: 0.015,
+                sample: sample(vec![], 5.5, 0.0),
+            },
+        ];
+        frames[1].sample.gamepad.push((stick, 0.25));
+        frames[1].sample.touches.push(Touch {
+            id: 7,
+            phase: TouchPhase::Started,
+            position: Vec2::new(1.0, 2.0),
+        });
+
+        let mut recorded = vec![];
+        let mut recorder = Recorder::new(&mut recorded);
+        for frame in &frames {
+            recorder.record(frame).unwrap();
+        }
+        let recorded = String::from_utf8(recorded).unwrap();
+
+        // Replayed frames drive input exactly like the recorded frames.
+        let mut replay = Replay::from_text(&recorded).unwrap();
+        let mut live = Input::from_config("jump key:Space\nmove.right stick:LeftX+").unwrap();
+        let mut replayed = live.clone();
+        let mut rerecorded = vec![];
+        let mut rerecorder = Recorder::new(&mut rerecorded);
+        for frame in &frames {
+            let replayed_frame = replay.next_frame().unwrap();
+            assert_eq!(frame.frame_time, replayed_frame.frame_time);
+
+            live.update_from(&frame.sample);
+            replayed.update_from(&replayed_frame.sample);
+            assert_eq!(live.pressed("jump"), replayed.pressed("jump"));
+            assert_eq!(live.released("jump"), replayed.released("jump"));
+            assert_eq!(live.vector("move"), replayed.vector("move"));
+            assert_eq!(live.pointer(), replayed.pointer());
+            assert_eq!(live.wheel(), replayed.wheel());
+
+            rerecorder.record(&replayed_frame).unwrap();
+        }
+        assert!(replay.next_frame().is_none());
+        assert_eq!(recorded, String::from_utf8(rerecorded).unwrap());
+
+        let error = Replay::from_text("replay 1\n0.1 +key:Nope").unwrap_err();
+        assert_eq!(2, error.line);
+    }
+}
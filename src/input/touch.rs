@@ -0,0 +1,301 @@
+//! Touch gestures for playing without a keyboard or mouse.
+//!
+//! [`Gestures`] turns raw touches into a virtual joystick,
+//! one-finger drags, two-finger pinches and taps. An
+//! [`Input`](super::Input) polling devices feeds its
+//! gestures every update, and mirrors the joystick onto
+//! the left gamepad stick so it drives the same actions.
+use glam::Vec2;
+use macroquad::{
+    color::Color,
+    input::{Touch, TouchPhase},
+};
+
+/// Distance, in pixels, a touch may wander
+/// before it stops counting as a tap.
+const TAP_SLOP: f32 = 12.0;
+
+/// Longest duration, in seconds, of a tap.
+const TAP_SECONDS: f64 = 0.3;
+
+/// Fraction of the screen's width, from its left edge,
+/// and height, from its bottom edge, in which touches
+/// start the virtual joystick.
+const JOYSTICK_ZONE: Vec2 = Vec2::new(0.35, 0.5);
+
+/// Distance, in pixels, of the joystick's
+/// knob from its base at full deflection.
+const JOYSTICK_RADIUS: f32 = 64.0;
+
+/// A touch tracked across updates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Contact {
+    id: u64,
+    start: Vec2,
+    position: Vec2,
+
+    /// Position as of the previous update.
+    last: Vec2,
+    started_at: f64,
+
+    /// True once the touch wandered too far to be a tap.
+    moved: bool,
+}
+
+/// Gestures recognized from touches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gestures {
+    /// Touch driving the virtual joystick, if any.
+    joystick: Option<Contact>,
+
+    /// Other touches, in the order they started.
+    contacts: Vec<Contact>,
+
+    /// Joystick deflection, with a length of at most one.
+    stick: Vec2,
+
+    /// Drag movement, in pixels, this update.
+    drag: Vec2,
+
+    /// Pinch scale factor this update.
+    pinch: f32,
+
+    /// Position of a tap this update.
+    tap: Option<Vec2>,
+}
+
+impl Default for Gestures {
+    fn default() -> Self {
+        Self {
+            joystick: None,
+            contacts: vec![],
+            stick: Vec2::ZERO,
+            drag: Vec2::ZERO,
+            pinch: 1.0,
+            tap: None,
+        }
+    }
+}
+
+impl Gestures {
+    /// Recognizes gestures from the current `touches`
+    /// at `time` seconds, on a `screen`-sized display.
+    pub fn update(&mut self, touches: &[Touch], time: f64, screen: Vec2) {
+        self.drag = Vec2::ZERO;
+        self.pinch = 1.0;
+        self.tap = None;
+
+        // Span and center of a two-finger pinch before this update.
+        let before = self.pinch_span();
+
+        for touch in touches {
+            match touch.phase {
+                TouchPhase::Started => {
+                    let contact = Contact {
+                        id: touch.id,
+                        start: touch.position,
+                        position: touch.position,
+                        last: touch.position,
+                        started_at: time,
+                        moved: false,
+                    };
+
+                    let zone = Vec2::new(
+                        screen.x * JOYSTICK_ZONE.x,
+                        screen.y * (1.0 - JOYSTICK_ZONE.y),
+                    );
+                    if self.joystick.is_none()
+                        && touch.position.x < zone.x
+                        && touch.position.y > zone.y
+                    {
+                        self.joystick = Some(contact);
+                    } else {
+                        self.contacts.push(contact);
+                    }
+                }
+                TouchPhase::Moved | TouchPhase::Stationary => {
+                    if let Some(contact) = self.contact_mut(touch.id) {
+                        contact.position = touch.position;
+                        contact.moved |= contact.start.distance(touch.position) > TAP_SLOP;
+                    }
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    if self
+                        .joystick
+                        .is_some_and(|joystick| joystick.id == touch.id)
+                    {
+                        self.joystick = None;
+                        continue;
+                    }
+
+                    let Some(index) = self.contacts.iter().position(|c| c.id == touch.id) else {
+                        continue;
+                    };
+                    let contact = self.contacts.remove(index);
+
+                    let lone = self.contacts.is_empty();
+                    if touch.phase == TouchPhase::Ended
+                        && lone
+                        && !contact.moved
+                        && time - contact.started_at <= TAP_SECONDS
+                    {
+                        self.tap = Some(touch.position);
+                    }
+                }
+            }
+        }
+
+        // Drag with one finger, or pinch (and drag) with two.
+        match (before, self.pinch_span()) {
+            (Some((span_before, center_before)), Some((span, center))) => {
+                if span_before > 0.0 {
+                    self.pinch = span / span_before;
+                }
+                self.drag = center - center_before;
+            }
+            _ => {
+                if let [contact] = &self.contacts[..] {
+                    if contact.moved {
+                        self.drag = contact.position - contact.last;
+                    }
+                }
+            }
+        }
+
+        self.stick = self.joystick.map_or(Vec2::ZERO, |joystick| {
+            ((joystick.position - joystick.start) / JOYSTICK_RADIUS).clamp_length_max(1.0)
+        });
+
+        for contact in &mut self.contacts {
+            contact.last = contact.position;
+        }
+    }
+
+    /// Returns the joystick's deflection, with +Y pointing
+    /// down and a length of at most one.
+    pub fn stick(&self) -> Vec2 {
+        self.stick
+    }
+
+    /// Returns the drag movement, in pixels, this update.
+    pub fn drag(&self) -> Vec2 {
+        self.drag
+    }
+
+    /// Returns the pinch scale factor this update,
+    /// greater than one while spreading fingers apart.
+    pub fn pinch(&self) -> f32 {
+        self.pinch
+    }
+
+    /// Returns the position of a tap this update.
+    pub fn tap(&self) -> Option<Vec2> {
+        self.tap
+    }
+
+    /// Draws the virtual joystick, while it's held.
+    pub fn draw(&self, color: Color) {
+        let Some(joystick) = self.joystick else {
+            return;
+        };
+
+        let knob = joystick.start + self.stick * JOYSTICK_RADIUS;
+        macroquad::shapes::draw_circle_lines(
+            joystick.start.x,
+            joystick.start.y,
+            JOYSTICK_RADIUS,
+            2.0,
+            color,
+        );
+        macroquad::shapes::draw_circle(knob.x, knob.y, JOYSTICK_RADIUS / 3.0, color);
+    }
+
+    /// Returns the tracked touch with `id`.
+    fn contact_mut(&mut self, id: u64) -> Option<&mut Contact> {
+        self.joystick
+            .iter_mut()
+            .chain(self.contacts.iter_mut())
+            .find(|contact| contact.id == id)
+    }
+
+    /// Returns the distance between, and center
+    /// of, the first two non-joystick touches.
+    fn pinch_span(&self) -> Option<(f32, Vec2)> {
+        match &self.contacts[..] {
+            [a, b, ..] => Some((
+                a.position.distance(b.position),
+                a.position.midpoint(b.position),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Vec2 = Vec2::new(800.0, 600.0);
+
+    fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> Touch {
+        Touch {
+            id,
+            phase,
+            position: Vec2::new(x, y),
+        }
+    }
+
+    #[test]
+    fn taps_and_drags() {
+        let mut gestures = Gestures::default();
+        gestures.update(&[touch(0, TouchPhase::Started, 400.0, 100.0)], 0.0, SCREEN);
+        gestures.update(&[touch(0, TouchPhase::Ended, 402.0, 101.0)], 0.1, SCREEN);
+        assert_eq!(Some(Vec2::new(402.0, 101.0)), gestures.tap());
+
+        // Touches wandering too far drag instead.
+        gestures.update(&[touch(1, TouchPhase::Started, 400.0, 100.0)], 1.0, SCREEN);
+        gestures.update(&[touch(1, TouchPhase::Moved, 450.0, 100.0)], 1.1, SCREEN);
+        assert_eq!(Vec2::new(50.0, 0.0), gestures.drag());
+        gestures.update(&[touch(1, TouchPhase::Moved, 460.0, 90.0)], 1.2, SCREEN);
+        assert_eq!(Vec2::new(10.0, -10.0), gestures.drag());
+        gestures.update(&[touch(1, TouchPhase::Ended, 460.0, 90.0)], 1.3, SCREEN);
+        assert_eq!(None, gestures.tap());
+    }
+
+    #[test]
+    fn pinches() {
+        let mut gestures = Gestures::default();
+        gestures.update(
+            &[
+                touch(0, TouchPhase::Started, 300.0, 100.0),
+                touch(1, TouchPhase::Started, 500.0, 100.0),
+            ],
+            0.0,
+            SCREEN,
+        );
+        gestures.update(
+            &[
+                touch(0, TouchPhase::Moved, 200.0, 100.0),
+                touch(1, TouchPhase::Moved, 600.0, 100.0),
+            ],
+            0.1,
+            SCREEN,
+        );
+
+        assert_eq!(2.0, gestures.pinch());
+        assert_eq!(Vec2::ZERO, gestures.drag());
+    }
+
+    #[test]
+    fn joystick() {
+        let mut gestures = Gestures::default();
+        gestures.update(&[touch(0, TouchPhase::Started, 100.0, 500.0)], 0.0, SCREEN);
+        gestures.update(&[touch(0, TouchPhase::Moved, 100.0, 300.0)], 0.1, SCREEN);
+        assert_eq!(Vec2::new(0.0, -1.0), gestures.stick());
+        assert_eq!(Vec2::ZERO, gestures.drag());
+
+        gestures.update(&[touch(0, TouchPhase::Ended, 100.0, 300.0)], 0.2, SCREEN);
+        assert_eq!(Vec2::ZERO, gestures.stick());
+        assert_eq!(None, gestures.tap());
+    }
+}
@@ -0,0 +1,452 @@
+//! Level packs loaded at runtime.
+//!
+//! A pack is a manifest (in the format documented
+//! by `assets/levels.pack`) listing levels alongside
+//! their bitmaps or plain-text maps and settings.
+//! [`LevelPack::load`] reads packs from disk on native
+//! builds, and fetches them over HTTP on the web.
+use std::{collections::HashMap, fmt::Display};
+
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::{
+    rules::{Patrol, Puzzle, ThreatKind, Threats},
+    tile,
+};
+
+/// Tile kinds a legend can assign to a
+/// symbol, and their plain-text map symbols.
+const LEGEND_KINDS: [(&str, char); 6] = [
+    ("wall", '#'),
+    ("objective", 'o'),
+    ("threat", 'x'),
+    ("spawn", '@'),
+    ("floor", '.'),
+    ("empty", '_'),
+];
+
+/// Error reading a [`LevelPack`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackError {
+    /// Line (counting from one) of the manifest the error
+    /// is on, or zero if the manifest couldn't be read.
+    pub line: usize,
+
+    /// Description of the error.
+    pub message: String,
+}
+
+impl Display for PackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PackError {}
+
+/// Per-level settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelSettings {
+    /// Opacity (`0.0..=1.0`) of plain floor tiles.
+    pub floor_opacity: f32,
+
+    /// Threats of the level.
+    pub threats: Threats,
+}
+
+impl Default for LevelSettings {
+    fn default() -> Self {
+        Self {
+            floor_opacity: 0.75,
+            threats: Threats::default(),
+        }
+    }
+}
+
+/// A level in a [`LevelPack`].
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub name: String,
+
+    /// Path of the level's bitmap or map,
+    /// relative to the pack's manifest.
+    pub source: String,
+
+    /// Tiles of the level, in the format read
+    /// by [`tile::TileMap::set_tiles_from_bitmap`].
+    pub bitmap: DynamicImage,
+
+    pub settings: LevelSettings,
+}
+
+impl Level {
+    /// Returns the level's size, in tiles.
+    pub fn size(&self) -> (usize, usize) {
+        (self.bitmap.width() as usize, self.bitmap.height() as usize)
+    }
+}
+
+/// Named, ordered list of [`Level`]s.
+#[derive(Clone, Debug)]
+pub struct LevelPack {
+    pub name: String,
+    pub levels: Vec<Level>,
+}
+
+impl LevelPack {
+    /// Reads the pack described by `manifest`, reading
+    /// the source of each level with `read`.
+    pub fn from_manifest(
+        manifest: &str,
+        mut read: impl FnMut(&str) -> Option<Vec<u8>>,
+    ) -> Result<Self, PackError> {
+        let (name, entries) = parse_manifest(manifest)?;
+
+        let mut levels = vec![];
+        for entry in entries {
+            let bytes = read(&entry.source).ok_or_else(|| PackError {
+                line: entry.line,
+                message: format!("failed to read `{}`", entry.source),
+            })?;
+            levels.push(entry.load(&bytes)?);
+        }
+
+        Ok(Self { name, levels })
+    }
+
+    /// Loads the pack whose manifest is at `path`, reading
+    /// level sources relative to the manifest.
+    ///
+    /// Native builds read from disk, while
+    /// web builds fetch over HTTP.
+    pub async fn load(path: &str) -> Result<Self, PackError> {
+        let manifest = macroquad::file::load_file(path)
+            .await
+            .map_err(|error| PackError {
+                line: 0,
+                message: format!("failed to read `{path}`: {error}"),
+            })?;
+        let manifest = String::from_utf8_lossy(&manifest);
+        let (_, entries) = parse_manifest(&manifest)?;
+
+        // Fetch every source up front, so
+        // the pack can be read synchronously.
+        let directory = path.rsplit_once('/').map_or("", |(directory, _)| directory);
+        let mut sources = HashMap::new();
+        for entry in entries {
+            if sources.contains_key(&entry.source) {
+                continue;
+            }
+
+            let source_path = if directory.is_empty() {
+                entry.source.clone()
+            } else {
+                format!("{directory}/{}", entry.source)
+            };
+            if let Ok(bytes) = macroquad::file::load_file(&source_path).await {
+                sources.insert(entry.source, bytes);
+            }
+        }
+
+        Self::from_manifest(&manifest, |source| sources.remove(source))
+    }
+
+    /// Returns the size, in tiles, fitting every level.
+    pub fn size(&self) -> (usize, usize) {
+        self.levels
+            .iter()
+            .map(Level::size)
+            .fold((0, 0), |(w, h), (width, height)| {
+                (w.max(width), h.max(height))
+            })
+    }
+}
+
+/// A level listed in a manifest.
+#[derive(Clone, Debug, Default)]
+struct Entry {
+    /// Manifest line listing the level.
+    line: usize,
+    name: String,
+    source: String,
+    size: Option<(u32, u32)>,
+
+    /// Clockwise rotation, in degrees.
+    rotation: u32,
+
+    /// Plain-text map symbols, and the
+    /// symbols of the kinds they stand in for.
+    legend: Vec<(char, char)>,
+    settings: LevelSettings,
+}
+
+impl Entry {
+    /// Returns the level read from `bytes`.
+    fn load(&self, bytes: &[u8]) -> Result<Level, PackError> {
+        let error = |message: String| PackError {
+            line: self.line,
+            message,
+        };
+
+        let mut bitmap = if self.source.ends_with(".png") {
+            image::load_from_memory(bytes)
+                .map_err(|e| error(format!("failed to decode `{}`: {e}", self.source)))?
+        } else {
+            let map = std::str::from_utf8(bytes)
+                .map_err(|e| error(format!("failed to decode `{}`: {e}", self.source)))?;
+            let map: String = map
+                .chars()
+                .map(|symbol| {
+                    self.legend
+                        .iter()
+                        .rev()
+                        .find(|(s, _)| *s == symbol)
+                        .map_or(symbol, |(_, kind)| *kind)
+                })
+                .collect();
+            tile::bitmap_from_map(&map)
+        };
+
+        bitmap = match self.rotation {
+            90 => bitmap.rotate90(),
+            180 => bitmap.rotate180(),
+            270 => bitmap.rotate270(),
+            _ => bitmap,
+        };
+
+        if let Some((width, height)) = self.size {
+            bitmap = bitmap.resize_exact(width, height, FilterType::Nearest);
+        }
+
+        if Puzzle::from_bitmap(&bitmap).spawn().is_none() {
+            return Err(error(format!("level `{}` has no spawn", self.name)));
+        }
+
+        Ok(Level {
+            name: self.name.clone(),
+            source: self.source.clone(),
+            bitmap,
+            settings: self.settings.clone(),
+        })
+    }
+}
+
+/// Returns the pack name and level entries of `manifest`.
+fn parse_manifest(manifest: &str) -> Result<(String, Vec<Entry>), PackError> {
+    let mut name = String::new();
+    let mut defaults = Entry::default();
+    let mut entries: Vec<Entry> = vec![];
+
+    for (number, line) in manifest.lines().enumerate() {
+        let line_number = number + 1;
+        let error = |message: String| PackError {
+            line: line_number,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(key) = words.next() else {
+            continue;
+        };
+        let values: Vec<&str> = words.collect();
+
+        match (key, &values[..]) {
+            ("pack", [_, ..]) => {
+                name = values.join(" ");
+                continue;
+            }
+            ("level", [level, source]) => {
+                entries.push(Entry {
+                    line: line_number,
+                    name: level.to_string(),
+                    source: source.to_string(),
+                    ..defaults.clone()
+                });
+                continue;
+            }
+            _ => {}
+        }
+
+        // Settings apply to the latest level, if any.
+        let target = entries.last_mut().unwrap_or(&mut defaults);
+
+        match (key, &values[..]) {
+            ("size", [width, height]) => {
+                let width = parse(width).map_err(error)?;
+                let height = parse(height).map_err(error)?;
+                if width == 0 || height == 0 {
+                    return Err(error(format!("empty size `{width} {height}`")));
+                }
+                target.size = Some((width, height));
+            }
+            ("rotate", [degrees]) => {
+                target.rotation = match parse(degrees).map_err(error)? {
+                    degrees @ (0 | 90 | 180 | 270) => degrees,
+                    degrees => return Err(error(format!("unsupported rotation `{degrees}`"))),
+                };
+            }
+            ("legend", [symbol, kind]) => {
+                let mut chars = symbol.chars();
+                let (Some(symbol), None) = (chars.next(), chars.next()) else {
+                    return Err(error(format!(
+                        "legend symbol `{symbol}` isn't one character"
+                    )));
+                };
+                let (_, kind) = LEGEND_KINDS
+                    .iter()
+                    .find(|(name, _)| name == kind)
+                    .ok_or_else(|| error(format!("unknown tile kind `{kind}`")))?;
+                target.legend.push((symbol, *kind));
+            }
+            ("floor_opacity", [opacity]) => {
+                target.settings.floor_opacity =
+                    parse::<f32>(opacity).map_err(error)?.clamp(0.0, 1.0);
+            }
+            ("threat", [kind, parameters @ ..]) => {
+                let parameters = parameters
+                    .iter()
+                    .map(|value| positive(value))
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(error)?;
+                target.settings.threats.kind = match (*kind, &parameters[..]) {
+                    ("ring", [seconds]) => ThreatKind::Ring { seconds: *seconds },
+                    ("beam", [length, seconds]) => ThreatKind::Beam {
+                        length: *length as f32,
+                        seconds: *seconds,
+                    },
+                    ("pulse", [radius, on_seconds, off_seconds]) => ThreatKind::Pulse {
+                        radius: *radius as f32,
+                        on_seconds: *on_seconds,
+                        off_seconds: *off_seconds,
+                    },
+                    ("spread", [seconds]) => ThreatKind::Spread { seconds: *seconds },
+                    _ => return Err(error(format!("malformed threat `{}`", line.trim()))),
+                };
+            }
+            ("patrol", [radius, seconds, waypoints @ ..]) if waypoints.len() >= 2 => {
+                let waypoints = waypoints
+                    .iter()
+                    .map(|waypoint| {
+                        let (x, y) = waypoint
+                            .split_once(',')
+                            .ok_or_else(|| format!("waypoint `{waypoint}` isn't `x,y`"))?;
+                        Ok((parse(x)?, parse(y)?))
+                    })
+                    .collect::<Result<_, String>>()
+                    .map_err(error)?;
+                target.settings.threats.patrols.push(Patrol {
+                    waypoints,
+                    radius: positive(radius).map_err(error)? as f32,
+                    seconds: positive(seconds).map_err(error)?,
+                });
+            }
+            _ => return Err(error(format!("malformed line `{}`", line.trim()))),
+        }
+    }
+
+    if entries.is_empty() {
+        return Err(PackError {
+            line: 0,
+            message: "pack has no levels".into(),
+        });
+    }
+
+    Ok((name, entries))
+}
+
+/// Parses `value`, describing any error.
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number `{value}`"))
+}
+
+/// Parses `value` as a number greater than zero, describing any error.
+fn positive(value: &str) -> Result<f64, String> {
+    match parse(value)? {
+        number if number > 0.0 => Ok(number),
+        _ => Err(format!("`{value}` isn't positive")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_levels_with_defaults_and_legends() {
+        let manifest = "
+            pack Test Pack
+            threat ring 2 # Every level is quick.
+
+            level first first.map
+            size 4 2
+
+            level second second.map
+            legend ~ wall
+            legend o floor
+            floor_opacity 0.5
+            threat pulse 1 2 3
+            patrol 1.5 0.5 0,0 2,0
+        ";
+        let pack = LevelPack::from_manifest(manifest, |source| match source {
+            "first.map" => Some(b"#o\n@x\n".to_vec()),
+            "second.map" => Some(b"~o@\n".to_vec()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert_eq!("Test Pack", pack.name);
+        assert_eq!((4, 2), pack.size());
+
+        let [first, second] = &pack.levels[..] else {
+            panic!("expected two levels");
+        };
+        assert_eq!("first", first.name);
+        assert_eq!(
+            ThreatKind::Ring { seconds: 2.0 },
+            first.settings.threats.kind
+        );
+        assert!(first.settings.threats.patrols.is_empty());
+        assert_eq!(0.75, first.settings.floor_opacity);
+        assert_eq!(0.5, second.settings.floor_opacity);
+        assert_eq!(
+            ThreatKind::Pulse {
+                radius: 1.0,
+                on_seconds: 2.0,
+                off_seconds: 3.0
+            },
+            second.settings.threats.kind
+        );
+        assert_eq!(
+            vec![(0, 0), (2, 0)],
+            second.settings.threats.patrols[0].waypoints
+        );
+        assert_eq!("#.@\n", tile::map_from_bitmap(&second.bitmap));
+    }
+
+    #[test]
+    fn reports_errors_by_line() {
+        let manifest = "level first first.map\nrotate 45\n";
+        let error = LevelPack::from_manifest(manifest, |_| Some(vec![])).unwrap_err();
+        assert_eq!(2, error.line);
+
+        let error = LevelPack::from_manifest(manifest.lines().next().unwrap(), |_| None);
+        assert_eq!(1, error.unwrap_err().line);
+
+        let error =
+            LevelPack::from_manifest("\nlevel walled walled.map", |_| Some(b"#.\n".to_vec()));
+        assert_eq!(
+            PackError {
+                line: 2,
+                message: "level `walled` has no spawn".into()
+            },
+            error.unwrap_err()
+        );
+
+        let manifest = "level first first.map\npatrol -1 0.5 0,0 2,0\n";
+        let error = LevelPack::from_manifest(manifest, |_| Some(vec![])).unwrap_err();
+        assert_eq!(2, error.line);
+    }
+}
@@ -2,24 +2,53 @@
 //! > _Note_: This documentation is auto-generated
 //! > from the project's README.md file.
 
-use color::Color;
+use asset::{Assets, Handle, LoadState};
+use audio::{
+    emitter::{panned, SIDES},
+    Audio, Bus, Clip, Emitter, Preset, Synth, Waveform,
+};
+use easing::Easing;
 use glam::Vec2;
-use image::{imageops::FilterType, DynamicImage};
+use input::{replay::Frames, Binding, Input};
+use level::{Level, LevelPack};
 use macroquad::{
-    audio::{load_sound_from_bytes, play_sound, PlaySoundParams, Sound},
+    audio::Sound,
     color::{GRAY, WHITE},
-    input::KeyCode,
     texture::{DrawTextureParams, FilterMode, Texture2D},
 };
-use tile::{Tile, TileMap, TileTexture};
+#[cfg(not(target_arch = "wasm32"))]
+use reload::AssetWatcher;
+use rules::{Cell, Event, Puzzle, Rules};
+use sprite::{Actor, Sprite};
+use state::{State, StateStack, Transition};
+use storage::{Save, Storage};
+use tile::{Collider, Cutaway, Tile, TileEditor, TileMap, TileTexture};
+use timestep::FixedTimestep;
+use transition::{Effect, ScreenTransition};
+use tween::{Animation, Tween};
 
 pub mod asset;
+pub mod audio;
 pub mod color;
+pub mod easing;
+pub mod input;
+pub mod level;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod reload;
+pub mod rules;
+pub mod sprite;
+pub mod state;
+pub mod storage;
 pub mod tile;
+pub mod timestep;
+pub mod transition;
+pub mod tween;
+
+// Collision shape of the sprite.
+const COSY_COLLIDER: Collider = Collider::Circle { radius: 0.4 };
 
-// Map size in grid units.
-const WIDTH: usize = 48;
-const HEIGHT: usize = 48;
+// Speed, in tiles per second, of the sprite at full tilt.
+const COSY_SPEED: f32 = 22.0;
 
 // Map draw layers.
 const FOREGROUND_LAYER: i8 = 0;
@@ -32,468 +61,560 @@ const BACKGROUND_TILE: &[u8] = include_bytes!("../assets/cosi-tile-empty.png");
 const SPRITE: &[u8] = include_bytes!("../assets/cosy.png");
 const SPRITE_BACK: &[u8] = include_bytes!("../assets/cosy-back.png");
 
-// Tilemaps.
-const TILEMAPS: &[&[u8]] = &[
-    include_bytes!("../assets/layer-1.png"),
-    include_bytes!("../assets/layer-2.png"),
-    include_bytes!("../assets/layer-3.png"),
-    include_bytes!("../assets/layer-4.png"),
-    include_bytes!("../assets/layer-5.png"),
+// Level pack, preferring the pack at `LEVEL_PACK`
+// over the pack built into the game.
+const LEVEL_PACK: &str = "assets/levels.pack";
+const BUILTIN_LEVEL_PACK: &str = include_str!("../assets/levels.pack");
+const BUILTIN_LEVELS: &[(&str, &[u8])] = &[
+    ("layer-1.png", include_bytes!("../assets/layer-1.png")),
+    ("layer-2.png", include_bytes!("../assets/layer-2.png")),
+    ("layer-3.png", include_bytes!("../assets/layer-3.png")),
+    ("layer-4.png", include_bytes!("../assets/layer-4.png")),
+    ("layer-5.png", include_bytes!("../assets/layer-5.png")),
 ];
 
+// Default input bindings, and the path of any local bindings.
+const INPUT_CONFIG: &str = include_str!("../assets/input.cfg");
+#[cfg(not(target_arch = "wasm32"))]
+const INPUT_CONFIG_PATH: &str = "input.cfg";
+
+// Storage of the player's settings and progress.
+const STORAGE_APP: &str = "layered";
+const SAVE_KEY: &str = "save";
+
+// Set to watch and reload assets while the game runs.
+#[cfg(not(target_arch = "wasm32"))]
+const HOT_RELOAD_VAR: &str = "LAYERED_HOT_RELOAD";
+
+// Set to the path of a replay to play back input from,
+// or of a file to record input to.
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_VAR: &str = "LAYERED_REPLAY";
+#[cfg(not(target_arch = "wasm32"))]
+const RECORD_VAR: &str = "LAYERED_RECORD";
+
+// Assets reloaded while hot reloading.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Asset {
+    FloorTile,
+    WallTile,
+    BackgroundTile,
+    Sprite,
+    Splash,
+    Levels,
+    Input,
+}
+
+// Adaptive music stems, each fading in from a
+// layer depth (counting from zero) onwards.
+const MUSIC_STEMS: &[(&str, usize)] = &[("ambience", 0), ("drone", 2)];
+
+// Duration, in seconds, of fades between music stems.
+const MUSIC_FADE: f32 = 2.0;
+
+// Clip humming from threats, and the distance
+// (in tiles) from which it can be heard.
+const THREAT_HUM: &str = "threat_hum";
+const THREAT_HEARING: f32 = 16.0;
+
 // Miscellaneous assets.
 const SPLASH: &[u8] = include_bytes!("../assets/splash.png");
 const AMBIENCE: &[u8] = include_bytes!("../assets/MooMarMouse-itchio-ambience.wav");
-const HAPPY_SOUND: &[u8] = include_bytes!("../assets/JDWasabi-itchio-confirm.wav");
-const SAD_SOUND: &[u8] = include_bytes!("../assets/JDWasabi-itchio-bubble.wav");
 
 /// Entrypoint for the infinite simulation loop.
 pub async fn simulation_loop() {
     macroquad::prelude::clear_background(color::as_macroquad_color(color::BACKGROUND));
     macroquad::prelude::next_frame().await;
 
-    // Play some nice music.
-    let sound = load_sound_from_bytes(AMBIENCE).await.unwrap();
-    play_sound(
-        &sound,
-        PlaySoundParams {
-            looped: true,
-            volume: 1.0,
-        },
-    );
+    // Load assets one per frame, showing progress
+    // over the splash screen once it's loaded.
+    let mut assets = Assets::default();
+    let handles = LayerAssets::queue(&mut assets);
+    while assets.load_next().await {
+        draw_loading_screen(&assets, &handles);
+        macroquad::prelude::next_frame().await;
+    }
+
+    // Touches are read as gestures, not as a mouse.
+    macroquad::input::simulate_mouse_with_touch(false);
 
     // Initialize state.
-    let mut state = LayerState::new().await;
+    let mut state = LayerState::new(assets, handles);
 
-    // Track system phase.
-    const PHASE_TRANSITION: f64 = 0.75;
-    let mut phase = SystemPhase::Splash(3.0);
-    let mut phase_start = macroquad::prelude::get_time();
-    let mut layer_reset = false;
-    let mut transition_tint = None;
-    let mut transition_color = color::BACKGROUND;
+    // Run the game's states, fading from the splash screen into the first layer.
+    let splash = ScreenTransition::new(
+        Effect::Fade {
+            color: color::BACKGROUND,
+        },
+        LAYER_TRANSITION,
+        LAYER_TRANSITION,
+    )
+    .from(Splash)
+    .to(Play)
+    .on_midpoint(|state| state.activate_layer(0));
+    let mut states = StateStack::new(&mut state, splash);
+
+    // Take each frame's time and input from the
+    // devices or a replay, recording them if asked.
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut frames = frames(&mut state);
+    #[cfg(target_arch = "wasm32")]
+    let mut frames = Frames::default();
 
     // Frame loop.
     loop {
-        // Track phase timing.
-        let time = macroquad::prelude::get_time();
-        let elapsed = time - phase_start;
-
-        // Track phase transition.
-        match phase {
-            SystemPhase::Splash(duration) => {
-                let remaining = duration - elapsed;
-                draw_splash_screen(&state.splash_texture);
-
-                if remaining <= 0.0 {
-                    phase =
-                        SystemPhase::LayerTransition(PHASE_TRANSITION, PHASE_TRANSITION, 0, false);
-                    phase_start = time;
-                }
-            }
-            SystemPhase::LayerTransition(out_duration, in_duration, layer, draw_current_layer) => {
-                let remaining = out_duration + in_duration - elapsed;
-
-                // Complete transition.
-                if remaining <= 0.0 {
-                    phase = SystemPhase::Layer;
-                    phase_start = time;
-                    draw_layer(&mut state);
-
-                // Activate new layer and fade in.
-                } else if remaining <= in_duration {
-                    if !layer_reset {
-                        state.activate_layer(layer);
-                        layer_reset = true;
-                    }
-
-                    draw_layer(&mut state);
-                    draw_overlay(transition_color, (remaining / in_duration) as f32);
-
-                    if let Some(tint) = transition_tint {
-                        draw_overlay(tint, ((remaining / in_duration) as f32) * 0.25);
-                    }
+        // Pick up changed assets during development.
+        #[cfg(not(target_arch = "wasm32"))]
+        state.hot_reload().await;
+
+        // Sample input once per frame.
+        #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+        input::gamepad::poll(&mut state.input);
+        let frame = frames.next_frame(&state.input);
+        state.input.update_from(&frame.sample);
+
+        let frame_time = frame.frame_time;
+        states.update(&mut state, frame_time);
+        states.draw(&mut state);
+
+        // Mix audio, muting it on request.
+        if state.input.released("mute") {
+            let muted = state.audio.is_muted();
+            state.audio.set_muted(!muted);
+            state.persist();
+        }
+        state.audio.update(frame_time);
 
-                // Fade out.
-                } else {
-                    if draw_current_layer {
-                        draw_layer(&mut state);
-                    } else {
-                        draw_splash_screen(&state.splash_texture);
-                    }
+        // Show the latest failure, if any.
+        if let Some(error) = frames.take_error() {
+            state.error = Some(error);
+        }
+        if let Some(error) = &state.error {
+            macroquad::prelude::draw_text(error, 10., 30., 20., macroquad::color::RED);
+        }
 
-                    (transition_tint, transition_color) = if state.threatened {
-                        (None, color::ACCENT_2)
-                    } else if state.remaining_objectives && draw_current_layer {
-                        (Some(color::ACCENT_3), color::BACKGROUND)
-                    } else {
-                        (None, color::BACKGROUND)
-                    };
-
-                    draw_overlay(
-                        transition_color,
-                        1.0 - ((remaining - in_duration) / out_duration) as f32,
-                    );
+        macroquad::prelude::next_frame().await;
+    }
+}
 
-                    if let Some(tint) = transition_tint {
-                        draw_overlay(
-                            tint,
-                            (1.0 - ((remaining - in_duration) / out_duration) as f32) * 0.25,
-                        );
-                    }
-                }
+/// Returns the source of each frame's time and input, replaying
+/// and recording input as asked by [`REPLAY_VAR`] and [`RECORD_VAR`],
+/// reporting failures to open either file through `state`.
+#[cfg(not(target_arch = "wasm32"))]
+fn frames(state: &mut LayerState) -> Frames {
+    let mut frames = Frames::default();
+
+    if let Some(path) = std::env::var_os(REPLAY_VAR) {
+        let replay = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| input::replay::Replay::from_text(&text).map_err(|e| e.to_string()));
+        match replay {
+            Ok(replay) => frames = frames.replay(replay),
+            Err(error) => {
+                state.error = Some(format!(
+                    "failed to load replay {}: {error}",
+                    path.to_string_lossy()
+                ));
             }
-            SystemPhase::Layer => {
-                if let Some(next_layer) = draw_layer(&mut state) {
-                    let out_duration = if state.threatened {
-                        play_sound(
-                            &state.sad_sound,
-                            PlaySoundParams {
-                                looped: false,
-                                volume: 1.0,
-                            },
-                        );
-                        PHASE_TRANSITION / 4.0
-                    } else {
-                        PHASE_TRANSITION / 2.0
-                    };
-
-                    phase = SystemPhase::LayerTransition(
-                        out_duration,
-                        PHASE_TRANSITION,
-                        next_layer,
-                        true,
-                    );
+        }
+    }
 
-                    phase_start = time;
-                    layer_reset = false;
-                }
+    if let Some(path) = std::env::var_os(RECORD_VAR) {
+        match std::fs::File::create(&path) {
+            Ok(file) => frames = frames.record(file),
+            Err(error) => {
+                state.error = Some(format!(
+                    "failed to record to {}: {error}",
+                    path.to_string_lossy()
+                ));
             }
         }
-
-        macroquad::prelude::next_frame().await;
     }
+
+    frames
 }
 
-/// Draws a layer.
-fn draw_layer(state: &mut LayerState) -> Option<usize> {
-    // Toggle debugger.
-    if macroquad::prelude::is_key_released(KeyCode::E) {
-        state.map.draw_debug_info = !state.map.draw_debug_info;
+// Duration, in seconds, of each simulation step.
+const SIMULATION_STEP: f32 = 1.0 / 60.0;
+
+// Duration, in seconds, of layer transitions.
+const LAYER_TRANSITION: f32 = 0.75;
+
+/// Splash screen shown at startup.
+struct Splash;
+
+impl State<LayerState> for Splash {
+    fn update(&mut self, _: &mut LayerState, _: f32) -> Transition<LayerState> {
+        Transition::None
     }
 
-    let mut next_layer = None;
+    fn draw(&mut self, state: &mut LayerState) {
+        draw_splash_screen(&state.splash_texture);
+    }
+}
 
-    // Reset world state on objective completion or a keypress.
-    if !state.remaining_objectives
-        || macroquad::prelude::is_key_released(KeyCode::R)
-        || state.threatened
-    {
-        if state.remaining_objectives {
-            next_layer = Some(state.active_tilemap_index);
+/// Active, playable layer.
+struct Play;
+
+impl State<LayerState> for Play {
+    fn update(&mut self, state: &mut LayerState, frame_time: f32) -> Transition<LayerState> {
+        let Some(next_layer) = update_layer(state, frame_time) else {
+            return Transition::None;
+        };
 
-        // If all objectives are cleared, cycle to the next tilemap.
+        // Flash red on threats, or tint incomplete layers.
+        let (effect, out_duration) = if state.rules.is_threatened() {
+            let color = color::ACCENT_2;
+            (Effect::Fade { color }, LAYER_TRANSITION / 4.0)
+        } else if !state.rules.is_cleared() {
+            let effect = Effect::Flash {
+                color: color::BACKGROUND,
+                tint: color::ACCENT_3,
+                tint_opacity: 0.25,
+            };
+            (effect, LAYER_TRANSITION / 2.0)
         } else {
-            next_layer = Some((state.active_tilemap_index + 1) % state.tilemaps.len());
+            let color = color::BACKGROUND;
+            (Effect::Fade { color }, LAYER_TRANSITION / 2.0)
+        };
+
+        Transition::replace(
+            ScreenTransition::new(effect, out_duration, LAYER_TRANSITION)
+                .from(Play)
+                .to(Play)
+                .on_midpoint(move |state| state.activate_layer(next_layer)),
+        )
+    }
+
+    fn exit(&mut self, state: &mut LayerState) {
+        state.audio.silence_emitters();
+        if state.rules.is_threatened() {
+            state.audio.play("sad");
+        } else if state.rules.is_cleared() {
+            state.complete_level();
         }
     }
 
-    // Reset active tile colors.
-    state.map.set_tiles_from_bitmap(
-        &state.tilemaps[state.active_tilemap_index],
-        state.active_layer,
-        state.wall_tile_texture.clone(),
-        state.floor_tile_texture.clone(),
-        0.75,
-    );
+    fn draw(&mut self, state: &mut LayerState) {
+        draw_layer(state);
+    }
+}
+
+/// Updates the active layer for a frame lasting `frame_time`
+/// seconds, returning the index of the next layer to transition to.
+///
+/// The viewport and editor follow input every frame,
+/// while the layer itself runs in fixed [`step_layer`]s.
+fn update_layer(state: &mut LayerState, frame_time: f32) -> Option<usize> {
+    // Toggle debugger.
+    if state.input.released("toggle_debug") {
+        state.map.draw_debug_info = !state.map.draw_debug_info;
+        state.persist();
+    }
 
     // Calculate mouse delta.
-    let new_mouse_pos = Vec2::from(macroquad::prelude::mouse_position());
+    let new_mouse_pos = state.input.pointer();
     let mouse_delta = state.mouse_pos - new_mouse_pos;
     state.mouse_pos = new_mouse_pos;
 
     // Pan the viewport.
-    if macroquad::prelude::is_key_down(KeyCode::Space) {
+    if state.input.down("pan") {
         state.map.viewport_offset -= mouse_delta;
     }
+    state.map.viewport_offset += state.input.gestures().drag();
+
+    // Scale the viewport, easing towards wheel zooms
+    // and following pinches as they happen.
+    let pinch = state.input.gestures().pinch();
+    if pinch != 1.0 {
+        let scale = (state.map.viewport_scale * pinch).clamp(1.0f32, 5.0f32);
+        state.zoom = zoom_tween(scale, scale);
+    }
+    let mouse_dy = state.input.wheel();
+    if mouse_dy != 0.0 {
+        let (_, target) = state.zoom.endpoints();
+        let target = (target + 0.01 * mouse_dy).clamp(1.0f32, 5.0f32);
+        state.zoom.retarget(target);
+    }
+    state.zoom.advance(&mut state.map, frame_time);
+
+    // Toggle the tile editor.
+    if state.input.released("toggle_editor") {
+        state.editing = !state.editing;
+        state.editor.active_layer = state.active_layer;
+        if state.editing {
+            state.audio.silence_emitters();
+        }
+    }
 
-    // Scale the viewport.
-    let mouse_dy = macroquad::prelude::mouse_wheel().1;
-    let scale_change = 0.01 * mouse_dy;
-    let new_scale = state.map.viewport_scale + scale_change;
-    state.map.viewport_scale = new_scale.clamp(1.0f32, 5.0f32);
-
-    // Configure position translation.
-    let last_pos = state.cosy_pos;
-    let cosy_pos = Vec2::from(state.cosy_pos);
-
-    // Velocity between frames should be stable.
-    let frame_time = macroquad::prelude::get_frame_time();
-    let velocity = 22.0 * frame_time;
-
-    // Calculate the _target_ position for the sprite.
-    let mut target_pos = cosy_pos;
+    // While editing, pause the layer and draw the editor.
+    if state.editing {
+        state.reset_tiles();
+        state.editor.update(&mut state.map, &state.input);
+
+        // Keep edits across tile resets in the edited layer's level,
+        // unless it shares the active layer's level, and play by them.
+        let layer = state.editor.active_layer;
+        let index = usize::try_from(layer)
+            .ok()
+            .map(|layer| layer % state.levels.len())
+            .filter(|index| layer == state.active_layer || *index != state.active_tilemap_index)?;
+        let bitmap = state.map.bitmap_from_tiles(layer, &state.wall_tile_texture);
+        if layer == state.active_layer {
+            state.rules.set_puzzle(Puzzle::from_bitmap(&bitmap));
+        }
+        state.levels[index].bitmap = bitmap;
+
+        // Save the edited layer.
+        #[cfg(not(target_arch = "wasm32"))]
+        if state.input.down("edit_modifier") && state.input.pressed("edit_save") {
+            let path = format!("layer-{}.map", index + 1);
+            if let Err(error) =
+                state
+                    .editor
+                    .save(&state.map, path.as_ref(), &state.wall_tile_texture)
+            {
+                state.error = Some(format!("failed to save {path}: {error}"));
+            }
+        }
 
-    // If the mouse is held, move the sprite towards the cursor.
-    if macroquad::prelude::is_mouse_button_down(miniquad::MouseButton::Left) {
-        let mouse_pos = state.mouse_pos;
-        target_pos = state
-            .map
-            .view_to_grid(mouse_pos.x, mouse_pos.y, state.active_layer);
+        return None;
+    }
 
-    // Otherwise, move the sprite "towards" any
-    // held WASD keys, relative to screen-space.
-    } else {
-        if macroquad::prelude::is_key_down(KeyCode::W) {
-            target_pos.x -= 1.0;
-            target_pos.y -= 1.0;
-        } else if macroquad::prelude::is_key_down(KeyCode::S) {
-            target_pos.x += 1.0;
-            target_pos.y += 1.0;
-        }
+    // Remember where the screen was last tapped.
+    if let Some(tap) = state.input.gestures().tap() {
+        state.tap_target = Some(state.map.view_to_grid(tap.x, tap.y, state.active_layer));
+    }
 
-        if macroquad::prelude::is_key_down(KeyCode::A) {
-            target_pos.x -= 1.0;
-            target_pos.y += 1.0;
-        } else if macroquad::prelude::is_key_down(KeyCode::D) {
-            target_pos.x += 1.0;
-            target_pos.y -= 1.0;
+    // Run the layer in fixed steps, until it ends.
+    let mut next_layer = None;
+    for _ in 0..state.timestep.advance(frame_time) {
+        next_layer = step_layer(state, state.timestep.step());
+        if next_layer.is_some() {
+            break;
         }
     }
 
-    // Perform a linear interpolation if the sprite should move.
-    let dx = target_pos.x - cosy_pos.x;
-    let dy = target_pos.y - cosy_pos.y;
-    let distance = (dx * dx + dy * dy).sqrt();
-
-    // Only perform a move if the sprite would move
-    // one unit or more. Without this check, the
-    // sprite will appear to move erratically when
-    // the target location is _very_ close to the sprite.
-    if distance.abs() >= 1.0 {
-        // Interpolate by a constant velocity so
-        // that movement doesn't slow down when the
-        // sprite is close to the target.
-        let lerp_step = velocity / distance;
-        state.cosy_pos.0 = cosy_pos.x + (target_pos.x - cosy_pos.x) * lerp_step;
-        state.cosy_pos.1 = cosy_pos.y + (target_pos.y - cosy_pos.y) * lerp_step;
-
-        // Show the back of the sprite during "upwards" motion.
-        state.cosy_sprite = if cosy_pos.y > target_pos.y {
-            SPRITE_BACK
-        } else {
-            SPRITE
-        };
-
-        // Flip the sprite during "rightwards" motion.
-        state.cosy_flip = cosy_pos.x < target_pos.x;
+    // Reset the layer on a keypress.
+    if state.input.released("reset") {
+        next_layer = next_layer.or(Some(state.active_tilemap_index));
     }
 
-    // Only permit moves which keep the avatar on the field.
-    if state.cosy_pos.0 < 0.0
-        || state.cosy_pos.0 > (WIDTH - 1) as f32
-        || state.cosy_pos.1 < 0.0
-        || state.cosy_pos.1 > (HEIGHT - 1) as f32
-    {
-        state.cosy_pos = last_pos;
+    next_layer
+}
 
-    // Check for wall collisions.
-    } else {
-        let x = state.cosy_pos.0 as usize;
-        let y = state.cosy_pos.1 as usize;
-        let neighbor_tiles = vec![(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+/// Runs the active layer for a step lasting `step` seconds,
+/// returning the index of the next layer to transition to.
+fn step_layer(state: &mut LayerState, step: f32) -> Option<usize> {
+    state.time += step as f64;
+    state.cosy_previous = state.cosy.position;
 
-        for (x, y) in neighbor_tiles {
-            if let Some(Tile::Filled { texture, .. }) = state.map.get_tile(x, y, state.active_layer)
-            {
-                if texture == &state.wall_tile_texture {
-                    state.cosy_pos = last_pos;
-                    break;
-                }
-            }
-        }
-    };
+    let mut next_layer = None;
 
-    // Highlight completed objective tiles.
-    for line in &state.completed_objective_lines {
-        for (x, y) in line.iter() {
-            state.map.flood_fill_tiles(
-                *x,
-                *y,
-                state.active_layer,
-                color::ACCENT_1,
-                color::ACCENT_3,
-            );
-
-            if let Some(Tile::Filled { blend_color, .. }) =
-                state.map.get_tile(*x, *y, state.active_layer)
-            {
-                *blend_color = Some(color::ACCENT_3);
-            };
-        }
+    // If all objectives are cleared, cycle to the next
+    // tilemap, or reset the tilemap on a threat.
+    if state.rules.is_cleared() {
+        next_layer = Some((state.active_tilemap_index + 1) % state.levels.len());
+    } else if state.rules.is_threatened() {
+        next_layer = Some(state.active_tilemap_index);
     }
 
-    // Check if any objectives are remaining on the field.
-    state.remaining_objectives = false;
-    for x in 0..WIDTH {
-        for y in 0..HEIGHT {
-            if state
+    // Reset active tile colors.
+    state.reset_tiles();
+
+    // Walls block the sprite, and muffle what it hears.
+    let wall_tile_texture = &state.wall_tile_texture;
+    let is_wall =
+        |tile: &Tile| matches!(tile, Tile::Filled { texture, .. } if texture == wall_tile_texture);
+
+    // Move the sprite towards the held pointer, along any held
+    // movement (relative to screen-space), or towards the last tap.
+    let cosy_pos = state.cosy.position;
+    let movement = state.input.vector("move");
+    let target = if state.input.down("move_to") {
+        let mouse_pos = state.mouse_pos;
+        state.tap_target = None;
+        Some(
+            state
                 .map
-                .tile_has_color(x, y, state.active_layer, color::ACCENT_1)
-            {
-                state.remaining_objectives = true;
-                break;
-            }
-        }
+                .view_to_grid(mouse_pos.x, mouse_pos.y, state.active_layer),
+        )
+    } else if movement != Vec2::ZERO {
+        state.tap_target = None;
+        None
+    } else {
+        state.tap_target
+    };
+    if target.is_some_and(|target| target.distance(cosy_pos) < 1.0) {
+        state.tap_target = None;
     }
 
-    // Highlight tiles between the sprite and the objective.
-    let mut unbroken_line = true;
-    let line_points = state.map.tiles_on_line_between(
-        state.checkpoint.0,
-        state.checkpoint.1,
-        state.cosy_pos.0,
-        state.cosy_pos.1,
-    );
-    for (x, y) in line_points.iter().take(line_points.len() - 1).skip(1) {
-        if let Some(Tile::Filled {
-            texture,
-            blend_color,
-            ..
-        }) = state.map.get_tile(*x, *y, state.active_layer)
-        {
-            if texture == &state.wall_tile_texture {
-                unbroken_line = false;
-                break;
-            }
+    let motion = sprite_motion(cosy_pos, target, movement, step);
+    if motion != Vec2::ZERO {
+        // Slide along walls and the edges of the field.
+        let sweep = state
+            .map
+            .sweep(COSY_COLLIDER, cosy_pos, motion, state.active_layer, is_wall);
+        state.cosy.position = sweep.position;
 
-            *blend_color = Some(color::ACCENT_3);
-        };
+        // Face the sprite the way it's heading, even while blocked.
+        state.cosy.face_towards(target.unwrap_or(cosy_pos + motion));
     }
+    state.cosy.set_moving(motion != Vec2::ZERO);
+    state.cosy.update(step);
 
-    // Detect if the sprite is on an objective tile.
-    let mut on_objective = false;
-    if state.map.tile_has_color(
-        state.cosy_pos.0 as usize,
-        state.cosy_pos.1 as usize,
-        state.active_layer,
-        color::ACCENT_1,
-    ) {
-        on_objective = true;
-    }
-
-    // Mark the objective complete and record a checkpoint.
-    if unbroken_line && on_objective {
-        state.completed_objective_lines.push(line_points);
-        state.checkpoint = (state.cosy_pos.0, state.cosy_pos.1);
-        play_sound(
-            &state.happy_sound,
-            PlaySoundParams {
-                looped: false,
-                volume: 1.0,
-            },
-        );
+    // Play the layer's rules where the sprite now stands.
+    for event in state.rules.step(state.cosy.position, step as f64) {
+        if let Event::ObjectiveCompleted(_) = event {
+            state.audio.play("happy");
+        }
     }
 
-    // Update threat radii.
-    let time = macroquad::prelude::get_time();
-    if time > state.threat_timestamp + state.threat_interval_seconds {
-        state.threat_timestamp = time;
-        state.threat_radius += 1;
-        state.threat_radius %= state.max_threat_radius as i32;
-    }
+    // Highlight completed objectives, and the tiles
+    // between the sprite and the last objective.
+    let layer = state.active_layer;
+    let rules = &state.rules;
+    let completed = rules
+        .puzzle()
+        .cells_of(Cell::Objective)
+        .filter(|(x, y)| rules.is_completed(*x, *y));
+    let lines = rules.completed_lines().iter().flatten().copied();
+    let sight_line = rules.sight_line(state.cosy.position);
+    tint_tiles(
+        &mut state.map,
+        completed.chain(lines),
+        layer,
+        color::ACCENT_3,
+    );
+    tint_tiles(&mut state.map, sight_line, layer, color::ACCENT_3);
+
+    // Draw threat radii.
+    tint_tiles(
+        &mut state.map,
+        rules.threat_front().iter().copied(),
+        layer,
+        color::ACCENT_2,
+    );
 
-    // Identify threats.
-    let mut rad_origins = vec![];
-    let mut rads = vec![];
-    for x in 0..WIDTH {
-        for y in 0..HEIGHT {
-            if state
+    // Hum from every threat, as heard by the sprite through the walls.
+    let hums: Vec<_> = rules
+        .threat_sources()
+        .into_iter()
+        .map(|source| {
+            let emitter = Emitter::new(THREAT_HUM, source, THREAT_HEARING);
+            state
                 .map
-                .tile_has_color(x, y, state.active_layer, color::ACCENT_2)
-            {
-                rad_origins.push((x, y));
-                rads.push(state.map.tiles_on_radius(
-                    x as isize,
-                    y as isize,
-                    3 + state.threat_radius as isize,
-                ));
-            }
-        }
-    }
+                .hear(&emitter, state.cosy.position, state.active_layer, is_wall)
+        })
+        .collect();
+    state
+        .audio
+        .play_emitters(hums.into_iter().map(|spatial| (THREAT_HUM, spatial)));
 
-    // Draw threat radii.
-    for (rad_origin, rad_points) in rad_origins.into_iter().zip(rads.into_iter()) {
-        for (x, y) in rad_points {
-            if let Some(Tile::Filled { texture, .. }) = state.map.get_tile(x, y, state.active_layer)
-            {
-                if texture == &state.floor_tile_texture {
-                    // TODO: Cheesy ray-tracing for occlusion on the wavefront.
-                    let mut occluded = false;
-                    let line_points = state.map.tiles_on_line_between(
-                        rad_origin.0 as f32,
-                        rad_origin.1 as f32,
-                        x as f32,
-                        y as f32,
-                    );
-                    for (x, y) in line_points.iter().take(line_points.len() - 1).skip(1) {
-                        if let Some(Tile::Filled { texture, .. }) =
-                            state.map.get_tile(*x, *y, state.active_layer)
-                        {
-                            if texture == &state.wall_tile_texture {
-                                occluded = true;
-                                break;
-                            }
-                        };
-                    }
+    next_layer
+}
 
-                    // If no occlusion is detected, draw the wavefront.
-                    if !occluded {
-                        if let Some(Tile::Filled { blend_color, .. }) =
-                            state.map.get_tile(x, y, state.active_layer)
-                        {
-                            *blend_color = Some(color::ACCENT_2);
-                        }
-                    }
-                }
-            };
+/// Returns how far the sprite at `position` moves in `step`
+/// seconds: at full speed towards any `target`, or else along
+/// the screen-space `movement`, as fast as it's long.
+///
+/// The sprite only heads for targets a tile or more away;
+/// otherwise it would jitter around targets very close by.
+fn sprite_motion(position: Vec2, target: Option<Vec2>, movement: Vec2, step: f32) -> Vec2 {
+    let velocity = COSY_SPEED * step;
+
+    match target {
+        Some(target) if target.distance(position) >= 1.0 => {
+            (target - position).normalize() * velocity
+        }
+        Some(_) => Vec2::ZERO,
+        None => {
+            // Turn screen-space movement into grid-space.
+            let direction = Vec2::new(movement.x + movement.y, movement.y - movement.x);
+            direction.normalize_or_zero() * velocity * movement.length().min(1.0)
         }
     }
+}
 
-    // Detect if the sprite is on a threat tile.
-    if state.map.tile_has_color(
-        state.cosy_pos.0 as usize,
-        state.cosy_pos.1 as usize,
-        state.active_layer,
-        color::ACCENT_2,
-    ) {
-        state.threatened = true;
+/// Blends every one of `tiles` in `layer` of `map` with `color`.
+fn tint_tiles(
+    map: &mut TileMap,
+    tiles: impl IntoIterator<Item = (usize, usize)>,
+    layer: i8,
+    color: color::Color,
+) {
+    for (x, y) in tiles {
+        if let Some(Tile::Filled { blend_color, .. }) = map.get_tile(x, y, layer) {
+            *blend_color = Some(color);
+        }
     }
+}
 
+/// Draws the active layer.
+fn draw_layer(state: &mut LayerState) {
     // Redraw the map.
     macroquad::prelude::set_default_camera();
     state.map.draw_tiles();
 
-    // Draw the cosi sprite onto the active layer.
-    let sprite: Texture2D = Texture2D::from_file_with_format(state.cosy_sprite, None);
-    sprite.set_filter(FilterMode::Nearest);
-    state.map.draw_sprite(
-        &sprite,
-        state.cosy_pos.0,
-        state.cosy_pos.1,
-        0.5,
-        state.active_layer,
-        state.cosy_flip,
-    );
+    // While editing, draw the editor over the paused layer.
+    if state.editing {
+        state.editor.draw(&state.map);
+        return;
+    }
+
+    // Draw the cosi sprite onto the active layer,
+    // between its positions in the last two steps.
+    let position = state.cosy.position;
+    let alpha = state.timestep.alpha();
+    state.cosy.position = state.cosy_previous.lerp(position, alpha);
+    state.map.draw_actor(&state.cosy);
+    state.cosy.position = position;
 
     // Draw controls
     let screen_height = macroquad::prelude::screen_height();
-    macroquad::prelude::draw_text("[space + cursor]", 10., screen_height - 80., 20., GRAY);
-    macroquad::prelude::draw_text("[w a s d]: mvmnt", 10., screen_height - 60., 20., GRAY);
-    macroquad::prelude::draw_text("[r]: reset layer", 10., screen_height - 40., 20., GRAY);
-    macroquad::prelude::draw_text("[e]: toggle dbgr", 10., screen_height - 20., 20., GRAY);
+    let level = &state.levels[state.active_tilemap_index];
+    if let Some(best) = state.save.best_times.get(&level.name) {
+        let best = format!("best: {best:.1}s");
+        macroquad::prelude::draw_text(&best, 10., screen_height - 120., 20., GRAY);
+    }
+    let hint = |actions: &[&str]| binding_hint(&state.input, actions);
+    let controls = [
+        format!("[{}]: edit tile", hint(&["toggle_editor"])),
+        format!("[{} + cursor]", hint(&["pan"])),
+        format!(
+            "[{}]: mvmnt",
+            hint(&["move.up", "move.left", "move.down", "move.right"])
+        ),
+        format!("[{}]: reset layer", hint(&["reset"])),
+        format!("[{}]: toggle dbgr", hint(&["toggle_debug"])),
+    ];
+    for (i, text) in controls.iter().enumerate() {
+        let y = screen_height - 20. * (controls.len() - i) as f32;
+        macroquad::prelude::draw_text(text, 10., y, 20., GRAY);
+    }
 
-    next_layer
+    // Draw the touch joystick.
+    state.input.gestures().draw(GRAY);
+}
+
+/// Returns the first binding of each of `actions`, preferring
+/// keys, as on-screen help like `w a s d`.
+fn binding_hint(input: &Input, actions: &[&str]) -> String {
+    let names: Vec<String> = actions
+        .iter()
+        .filter_map(|action| {
+            let bindings = input.bindings(action);
+            bindings
+                .iter()
+                .find_map(|binding| match binding {
+                    Binding::Key(key) => Some(format!("{key:?}").to_lowercase()),
+                    _ => None,
+                })
+                .or_else(|| bindings.first().map(ToString::to_string))
+        })
+        .collect();
+    names.join(" ")
 }
 
 /// Draws `splash_texture` as a full-screen, centered image.
@@ -533,29 +654,123 @@ fn draw_splash_screen(splash_texture: &Texture2D) {
     macroquad::prelude::draw_texture_ex(splash_texture, x, y, WHITE, draw_params);
 }
 
-/// Draws a full-screen rectangle with `color` and `opacity`.
-fn draw_overlay(mut color: Color, opacity: f32) {
+/// Draws the splash screen (once it's loaded) above
+/// a bar showing the progress of loading `assets`.
+fn draw_loading_screen(assets: &Assets, handles: &LayerAssets) {
+    match assets.get(&handles.splash) {
+        Some(splash_texture) => draw_splash_screen(splash_texture),
+        None => macroquad::prelude::clear_background(color::as_macroquad_color(color::BACKGROUND)),
+    }
+
     let screen_width = macroquad::prelude::screen_width();
     let screen_height = macroquad::prelude::screen_height();
-    color.alpha = (opacity * 255.) as u8;
-    macroquad::prelude::draw_rectangle(
-        0.0,
-        0.0,
-        screen_width,
-        screen_height,
-        color::as_macroquad_color(color),
+    let width = screen_width * 0.4;
+    let x = (screen_width - width) / 2.0;
+    let y = screen_height - 40.0;
+
+    macroquad::shapes::draw_rectangle(x, y, width, 8.0, GRAY);
+    macroquad::shapes::draw_rectangle(
+        x,
+        y,
+        width * assets.progress(),
+        8.0,
+        color::as_macroquad_color(color::ACCENT_3),
     );
 }
-/// TODO:
-enum SystemPhase {
-    /// Splash screen, with duration in seconds.
-    Splash(f64),
 
-    /// Transition to the layer with `index`.
-    LayerTransition(f64, f64, usize, bool),
+/// Returns a tween easing the viewport's scale from `from` to `to`.
+fn zoom_tween(from: f32, to: f32) -> Tween<TileMap, f32> {
+    Tween::new(from, to, 0.2)
+        .easing(Easing::CubicOut)
+        .on_update(|map: &mut TileMap, scale| map.viewport_scale = scale)
+}
+
+/// Returns the synth of the chime played
+/// on completing an objective.
+fn happy_sound() -> Synth {
+    Synth::preset(Preset::Pickup, 7)
+}
+
+/// Returns the synth of the crunch played
+/// on being caught by a threat.
+fn sad_sound() -> Synth {
+    Synth::preset(Preset::Hit, 3)
+}
 
-    /// Active layer.
-    Layer,
+/// Returns the synth of the low, wavering hum
+/// of threats, which loops seamlessly.
+fn threat_hum() -> Synth {
+    Synth {
+        waveform: Waveform::Sawtooth,
+        sustain: 1.0,
+        decay: 0.0,
+        frequency: 55.0,
+        vibrato_depth: 0.02,
+        vibrato_speed: 2.0,
+        low_pass: 0.9,
+        ..Default::default()
+    }
+}
+
+/// Returns the synth of the soft, wavering drone
+/// underscoring deeper layers, which loops seamlessly.
+fn drone() -> Synth {
+    Synth {
+        waveform: Waveform::Sine,
+        sustain: 4.0,
+        decay: 0.0,
+        frequency: 110.0,
+        vibrato_depth: 0.01,
+        vibrato_speed: 0.5,
+        low_pass: 0.5,
+        volume: 0.3,
+        ..Default::default()
+    }
+}
+
+/// Handles to the assets of [`LayerState`].
+struct LayerAssets {
+    splash: Handle<Texture2D>,
+    floor_tile: Handle<Texture2D>,
+    wall_tile: Handle<Texture2D>,
+    background_tile: Handle<Texture2D>,
+    sprite: Handle<Texture2D>,
+    sprite_back: Handle<Texture2D>,
+    ambience: Handle<Sound>,
+    drone: Handle<Sound>,
+    happy_sound: Handle<Sound>,
+    sad_sound: Handle<Sound>,
+    threat_hums: [Handle<Sound>; 2],
+    pack: Handle<LevelPack>,
+}
+
+impl LayerAssets {
+    /// Queues every asset to load in `assets`,
+    /// starting with the splash screen.
+    fn queue(assets: &mut Assets) -> Self {
+        Self {
+            splash: assets.load_bytes("assets/splash.png", SPLASH),
+            floor_tile: assets.load_bytes("assets/cosi-tile-light.png", FLOOR_TILE),
+            wall_tile: assets.load_bytes("assets/cosi-tile-dark.png", WALL_TILE),
+            background_tile: assets.load_bytes("assets/cosi-tile-empty.png", BACKGROUND_TILE),
+            sprite: assets.load_bytes("assets/cosy.png", SPRITE),
+            sprite_back: assets.load_bytes("assets/cosy-back.png", SPRITE_BACK),
+            ambience: assets.load_bytes("assets/MooMarMouse-itchio-ambience.wav", AMBIENCE),
+            drone: assets.load_synth("drone", drone()),
+            happy_sound: assets.load_synth("happy", happy_sound()),
+            sad_sound: assets.load_synth("sad", sad_sound()),
+            threat_hums: SIDES.map(|(side, pan)| {
+                assets.load_synth(
+                    &panned(THREAT_HUM, side),
+                    Synth {
+                        pan,
+                        ..threat_hum()
+                    },
+                )
+            }),
+            pack: assets.load(LEVEL_PACK),
+        }
+    }
 }
 
 /// TODO:
@@ -566,142 +781,279 @@ struct LayerState {
     background_tile_texture: TileTexture,
     splash_texture: Texture2D,
 
-    // Sounds.
-    happy_sound: Sound,
-    sad_sound: Sound,
+    // Sounds and music.
+    audio: Audio,
 
-    // Mouse info.
+    // Input info.
+    input: Input,
     mouse_pos: Vec2,
+    tap_target: Option<Vec2>,
+
+    // Tile editor.
+    editor: TileEditor,
+    editing: bool,
 
     // Tilemap state.
     map: TileMap,
     active_layer: i8,
-    tilemaps: Vec<DynamicImage>,
+    levels: Vec<Level>,
     active_tilemap_index: usize,
 
-    // Track sprite.
-    cosy_pos: (f32, f32),
-    cosy_sprite: &'static [u8],
-    cosy_flip: bool,
-
-    // Track all completed objectives.
-    completed_objective_lines: Vec<Vec<(usize, usize)>>,
-    checkpoint: (f32, f32),
-    remaining_objectives: bool,
-
-    // Track all threats.
-    max_threat_radius: isize,
-    threat_interval_seconds: f64,
-    threat_radius: i32,
-    threat_timestamp: f64,
-    threatened: bool,
+    // Track sprite, and its position as of the previous step.
+    cosy: Actor,
+    cosy_previous: Vec2,
+
+    // Simulation time, in seconds, advanced in fixed steps.
+    timestep: FixedTimestep,
+    time: f64,
+
+    // Eased viewport scale.
+    zoom: Tween<TileMap, f32>,
+
+    // Track objectives and threats.
+    rules: Rules,
+
+    // Settings and progress saved between sessions.
+    storage: Storage,
+    save: Save,
+    level_started_at: f64,
+
+    // Hot reloading, if enabled, of the loaded assets.
+    #[cfg(not(target_arch = "wasm32"))]
+    assets: Assets,
+    #[cfg(not(target_arch = "wasm32"))]
+    handles: LayerAssets,
+    #[cfg(not(target_arch = "wasm32"))]
+    watcher: Option<AssetWatcher<Asset>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    failed_reload: Option<(Asset, String)>,
+
+    // Latest failure to show on screen, if any.
+    error: Option<String>,
 }
 
 impl LayerState {
     /// TODO:
-    pub async fn new() -> Self {
-        // Load textures.
-        let floor_tile_texture = TileTexture::from_bytes(FLOOR_TILE);
-        let wall_tile_texture = TileTexture::from_bytes(WALL_TILE);
-        let background_tile_texture = TileTexture::from_bytes(BACKGROUND_TILE);
-        let splash_texture = Texture2D::from_file_with_format(SPLASH, None);
+    pub fn new(assets: Assets, handles: LayerAssets) -> Self {
+        // Take textures and sounds from the loaded assets.
+        let floor_tile_texture =
+            TileTexture::from_texture(assets.texture(&handles.floor_tile).clone());
+        let wall_tile_texture =
+            TileTexture::from_texture(assets.texture(&handles.wall_tile).clone());
+        let background_tile_texture =
+            TileTexture::from_texture(assets.texture(&handles.background_tile).clone());
+        let splash_texture = assets.texture(&handles.splash).clone();
         splash_texture.set_filter(FilterMode::Linear);
 
-        // Load sounds.
-        let happy_sound = load_sound_from_bytes(HAPPY_SOUND).await.unwrap();
-        let sad_sound = load_sound_from_bytes(SAD_SOUND).await.unwrap();
-
-        // Load tilemaps.
-        let mut tilemaps = vec![];
-        for tilemap in TILEMAPS {
-            let tilemap = image::load_from_memory(tilemap)
-                .unwrap()
-                .rotate270()
-                .resize_exact(WIDTH as u32, HEIGHT as u32, FilterType::Nearest);
-            tilemaps.push(tilemap);
+        // Restore settings and progress from earlier sessions.
+        let storage = Storage::new(STORAGE_APP);
+        let mut error = None;
+        let save = match storage.read(SAVE_KEY).map(|text| Save::from_text(&text)) {
+            Some(Ok(save)) => save,
+            Some(Err(e)) => {
+                error = Some(format!("failed to load save: {e}"));
+                Save::default()
+            }
+            None => Save::default(),
+        };
+
+        // Mix sounds and music, starting every music stem at once.
+        let mut audio = Audio::default();
+        audio.set_master_volume(save.settings.master_volume);
+        audio.set_muted(save.settings.muted);
+        for (bus, volume) in &save.settings.bus_volumes {
+            audio.set_bus_volume(*bus, *volume);
+        }
+        if let Some(sound) = assets.get(&handles.ambience) {
+            audio.add("ambience", Clip::new(sound.clone(), Bus::Music));
+        }
+        if let Some(sound) = assets.get(&handles.drone) {
+            audio.add("drone", Clip::new(sound.clone(), Bus::Music));
+        }
+        if let Some(sound) = assets.get(&handles.happy_sound) {
+            let clip = Clip::new(sound.clone(), Bus::Sfx);
+            audio.add("happy", clip.priority(1).seconds(happy_sound().duration()));
+        }
+        if let Some(sound) = assets.get(&handles.sad_sound) {
+            let clip = Clip::new(sound.clone(), Bus::Sfx);
+            audio.add("sad", clip.priority(2).seconds(sad_sound().duration()));
         }
+        for ((side, _), handle) in SIDES.iter().zip(&handles.threat_hums) {
+            if let Some(sound) = assets.get(handle) {
+                let clip = Clip::new(sound.clone(), Bus::Sfx).volume(0.5);
+                audio.add(&panned(THREAT_HUM, side), clip);
+            }
+        }
+        let stems: Vec<&str> = MUSIC_STEMS.iter().map(|(stem, _)| *stem).collect();
+        audio.start_tracks(&stems);
+
+        // Fall back to the built-in levels, reporting why.
+        if let LoadState::Failed(message) = assets.state(&handles.pack) {
+            error = Some(format!("failed to load {LEVEL_PACK}: {message}"));
+        }
+        let pack = assets.get(&handles.pack).cloned().unwrap_or_else(|| {
+            LevelPack::from_manifest(BUILTIN_LEVEL_PACK, |source| {
+                BUILTIN_LEVELS
+                    .iter()
+                    .find(|(name, _)| *name == source)
+                    .map(|(_, bytes)| bytes.to_vec())
+            })
+            .unwrap()
+        });
+        let (width, height) = pack.size();
+        let levels = pack.levels;
 
         // Initialize map.
-        let mut map = crate::tile::TileMap::new(WIDTH, HEIGHT);
-        map.draw_debug_info = false;
+        let mut map = crate::tile::TileMap::new(width, height);
+        map.draw_debug_info = save.settings.debug;
         map.viewport_scale = 1.0;
+        map.ghost_opacity = Some(0.1);
+        map.cutaway = Some(Cutaway::default());
+
+        // Load input bindings, preferring any local config
+        // over bindings saved from earlier sessions.
+        #[cfg(not(target_arch = "wasm32"))]
+        let local = match std::fs::read_to_string(INPUT_CONFIG_PATH) {
+            Ok(config) => match Input::from_config(&config) {
+                Ok(input) => Some(input),
+                Err(e) => {
+                    error = Some(format!("failed to load {INPUT_CONFIG_PATH}: {e}"));
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        #[cfg(target_arch = "wasm32")]
+        let local: Option<Input> = None;
+        let input = local.unwrap_or_else(|| {
+            let mut input = Input::from_config(INPUT_CONFIG).unwrap();
+            for (action, bindings) in &save.keybinds {
+                input.set_bindings(action, bindings);
+            }
+            input
+        });
 
         // Track mouse position between frames.
-        let mouse_pos = Vec2::from(macroquad::prelude::mouse_position());
+        let mouse_pos = input.pointer();
+
+        // Offer every tile a bitmap can describe in the editor.
+        let mut floor_color = color::DEFAULT;
+        floor_color.alpha = (0.75 * 255.) as u8;
+        let mut palette = vec![Tile::Filled {
+            texture: wall_tile_texture.clone(),
+            height_offset: None,
+            blend_color: None,
+        }];
+        for blend_color in [
+            floor_color,
+            color::ACCENT_1,
+            color::ACCENT_2,
+            color::ACCENT_3,
+        ] {
+            palette.push(Tile::Filled {
+                texture: floor_tile_texture.clone(),
+                height_offset: None,
+                blend_color: Some(blend_color),
+            });
+        }
+        let editor = TileEditor::new(palette);
+        let editing = false;
 
         // Keep track of the layer the user can interact with.
         let active_layer = FOREGROUND_LAYER;
         let active_tilemap_index = 0;
 
         // Establish cosy's location on the grid.
-        let cosy_pos = (0.0, 0.0);
-        let cosy_sprite = SPRITE;
-        let cosy_flip = false;
-
-        // Track all completed objectives.
-        let completed_objective_lines: Vec<Vec<(usize, usize)>> = vec![];
-        let checkpoint = cosy_pos;
-        let remaining_objectives = true;
-
-        // Track all threats' states.
-        let max_threat_radius = (HEIGHT.max(WIDTH) as f32 * 1.25) as isize;
-        let threat_interval_seconds = 4.0 / max_threat_radius as f64;
-        let threat_radius = 0;
-        let threat_timestamp = macroquad::prelude::get_time();
-        let threatened = false;
+        let mut cosy = Actor::new(
+            Sprite::from_textures(
+                assets.texture(&handles.sprite).clone(),
+                assets.texture(&handles.sprite_back).clone(),
+            ),
+            Vec2::ZERO,
+            active_layer,
+        );
+        cosy.z = 0.5;
+
+        // Track objectives and threats.
+        let level = &levels[0];
+        let rules = Rules::new(
+            Puzzle::from_bitmap(&level.bitmap),
+            level.settings.threats.clone(),
+        );
+
+        // Watch assets for changes, if hot reloading.
+        #[cfg(not(target_arch = "wasm32"))]
+        let watcher = std::env::var_os(HOT_RELOAD_VAR).map(|_| {
+            let mut watcher = AssetWatcher::default();
+            for (asset, handle) in [
+                (Asset::FloorTile, &handles.floor_tile),
+                (Asset::WallTile, &handles.wall_tile),
+                (Asset::BackgroundTile, &handles.background_tile),
+                (Asset::Sprite, &handles.sprite),
+                (Asset::Sprite, &handles.sprite_back),
+                (Asset::Splash, &handles.splash),
+            ] {
+                watcher.watch(assets.key(handle), asset);
+            }
+            watcher.watch(assets.key(&handles.pack), Asset::Levels);
+            watcher.watch(INPUT_CONFIG_PATH, Asset::Input);
+            for level in &levels {
+                watcher.watch(level_path(level), Asset::Levels);
+            }
+            watcher
+        });
 
         Self {
             floor_tile_texture,
             wall_tile_texture,
             background_tile_texture,
             splash_texture,
-            happy_sound,
-            sad_sound,
+            audio,
+            input,
             mouse_pos,
+            tap_target: None,
+            editor,
+            editing,
             map,
             active_layer,
-            tilemaps,
+            levels,
             active_tilemap_index,
-            cosy_pos,
-            cosy_sprite,
-            cosy_flip,
-            completed_objective_lines,
-            checkpoint,
-            remaining_objectives,
-            max_threat_radius,
-            threat_interval_seconds,
-            threat_radius,
-            threat_timestamp,
-            threatened,
+            cosy_previous: cosy.position,
+            cosy,
+            timestep: FixedTimestep::new(SIMULATION_STEP),
+            time: 0.0,
+            zoom: zoom_tween(1.0, 1.0),
+            rules,
+            storage,
+            save,
+            level_started_at: 0.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            assets,
+            #[cfg(not(target_arch = "wasm32"))]
+            handles,
+            #[cfg(not(target_arch = "wasm32"))]
+            watcher,
+            #[cfg(not(target_arch = "wasm32"))]
+            failed_reload: None,
+            error,
         }
     }
 
     /// TODO:
     pub fn activate_layer(&mut self, layer: usize) {
-        if layer as i8 != self.active_layer {
-            for x in 0..WIDTH {
-                for y in 0..HEIGHT {
-                    if let Some(Tile::Filled {
-                        blend_color: Some(color),
-                        ..
-                    }) = self.map.get_tile(x, y, self.active_layer)
-                    {
-                        if color.red >= 230 && color.green >= 230 && color.blue >= 230 {
-                            color.alpha = 26;
-                        }
-                    }
-                }
-            }
-        }
-
-        self.active_tilemap_index = layer % self.tilemaps.len();
+        self.active_tilemap_index = layer % self.levels.len();
         self.active_layer = layer as i8;
 
         if layer == 0 {
             self.map.clear();
 
-            for x in 0..WIDTH {
-                for y in 0..HEIGHT {
+            // Keep the background fully visible beneath ghosted layers.
+            self.map
+                .add_layer(BACKGROUND_LAYER, "Background")
+                .ghost_opacity = Some(1.0);
+
+            for x in 0..self.map.width() {
+                for y in 0..self.map.height() {
                     self.map.set_tile(
                         x,
                         y,
@@ -716,22 +1068,249 @@ impl LayerState {
             }
         }
 
-        self.cosy_pos = self
-            .map
-            .set_tiles_from_bitmap(
-                &self.tilemaps[self.active_tilemap_index],
-                self.active_layer,
-                self.wall_tile_texture.clone(),
-                self.floor_tile_texture.clone(),
-                0.75,
-            )
-            .unwrap();
-        self.completed_objective_lines.clear();
-        self.checkpoint = self.cosy_pos;
-        self.remaining_objectives = true;
-
-        self.threat_radius = 0;
-        self.threat_timestamp = macroquad::prelude::get_time();
-        self.threatened = false;
+        // Ghost every layer below the new one.
+        let level = &self.levels[self.active_tilemap_index];
+        self.map.add_layer(self.active_layer, level.name.clone());
+        self.map.active_layer = Some(self.active_layer);
+
+        let spawn = self.map.set_tiles_from_bitmap(
+            &level.bitmap,
+            self.active_layer,
+            self.wall_tile_texture.clone(),
+            self.floor_tile_texture.clone(),
+            level.settings.floor_opacity,
+        );
+
+        // Levels edited without a spawn keep the sprite where it is.
+        match spawn {
+            Some(spawn) => self.cosy.position = Vec2::from(spawn),
+            None => self.error = Some(format!("level `{}` has no spawn", level.name)),
+        }
+        self.cosy_previous = self.cosy.position;
+        self.cosy.layer = self.active_layer;
+        self.tap_target = None;
+
+        // Play the level's own threats.
+        self.rules = Rules::new(
+            Puzzle::from_bitmap(&level.bitmap),
+            level.settings.threats.clone(),
+        );
+        self.level_started_at = self.time;
+
+        // Bring in music stems as the layers deepen.
+        for (stem, depth) in MUSIC_STEMS {
+            let level = if layer >= *depth { 1.0 } else { 0.0 };
+            self.audio.fade_track(stem, level, MUSIC_FADE);
+        }
+    }
+
+    /// Resets the active layer's tiles from its level's bitmap.
+    fn reset_tiles(&mut self) {
+        let level = &self.levels[self.active_tilemap_index];
+        self.map.set_tiles_from_bitmap(
+            &level.bitmap,
+            self.active_layer,
+            self.wall_tile_texture.clone(),
+            self.floor_tile_texture.clone(),
+            level.settings.floor_opacity,
+        );
+    }
+
+    /// Records the completion of the active level,
+    /// unlocking the level after it.
+    fn complete_level(&mut self) {
+        let seconds = self.time - self.level_started_at;
+        let level = &self.levels[self.active_tilemap_index];
+        self.save.record_time(&level.name, seconds);
+
+        let next = &self.levels[(self.active_tilemap_index + 1) % self.levels.len()];
+        self.save.unlocked.insert(next.name.clone());
+        self.persist();
+    }
+
+    /// Saves the current settings and progress for later sessions.
+    ///
+    /// Only bindings changed from the defaults are saved, so
+    /// bindings added to the defaults reach existing players.
+    fn persist(&mut self) {
+        let settings = &mut self.save.settings;
+        settings.master_volume = self.audio.master_volume();
+        settings.muted = self.audio.is_muted();
+        for bus in Bus::ALL {
+            settings.bus_volumes.insert(bus, self.audio.bus_volume(bus));
+        }
+        settings.debug = self.map.draw_debug_info;
+
+        let defaults = Input::from_config(INPUT_CONFIG).unwrap();
+        self.save.keybinds = self
+            .input
+            .actions()
+            .filter(|action| self.input.bindings(action) != defaults.bindings(action))
+            .map(|action| (action.to_string(), self.input.bindings(action).to_vec()))
+            .collect();
+
+        if let Err(error) = self.storage.write(SAVE_KEY, &self.save.to_text()) {
+            self.error = Some(format!("failed to save: {error}"));
+        }
+    }
+
+    /// Reloads any assets whose files changed, while hot reloading.
+    ///
+    /// Reloads keep the rest of the game's state, like the
+    /// sprite's position; failed reloads keep the previous
+    /// asset, and are reported by `error` until the asset
+    /// reloads.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn hot_reload(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+
+        for asset in watcher.changed(macroquad::prelude::get_time()) {
+            match self.reload(asset).await {
+                Err(error) => {
+                    self.error = Some(error.clone());
+                    self.failed_reload = Some((asset, error));
+                }
+                // Clear the asset's earlier failure, unless
+                // another error has been shown since.
+                Ok(()) => {
+                    if let Some((failed, error)) = self.failed_reload.take() {
+                        if failed != asset {
+                            self.failed_reload = Some((failed, error));
+                        } else if self.error.as_ref() == Some(&error) {
+                            self.error = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reloads `asset` from its files.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn reload(&mut self, asset: Asset) -> Result<(), String> {
+        let (assets, handles) = (&mut self.assets, &self.handles);
+        match asset {
+            Asset::FloorTile => assets.reload(&handles.floor_tile).await,
+            Asset::WallTile => assets.reload(&handles.wall_tile).await,
+            Asset::BackgroundTile => assets.reload(&handles.background_tile).await,
+            Asset::Splash => assets.reload(&handles.splash).await,
+            Asset::Sprite => {
+                assets.reload(&handles.sprite).await?;
+                assets.reload(&handles.sprite_back).await
+            }
+            Asset::Input => {
+                let config = std::fs::read_to_string(INPUT_CONFIG_PATH)
+                    .map_err(|e| format!("{INPUT_CONFIG_PATH}: {e}"))?;
+                let config =
+                    Input::from_config(&config).map_err(|e| format!("{INPUT_CONFIG_PATH}: {e}"))?;
+
+                // Rebind in place, keeping the state of held inputs.
+                for action in config.actions() {
+                    self.input.set_bindings(action, config.bindings(action));
+                }
+                Ok(())
+            }
+            Asset::Levels => {
+                assets.reload(&handles.pack).await?;
+                let Some(pack) = assets.get(&handles.pack).cloned() else {
+                    return Ok(());
+                };
+
+                // The map can't grow while the game runs.
+                let (width, height) = pack.size();
+                if width > self.map.width() || height > self.map.height() {
+                    return Err(format!(
+                        "{LEVEL_PACK}: levels outgrew the {}x{} map; restart to load them",
+                        self.map.width(),
+                        self.map.height()
+                    ));
+                }
+
+                if let Some(watcher) = &mut self.watcher {
+                    for level in &pack.levels {
+                        watcher.watch(level_path(level), Asset::Levels);
+                    }
+                }
+
+                // The active level's tiles are reset from its bitmap
+                // every update, so reloaded levels apply in place,
+                // once tiles beyond smaller levels are cleared.
+                self.levels = pack.levels;
+                match self.levels.get(self.active_tilemap_index) {
+                    Some(level) => {
+                        self.rules.set_puzzle(Puzzle::from_bitmap(&level.bitmap));
+                        self.rules.set_threats(level.settings.threats.clone());
+                        if let Some(layer) = self.map.layer_mut(self.active_layer) {
+                            layer.clear();
+                        }
+                        self.reset_tiles();
+                    }
+                    None => self.activate_layer(0),
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the path of `level`'s source in the [`LEVEL_PACK`].
+#[cfg(not(target_arch = "wasm32"))]
+fn level_path(level: &Level) -> std::path::PathBuf {
+    std::path::Path::new(LEVEL_PACK).with_file_name(&level.source)
+}
+
+#[cfg(test)]
+mod tests {
+    use input::Sample;
+    use macroquad::input::{Touch, TouchPhase};
+
+    use super::*;
+
+    #[test]
+    fn sprites_move_as_far_as_they_are_pushed() {
+        let step = 0.1;
+        let full = sprite_motion(Vec2::ZERO, None, Vec2::new(1.0, 0.0), step);
+        let half = sprite_motion(Vec2::ZERO, None, Vec2::new(0.5, 0.0), step);
+        assert!((full.length() - COSY_SPEED * step).abs() < 1e-4);
+        assert!((half.length() - full.length() / 2.0).abs() < 1e-4);
+
+        // Targets move the sprite at full speed, once they're far enough away.
+        let target = Some(Vec2::new(0.0, 10.0));
+        assert_eq!(
+            full.length(),
+            sprite_motion(Vec2::ZERO, target, Vec2::ZERO, step).length()
+        );
+        let target = Some(Vec2::new(0.0, 0.5));
+        assert_eq!(
+            Vec2::ZERO,
+            sprite_motion(Vec2::ZERO, target, Vec2::ZERO, step)
+        );
+    }
+
+    #[test]
+    fn half_deflected_joysticks_move_the_sprite() {
+        let mut input = Input::from_config(INPUT_CONFIG).unwrap();
+        let touch = |phase, x| Sample {
+            touches: vec![Touch {
+                id: 0,
+                phase,
+                position: Vec2::new(x, 500.0),
+            }],
+            screen: Vec2::new(800.0, 600.0),
+            ..Default::default()
+        };
+
+        // Drag the joystick halfway to full deflection.
+        input.update_from(&touch(TouchPhase::Started, 100.0));
+        input.update_from(&touch(TouchPhase::Moved, 132.0));
+        let movement = input.vector("move");
+        assert_eq!(Vec2::new(0.5, 0.0), movement);
+
+        let step = 0.1;
+        let motion = sprite_motion(Vec2::ZERO, None, movement, step);
+        assert!((motion.length() - COSY_SPEED * step / 2.0).abs() < 1e-4);
     }
 }
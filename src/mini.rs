@@ -8,9 +8,9 @@ use miniquad::*;
 /// Texture used for testing image rendering.
 const TESTURE: &[u8] = include_bytes!("../assets/splash.png");
 
-const MIN_CLIP_X: f32 = MAX_CLIP_X * -1.0;
+const MIN_CLIP_X: f32 = -MAX_CLIP_X;
 const MAX_CLIP_X: f32 = 1.0;
-const MIN_CLIP_Y: f32 = MAX_CLIP_Y * -1.0;
+const MIN_CLIP_Y: f32 = -MAX_CLIP_Y;
 const MAX_CLIP_Y: f32 = 1.0;
 
 /// Entrypoint which spawns a default
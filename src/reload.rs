@@ -0,0 +1,125 @@
+//! Native-only hot reloading of assets during development.
+//!
+//! An [`AssetWatcher`] polls the modification times of
+//! watched files, reporting the assets whose files changed.
+//! Reloaded textures keep their identity via [`reload_texture`],
+//! so tiles and sprites drawing them pick up changes in place.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use macroquad::texture::Texture2D;
+
+/// Seconds between checks for changed files.
+const POLL_SECONDS: f64 = 0.5;
+
+/// Watches files for changes, reporting
+/// the assets (of type `A`) they belong to.
+#[derive(Clone, Debug)]
+pub struct AssetWatcher<A> {
+    /// Watched files, their assets, and their
+    /// modification times as of the last check.
+    files: HashMap<PathBuf, (A, Option<SystemTime>)>,
+
+    /// Time, in seconds, of the last check.
+    checked_at: f64,
+}
+
+impl<A> Default for AssetWatcher<A> {
+    fn default() -> Self {
+        Self {
+            files: HashMap::new(),
+            checked_at: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl<A: Copy + PartialEq> AssetWatcher<A> {
+    /// Watches the file at `path`, which belongs to `asset`.
+    ///
+    /// Files already being watched are left unchanged.
+    pub fn watch(&mut self, path: impl AsRef<Path>, asset: A) {
+        let path = path.as_ref();
+        if !self.files.contains_key(path) {
+            self.files
+                .insert(path.to_path_buf(), (asset, modified(path)));
+        }
+    }
+
+    /// Returns the assets whose files changed (or were created
+    /// or removed) since the last check, checking at most
+    /// every [`POLL_SECONDS`] from `time` seconds.
+    pub fn changed(&mut self, time: f64) -> Vec<A> {
+        if time - self.checked_at < POLL_SECONDS {
+            return vec![];
+        }
+        self.checked_at = time;
+
+        let mut changed = vec![];
+        for (path, (asset, last_modified)) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                if !changed.contains(asset) {
+                    changed.push(*asset);
+                }
+            }
+        }
+
+        changed
+    }
+}
+
+/// Returns the modification time of the file at `path`.
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Replaces the image of `texture` with the image in `bytes`,
+/// resizing the texture if the new image is a different size.
+pub fn reload_texture(texture: &Texture2D, bytes: &[u8]) -> Result<(), image::ImageError> {
+    let image = image::load_from_memory(bytes)?.to_rgba8();
+
+    unsafe {
+        let context = macroquad::window::get_internal_gl();
+        context.quad_context.texture_resize(
+            texture.raw_miniquad_id(),
+            image.width(),
+            image.height(),
+            Some(image.as_raw()),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn reports_changed_assets() {
+        let path = std::env::temp_dir().join(format!("layered-watch-{}", std::process::id()));
+        std::fs::write(&path, "before").unwrap();
+
+        let mut watcher = AssetWatcher::default();
+        watcher.watch(&path, "level");
+        assert!(watcher.changed(0.0).is_empty());
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+
+        // Changes are only seen once the poll interval passes.
+        assert!(watcher.changed(0.1).is_empty());
+        assert_eq!(vec!["level"], watcher.changed(1.0));
+        assert!(watcher.changed(2.0).is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(vec!["level"], watcher.changed(3.0));
+    }
+}
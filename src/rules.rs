@@ -0,0 +1,392 @@
+//! Rules of Layered's puzzles, free of any renderer.
+//!
+//! A [`Puzzle`] is the grid of [`Cell`]s a layer is played
+//! on, read from the same bitmaps (or plain-text maps) as
+//! the layer's tiles. [`Rules`] play a puzzle as the player
+//! moves around it, reporting [`Event`]s as they happen:
+//!
+//! - Objectives complete when the player reaches them in an
+//!   unbroken line of sight from the last checkpoint, which
+//!   then moves to the completed objective.
+//! - [`Threats`] reach across the puzzle over time, in the
+//!   ways described by [`threat`], threatening a player
+//!   caught in them.
+//! - Levels clear once every objective is complete.
+use glam::Vec2;
+use image::{DynamicImage, GenericImageView};
+
+use crate::{
+    color,
+    tile::{bitmap_from_map, shape},
+};
+
+pub mod threat;
+
+pub use threat::{Patrol, ThreatKind, Threats};
+
+/// What occupies a cell of a [`Puzzle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Floor,
+    Wall,
+    Objective,
+    Threat,
+    Spawn,
+}
+
+/// Grid of cells a layer is played on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Puzzle {
+    width: usize,
+    height: usize,
+
+    /// Cells, in rows from the top.
+    cells: Vec<Cell>,
+}
+
+impl Puzzle {
+    /// Returns the puzzle in `bitmap`, which is in the format
+    /// read by [`crate::tile::TileMap::set_tiles_from_bitmap`].
+    pub fn from_bitmap(bitmap: &DynamicImage) -> Self {
+        let kinds: [([u8; 4], Cell); 4] = [
+            (color::BACKGROUND.into(), Cell::Wall),
+            (color::ACCENT_1.into(), Cell::Objective),
+            (color::ACCENT_2.into(), Cell::Threat),
+            (color::ACCENT_3.into(), Cell::Spawn),
+        ];
+
+        let (width, height) = bitmap.dimensions();
+        let mut cells = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = bitmap.get_pixel(x, y).0;
+                let cell = kinds.iter().find(|(color, _)| *color == pixel);
+                cells.push(cell.map_or(Cell::Floor, |(_, cell)| *cell));
+            }
+        }
+
+        Self {
+            width: width as usize,
+            height: height as usize,
+            cells,
+        }
+    }
+
+    /// Returns the puzzle in the plain-text `map`,
+    /// in the format read by [`bitmap_from_map`].
+    pub fn from_map(map: &str) -> Self {
+        Self::from_bitmap(&bitmap_from_map(map))
+    }
+
+    /// Returns the width and height of the puzzle.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Returns the cell at `x, y`, if it's in the puzzle.
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        (x < self.width && y < self.height).then(|| self.cells[y * self.width + x])
+    }
+
+    /// Returns the position of every cell of `kind`.
+    pub fn cells_of(&self, kind: Cell) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.cells.len())
+            .filter(move |i| self.cells[*i] == kind)
+            .map(|i| (i % self.width, i / self.width))
+    }
+
+    /// Returns the position of the last spawn in the puzzle.
+    pub fn spawn(&self) -> Option<(usize, usize)> {
+        self.cells_of(Cell::Spawn).last()
+    }
+
+    /// Returns true if a wall is strictly between the
+    /// cells on `line`, excluding its endpoints.
+    fn blocked(&self, line: &[(isize, isize)]) -> bool {
+        line.iter()
+            .take(line.len().saturating_sub(1))
+            .skip(1)
+            .any(|(x, y)| self.cell(*x as usize, *y as usize) == Some(Cell::Wall))
+    }
+}
+
+/// Something that happened while playing a [`Puzzle`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The objective at a cell was completed.
+    ObjectiveCompleted((usize, usize)),
+
+    /// The player was caught by a threat.
+    Threatened,
+
+    /// Every objective was completed.
+    LevelCleared,
+}
+
+/// State of a [`Puzzle`] being played.
+#[derive(Clone, Debug)]
+pub struct Rules {
+    puzzle: Puzzle,
+
+    /// Position the player last completed an objective from.
+    checkpoint: Vec2,
+
+    /// Lines of sight along which objectives were completed.
+    completed_lines: Vec<Vec<(usize, usize)>>,
+
+    /// True for each completed objective cell, in rows from the top.
+    completed: Vec<bool>,
+
+    threats: Threats,
+
+    /// Seconds the puzzle has been played for.
+    time: f64,
+
+    /// Cells the threats reach.
+    threat_front: Vec<(usize, usize)>,
+
+    threatened: bool,
+    cleared: bool,
+}
+
+impl Rules {
+    /// Returns rules playing `puzzle` from its spawn, with `threats`.
+    pub fn new(puzzle: Puzzle, threats: Threats) -> Self {
+        let (width, height) = puzzle.size();
+        let (x, y) = puzzle.spawn().unwrap_or_default();
+
+        let mut rules = Self {
+            checkpoint: Vec2::new(x as f32, y as f32),
+            completed_lines: vec![],
+            completed: vec![false; width * height],
+            threats,
+            time: 0.0,
+            threat_front: vec![],
+            threatened: false,
+            cleared: false,
+            puzzle,
+        };
+        rules.update_threat_front();
+
+        rules
+    }
+
+    /// Returns the puzzle being played.
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    /// Replaces the puzzle being played with `puzzle`, keeping
+    /// the player's progress unless the puzzle changed size.
+    pub fn set_puzzle(&mut self, puzzle: Puzzle) {
+        if puzzle.size() != self.puzzle.size() {
+            let (width, height) = puzzle.size();
+            self.completed = vec![false; width * height];
+        }
+        self.puzzle = puzzle;
+        self.update_threat_front();
+    }
+
+    /// Replaces the threats of the puzzle with `threats`.
+    pub fn set_threats(&mut self, threats: Threats) {
+        self.threats = threats;
+        self.update_threat_front();
+    }
+
+    /// Returns the position the player last completed an objective from.
+    pub fn checkpoint(&self) -> Vec2 {
+        self.checkpoint
+    }
+
+    /// Returns the lines of sight along which objectives were completed.
+    pub fn completed_lines(&self) -> &[Vec<(usize, usize)>] {
+        &self.completed_lines
+    }
+
+    /// Returns true if the objective at `x, y` is complete.
+    pub fn is_completed(&self, x: usize, y: usize) -> bool {
+        self.puzzle.cell(x, y).is_some() && self.completed[y * self.puzzle.width + x]
+    }
+
+    /// Returns the number of objective cells not yet complete.
+    pub fn remaining_objectives(&self) -> usize {
+        self.puzzle
+            .cells_of(Cell::Objective)
+            .filter(|(x, y)| !self.is_completed(*x, *y))
+            .count()
+    }
+
+    /// Returns the cells between the checkpoint and a player at
+    /// `player`, up to any wall blocking the line of sight.
+    pub fn sight_line(&self, player: Vec2) -> Vec<(usize, usize)> {
+        let line = shape::bresenham_line(self.checkpoint.x, self.checkpoint.y, player.x, player.y);
+
+        line.iter()
+            .take(line.len().saturating_sub(1))
+            .skip(1)
+            .map(|(x, y)| (*x as usize, *y as usize))
+            .take_while(|(x, y)| self.puzzle.cell(*x, *y) != Some(Cell::Wall))
+            .collect()
+    }
+
+    /// Returns the cells the threats reach.
+    pub fn threat_front(&self) -> &[(usize, usize)] {
+        &self.threat_front
+    }
+
+    /// Returns the positions threats currently come from.
+    pub fn threat_sources(&self) -> Vec<Vec2> {
+        self.threats.sources(&self.puzzle, self.time)
+    }
+
+    /// Returns true once the player was caught by a threat.
+    pub fn is_threatened(&self) -> bool {
+        self.threatened
+    }
+
+    /// Returns true once every objective is complete.
+    pub fn is_cleared(&self) -> bool {
+        self.cleared
+    }
+
+    /// Plays the puzzle for `seconds` with the player at
+    /// `player`, returning the events which happened.
+    pub fn step(&mut self, player: Vec2, seconds: f64) -> Vec<Event> {
+        let mut events = vec![];
+        let cell = (player.x as usize, player.y as usize);
+
+        // Complete objectives reached in sight of the checkpoint.
+        let line = shape::bresenham_line(self.checkpoint.x, self.checkpoint.y, player.x, player.y);
+        let on_objective = self.puzzle.cell(cell.0, cell.1) == Some(Cell::Objective)
+            && !self.is_completed(cell.0, cell.1);
+        if on_objective && !self.puzzle.blocked(&line) {
+            let line: Vec<_> = line
+                .into_iter()
+                .filter_map(|(x, y)| Some((usize::try_from(x).ok()?, usize::try_from(y).ok()?)))
+                .collect();
+            for (x, y) in &line {
+                self.complete(*x, *y);
+            }
+
+            self.completed_lines.push(line);
+            self.checkpoint = player;
+            events.push(Event::ObjectiveCompleted(cell));
+        }
+
+        if !self.cleared && self.remaining_objectives() == 0 {
+            self.cleared = true;
+            events.push(Event::LevelCleared);
+        }
+
+        self.time += seconds;
+        self.update_threat_front();
+
+        let caught = self.puzzle.cell(cell.0, cell.1) == Some(Cell::Threat)
+            || self.threat_front.contains(&cell);
+        if caught && !self.threatened {
+            self.threatened = true;
+            events.push(Event::Threatened);
+        }
+
+        events
+    }
+
+    /// Completes the objective at `x, y`, along with every
+    /// objective cell orthogonally connected to it.
+    fn complete(&mut self, x: usize, y: usize) {
+        let mut pending = vec![(x, y)];
+        while let Some((x, y)) = pending.pop() {
+            if self.puzzle.cell(x, y) != Some(Cell::Objective) || self.is_completed(x, y) {
+                continue;
+            }
+
+            self.completed[y * self.puzzle.width + x] = true;
+            pending.push((x + 1, y));
+            pending.push((x, y + 1));
+            if x > 0 {
+                pending.push((x - 1, y));
+            }
+            if y > 0 {
+                pending.push((x, y - 1));
+            }
+        }
+    }
+
+    /// Finds the cells the threats reach.
+    fn update_threat_front(&mut self) {
+        self.threat_front = self.threats.front(&self.puzzle, self.time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the center of the cell at `x, y`.
+    fn at(x: usize, y: usize) -> Vec2 {
+        Vec2::new(x as f32, y as f32)
+    }
+
+    #[test]
+    fn objectives_complete_in_sight_of_the_checkpoint() {
+        let puzzle = Puzzle::from_map(
+            "\
+@...o
+.....
+#####
+oo...",
+        );
+        let mut rules = Rules::new(puzzle, Threats::default());
+        assert_eq!(3, rules.remaining_objectives());
+
+        assert_eq!(
+            vec![Event::ObjectiveCompleted((4, 0))],
+            rules.step(at(4, 0), 0.1)
+        );
+        assert_eq!(at(4, 0), rules.checkpoint());
+
+        // Walls break the line of sight.
+        assert!(rules.step(at(1, 3), 0.1).is_empty());
+        assert_eq!(2, rules.remaining_objectives());
+        assert_eq!(vec![(3, 1)], rules.sight_line(at(1, 3)));
+
+        // Connected objectives complete together.
+        let mut rules = Rules::new(Puzzle::from_map("o.@\no.#\noo."), Threats::default());
+        assert_eq!(
+            vec![Event::ObjectiveCompleted((0, 2)), Event::LevelCleared],
+            rules.step(at(0, 2), 0.1)
+        );
+        assert!(rules.is_completed(1, 2) && rules.is_completed(0, 0));
+    }
+
+    #[test]
+    fn threat_rings_expand_around_walls() {
+        let puzzle = Puzzle::from_map(
+            "\
+x.......
+........
+...#....
+........
+........
+........
+.......o
+.......@",
+        );
+        let mut rules = Rules::new(puzzle, Threats::new(ThreatKind::Ring { seconds: 10.0 }));
+        assert!(rules.threat_front().contains(&(3, 0)));
+        assert!(!rules.threat_front().contains(&(4, 0)));
+
+        // Rings expand every second, sweeping the
+        // puzzle's reach in ten seconds.
+        for _ in 0..4 {
+            assert!(rules.step(at(7, 7), 1.01).is_empty());
+        }
+
+        // Walls shadow the cells behind them.
+        assert!(rules.threat_front().contains(&(4, 6)));
+        assert!(!rules.threat_front().contains(&(6, 4)));
+
+        assert_eq!(vec![Event::Threatened], rules.step(at(4, 6), 0.0));
+        assert!(rules.step(at(4, 6), 0.0).is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+//! Threats endangering the player of a [`Puzzle`].
+//!
+//! Every threat cell of a puzzle behaves as its [`Threats`]'
+//! [`ThreatKind`], while [`Patrol`]s roam the puzzle besides
+//! its threat cells. Walls block every kind of threat.
+//!
+//! Threats depend on nothing but the puzzle and how long it's
+//! been played, so they're identical whenever a puzzle is
+//! played for the same time.
+use std::{collections::VecDeque, f32::consts::TAU};
+
+use glam::Vec2;
+
+use super::{Cell, Puzzle};
+use crate::tile::shape;
+
+/// Radius, in tiles, of the smallest ring.
+const RING_START: isize = 3;
+
+/// Largest ring, relative to the size of the puzzle.
+const RING_REACH: f32 = 1.25;
+
+/// How the threat cells of a puzzle behave.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThreatKind {
+    /// Rings expanding from each threat, sweeping across
+    /// the puzzle every `seconds` before starting over.
+    Ring { seconds: f64 },
+
+    /// Beams `length` tiles long, turning clockwise
+    /// around each threat once every `seconds`.
+    Beam { length: f32, seconds: f64 },
+
+    /// Zones within `radius` tiles of each threat, on for
+    /// `on_seconds` and then off for `off_seconds`, repeatedly.
+    Pulse {
+        radius: f32,
+        on_seconds: f64,
+        off_seconds: f64,
+    },
+
+    /// Contamination spreading from each threat across
+    /// the floors connected to it, a tile every `seconds`.
+    Spread { seconds: f64 },
+}
+
+impl Default for ThreatKind {
+    fn default() -> Self {
+        Self::Ring { seconds: 4.0 }
+    }
+}
+
+/// A hazard walking back and forth between waypoints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patrol {
+    /// Cells the hazard walks between, in order.
+    pub waypoints: Vec<(usize, usize)>,
+
+    /// Radius, in tiles, the hazard reaches.
+    pub radius: f32,
+
+    /// Seconds the hazard takes to walk a tile.
+    pub seconds: f64,
+}
+
+impl Patrol {
+    /// Returns the position of the hazard after patrolling for `time` seconds.
+    pub fn position(&self, time: f64) -> Vec2 {
+        let points: Vec<Vec2> = self
+            .waypoints
+            .iter()
+            .map(|(x, y)| Vec2::new(*x as f32, *y as f32))
+            .collect();
+        let Some(first) = points.first() else {
+            return Vec2::ZERO;
+        };
+
+        let length: f32 = points.windows(2).map(|leg| leg[0].distance(leg[1])).sum();
+        if length == 0.0 {
+            return *first;
+        }
+
+        // Walk to the last waypoint, and then back again.
+        let mut distance = ((time / self.seconds) % (2.0 * length as f64)) as f32;
+        if distance > length {
+            distance = 2.0 * length - distance;
+        }
+        for leg in points.windows(2) {
+            let leg_length = leg[0].distance(leg[1]);
+            if leg_length > 0.0 && distance <= leg_length {
+                return leg[0].lerp(leg[1], distance / leg_length);
+            }
+            distance -= leg_length;
+        }
+
+        points[points.len() - 1]
+    }
+}
+
+/// Threats of a puzzle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Threats {
+    /// How the puzzle's threat cells behave.
+    pub kind: ThreatKind,
+
+    /// Hazards patrolling the puzzle.
+    pub patrols: Vec<Patrol>,
+}
+
+impl Threats {
+    /// Returns threats whose threat cells behave as `kind`.
+    pub fn new(kind: ThreatKind) -> Self {
+        Self {
+            kind,
+            patrols: vec![],
+        }
+    }
+
+    /// Returns the positions threats come from after `puzzle`
+    /// is played for `time` seconds: every threat cell,
+    /// followed by the position of every patrol.
+    pub fn sources(&self, puzzle: &Puzzle, time: f64) -> Vec<Vec2> {
+        puzzle
+            .cells_of(Cell::Threat)
+            .map(|(x, y)| Vec2::new(x as f32, y as f32))
+            .chain(self.patrols.iter().map(|patrol| patrol.position(time)))
+            .collect()
+    }
+
+    /// Returns the (sorted) cells threats reach
+    /// after `puzzle` is played for `time` seconds.
+    pub fn front(&self, puzzle: &Puzzle, time: f64) -> Vec<(usize, usize)> {
+        let threats: Vec<Vec2> = puzzle
+            .cells_of(Cell::Threat)
+            .map(|(x, y)| Vec2::new(x as f32, y as f32))
+            .collect();
+
+        let mut front: Vec<(isize, isize)> = match self.kind {
+            ThreatKind::Ring { seconds } => {
+                // Start over once the rings reach well
+                // beyond the edges of the puzzle.
+                let (width, height) = puzzle.size();
+                let expansions = ((width.max(height) as f32 * RING_REACH) as isize).max(1);
+                let expansion = (time / seconds * expansions as f64) as isize % expansions;
+
+                threats
+                    .iter()
+                    .flat_map(|threat| {
+                        let ring = shape::circle(
+                            threat.x as isize,
+                            threat.y as isize,
+                            RING_START + expansion,
+                        );
+                        in_sight(puzzle, *threat, ring)
+                    })
+                    .collect()
+            }
+            ThreatKind::Beam { length, seconds } => {
+                let direction = Vec2::from_angle((time / seconds).fract() as f32 * TAU);
+
+                threats
+                    .iter()
+                    .flat_map(|threat| {
+                        let end = *threat + direction * length;
+                        shape::supercover_line(threat.x, threat.y, end.x, end.y).take_while(
+                            |cell| matches!(cell_at(puzzle, *cell), Some(c) if c != Cell::Wall),
+                        )
+                    })
+                    .collect()
+            }
+            ThreatKind::Pulse {
+                radius,
+                on_seconds,
+                off_seconds,
+            } => {
+                if time % (on_seconds + off_seconds) < on_seconds {
+                    threats
+                        .iter()
+                        .flat_map(|threat| {
+                            in_sight(puzzle, *threat, shape::disc(threat.x, threat.y, radius))
+                        })
+                        .collect()
+                } else {
+                    vec![]
+                }
+            }
+            ThreatKind::Spread { seconds } => spread(puzzle, (time / seconds) as usize),
+        };
+
+        for patrol in &self.patrols {
+            let position = patrol.position(time);
+            let reach = shape::disc(position.x, position.y, patrol.radius);
+            front.extend(in_sight(puzzle, position.round(), reach));
+        }
+
+        let mut front: Vec<(usize, usize)> = front
+            .into_iter()
+            .filter_map(|cell| match cell_at(puzzle, cell)? {
+                Cell::Wall => None,
+                _ => Some((cell.0 as usize, cell.1 as usize)),
+            })
+            .collect();
+        front.sort_unstable();
+        front.dedup();
+
+        front
+    }
+}
+
+/// Returns the cell of `puzzle` at `x, y`, if it's in the puzzle.
+fn cell_at(puzzle: &Puzzle, (x, y): (isize, isize)) -> Option<Cell> {
+    puzzle.cell(usize::try_from(x).ok()?, usize::try_from(y).ok()?)
+}
+
+/// Returns the `cells` which no wall hides from `origin`.
+fn in_sight(
+    puzzle: &Puzzle,
+    origin: Vec2,
+    cells: impl IntoIterator<Item = (isize, isize)>,
+) -> Vec<(isize, isize)> {
+    cells
+        .into_iter()
+        .filter(|(x, y)| {
+            let line = shape::bresenham_line(origin.x, origin.y, *x as f32, *y as f32);
+            !puzzle.blocked(&line)
+        })
+        .collect()
+}
+
+/// Returns the cells at most `steps` orthogonal steps across
+/// connected floors from any of `puzzle`'s threat cells.
+fn spread(puzzle: &Puzzle, steps: usize) -> Vec<(isize, isize)> {
+    let (width, height) = puzzle.size();
+    let mut reached = vec![false; width * height];
+    let mut pending: VecDeque<((usize, usize), usize)> = VecDeque::new();
+    for (x, y) in puzzle.cells_of(Cell::Threat) {
+        reached[y * width + x] = true;
+        pending.push_back(((x, y), 0));
+    }
+
+    let mut cells = vec![];
+    while let Some(((x, y), distance)) = pending.pop_front() {
+        cells.push((x as isize, y as isize));
+        if distance == steps {
+            continue;
+        }
+
+        let neighbors = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (x, y) in neighbors {
+            match puzzle.cell(x, y) {
+                Some(Cell::Wall) | None => {}
+                Some(_) if !reached[y * width + x] => {
+                    reached[y * width + x] = true;
+                    pending.push_back(((x, y), distance + 1));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threats_move_over_time() {
+        let puzzle = Puzzle::from_map(
+            "\
+x....
+.....
+..#..
+.....
+.....",
+        );
+
+        // Beams turn, stopping at walls.
+        let beam = Threats::new(ThreatKind::Beam {
+            length: 4.0,
+            seconds: 4.0,
+        });
+        assert_eq!(
+            vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)],
+            beam.front(&puzzle, 0.0)
+        );
+        assert!(beam.front(&puzzle, 1.0).contains(&(0, 4)));
+        assert!(!beam.front(&puzzle, 0.5).contains(&(3, 3)));
+
+        // Pulses switch on and off.
+        let pulse = Threats::new(ThreatKind::Pulse {
+            radius: 1.0,
+            on_seconds: 1.0,
+            off_seconds: 2.0,
+        });
+        assert_eq!(vec![(0, 0), (0, 1), (1, 0)], pulse.front(&puzzle, 0.5));
+        assert!(pulse.front(&puzzle, 1.5).is_empty());
+        assert_eq!(3, pulse.front(&puzzle, 3.5).len());
+
+        // Contamination spreads around walls.
+        let spread = Threats::new(ThreatKind::Spread { seconds: 1.0 });
+        assert_eq!(3, spread.front(&puzzle, 1.0).len());
+        assert_eq!(24, spread.front(&puzzle, 100.0).len());
+
+        // Patrols walk there and back again.
+        let mut patrol = Threats::new(ThreatKind::Spread {
+            seconds: f64::INFINITY,
+        });
+        patrol.patrols.push(Patrol {
+            waypoints: vec![(4, 0), (4, 4), (2, 4)],
+            radius: 0.0,
+            seconds: 0.5,
+        });
+        assert_eq!(vec![(0, 0), (4, 2)], patrol.front(&puzzle, 1.0));
+        assert_eq!(Vec2::new(3.0, 4.0), patrol.patrols[0].position(2.5));
+        assert_eq!(Vec2::new(4.0, 2.0), patrol.patrols[0].position(5.0));
+    }
+}
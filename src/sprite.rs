@@ -0,0 +1,427 @@
+//! Sprites and the actors that wear them on a [`TileMap`].
+use std::{
+    f32::consts::{FRAC_PI_4, PI},
+    rc::Rc,
+};
+
+use glam::Vec2;
+use macroquad::{
+    math::Rect,
+    texture::{FilterMode, Texture2D},
+};
+
+use crate::tile::TileMap;
+
+pub mod clip;
+
+pub use clip::{Clip, ClipPlayer, Frame, Playback, SheetError, SpriteSheet};
+
+/// Height, in tiles, of an [`Actor`]'s jump.
+const JUMP_HEIGHT: f32 = 0.5;
+
+/// Duration, in seconds, of an [`Actor`]'s jump.
+const JUMP_SECONDS: f32 = 0.4;
+
+/// One of the eight directions an [`Actor`]
+/// can face, relative to the screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facing {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    #[default]
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Facing {
+    /// All facings, clockwise from [`Facing::North`].
+    pub const ALL: [Self; 8] = [
+        Self::North,
+        Self::NorthEast,
+        Self::East,
+        Self::SouthEast,
+        Self::South,
+        Self::SouthWest,
+        Self::West,
+        Self::NorthWest,
+    ];
+
+    /// Returns the facing nearest to the planar grid
+    /// `direction`, as it appears on a dimetric map.
+    ///
+    /// Returns `None` if `direction` is zero.
+    pub fn from_grid_direction(direction: Vec2) -> Option<Self> {
+        if direction == Vec2::ZERO {
+            return None;
+        }
+
+        // Project the direction into screen space, where
+        // grid axes run diagonally and Y points down.
+        let screen = Vec2::new(direction.x - direction.y, (direction.x + direction.y) * 0.5);
+
+        // Measure clockwise from screen-space "up".
+        let angle = screen.x.atan2(-screen.y).rem_euclid(2.0 * PI);
+        let sector = ((angle + FRAC_PI_4 / 2.0) / FRAC_PI_4) as usize % 8;
+
+        Some(Self::ALL[sector])
+    }
+
+    /// Returns the planar grid direction which appears
+    /// to point towards this facing on a dimetric map.
+    pub fn grid_direction(&self) -> Vec2 {
+        let angle = self.index() as f32 * FRAC_PI_4;
+        let screen = Vec2::new(angle.sin(), -angle.cos());
+
+        // Undo the dimetric projection.
+        Vec2::new(screen.y + screen.x * 0.5, screen.y - screen.x * 0.5).normalize()
+    }
+
+    /// Returns the facing abbreviated
+    /// by `abbreviation`, like `ne`.
+    pub fn from_abbreviation(abbreviation: &str) -> Option<Self> {
+        let index = ["n", "ne", "e", "se", "s", "sw", "w", "nw"]
+            .iter()
+            .position(|a| a.eq_ignore_ascii_case(abbreviation))?;
+
+        Some(Self::ALL[index])
+    }
+
+    /// Returns the facing's index in [`Self::ALL`].
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Returns the facing mirrored across the
+    /// screen's vertical axis, like west for east.
+    pub fn mirrored(&self) -> Self {
+        Self::ALL[(8 - self.index()) % 8]
+    }
+
+    /// Returns true if an actor with this facing
+    /// shows their back to the viewer.
+    pub fn is_away(&self) -> bool {
+        self.grid_direction().y < 0.0
+    }
+
+    /// Returns true if an actor with this facing is drawn
+    /// mirrored, assuming sprites are drawn facing left.
+    pub fn is_flipped(&self) -> bool {
+        self.grid_direction().x > 0.0
+    }
+}
+
+/// Textures drawn for an [`Actor`].
+#[derive(Clone, PartialEq)]
+pub enum Sprite {
+    /// A still image for each side of the actor.
+    Still {
+        /// Texture drawn while facing the viewer.
+        front: Texture2D,
+
+        /// Texture drawn while facing away from the viewer.
+        back: Texture2D,
+    },
+
+    /// Clips from a sprite sheet, named after
+    /// the [`AnimationState`] they're played in.
+    Animated {
+        sheet: Rc<SpriteSheet>,
+        player: ClipPlayer,
+    },
+}
+
+impl Sprite {
+    /// Loads a still sprite from the images in `front` and `back`.
+    ///
+    /// The format of the images will be auto-detected
+    /// so long as they are one of [ImageFormat][image::ImageFormat].
+    pub fn from_bytes(front: &[u8], back: &[u8]) -> Self {
+        Self::from_textures(
+            Texture2D::from_file_with_format(front, None),
+            Texture2D::from_file_with_format(back, None),
+        )
+    }
+
+    /// Returns a still sprite drawing `front` and `back`.
+    pub fn from_textures(front: Texture2D, back: Texture2D) -> Self {
+        front.set_filter(FilterMode::Nearest);
+        back.set_filter(FilterMode::Nearest);
+
+        Self::Still { front, back }
+    }
+
+    /// Returns an animated sprite playing clips from `sheet`.
+    pub fn from_sheet(sheet: Rc<SpriteSheet>) -> Self {
+        Self::Animated {
+            sheet,
+            player: ClipPlayer::default(),
+        }
+    }
+
+    /// Returns the texture and source rectangle to draw
+    /// while `facing`, and true if it should be flipped.
+    pub fn frame(&self, facing: Facing) -> Option<(&Texture2D, Rect, bool)> {
+        match self {
+            Self::Still { front, back } => {
+                let texture = if facing.is_away() { back } else { front };
+                let source = Rect::new(0.0, 0.0, texture.width(), texture.height());
+                Some((texture, source, facing.is_flipped()))
+            }
+            Self::Animated { sheet, player } => {
+                let (frame, flip_x) = player.frame(sheet, facing)?;
+                Some((&sheet.texture, frame.source, flip_x))
+            }
+        }
+    }
+
+    /// Plays the clip for `state` while `facing`,
+    /// advancing it by `frame_time` seconds.
+    pub fn update(&mut self, state: AnimationState, facing: Facing, frame_time: f32) {
+        if let Self::Animated { sheet, player } = self {
+            player.play(state.name());
+            player.update(sheet, facing, frame_time);
+        }
+    }
+
+    /// Returns the events fired by clip
+    /// frames during the last update.
+    pub fn events(&self) -> &[String] {
+        match self {
+            Self::Still { .. } => &[],
+            Self::Animated { player, .. } => player.events(),
+        }
+    }
+}
+
+/// Animation states of an [`Actor`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationState {
+    /// Standing still.
+    #[default]
+    Idle,
+
+    /// Moving across the map.
+    Walk,
+
+    /// Airborne, returning to [`Self::Idle`]
+    /// or [`Self::Walk`] on landing.
+    Jump,
+}
+
+impl AnimationState {
+    /// Returns the name of the [`Clip`]
+    /// played in this state, like `walk`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Walk => "walk",
+            Self::Jump => "jump",
+        }
+    }
+}
+
+/// State machine tracking an [`Actor`]'s animation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Animator {
+    /// Current state.
+    state: AnimationState,
+
+    /// Seconds spent in the current state.
+    elapsed: f32,
+
+    /// True if the actor is moving, used to pick
+    /// the state to return to after a jump.
+    moving: bool,
+}
+
+impl Animator {
+    /// Returns the current state.
+    pub fn state(&self) -> AnimationState {
+        self.state
+    }
+
+    /// Returns the seconds spent in the current state.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Enters `state`, restarting its timer
+    /// if it differs from the current state.
+    pub fn transition(&mut self, state: AnimationState) {
+        if self.state != state {
+            self.state = state;
+            self.elapsed = 0.0;
+        }
+    }
+
+    /// Advances the current state by `frame_time` seconds.
+    pub fn update(&mut self, frame_time: f32) {
+        self.elapsed += frame_time;
+
+        match self.state {
+            AnimationState::Jump if self.elapsed >= JUMP_SECONDS => {
+                self.transition(if self.moving {
+                    AnimationState::Walk
+                } else {
+                    AnimationState::Idle
+                });
+            }
+            AnimationState::Idle if self.moving => self.transition(AnimationState::Walk),
+            AnimationState::Walk if !self.moving => self.transition(AnimationState::Idle),
+            _ => {}
+        }
+    }
+
+    /// Returns the height, in tiles, the current
+    /// state lifts the actor off of its layer.
+    pub fn lift(&self) -> f32 {
+        match self.state {
+            AnimationState::Jump => {
+                JUMP_HEIGHT * (PI * (self.elapsed / JUMP_SECONDS).min(1.0)).sin()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// A sprite-wearing entity on a [`TileMap`].
+#[derive(Clone, PartialEq)]
+pub struct Actor {
+    /// Planar grid position.
+    pub position: Vec2,
+
+    /// Height, in tiles, above [`Self::layer`].
+    pub z: f32,
+
+    /// Map layer the actor stands on.
+    pub layer: i8,
+
+    /// Direction the actor faces.
+    pub facing: Facing,
+
+    /// Animation state machine.
+    pub animator: Animator,
+
+    /// Textures drawn for the actor.
+    pub sprite: Sprite,
+}
+
+impl Actor {
+    /// Returns a new, idle actor wearing
+    /// `sprite` at `position` on `layer`.
+    pub fn new(sprite: Sprite, position: Vec2, layer: i8) -> Self {
+        Self {
+            position,
+            z: 0.0,
+            layer,
+            facing: Facing::default(),
+            animator: Animator::default(),
+            sprite,
+        }
+    }
+
+    /// Moves the actor to `position`, facing
+    /// the direction it moved in (if any).
+    pub fn move_to(&mut self, position: Vec2) {
+        if let Some(facing) = Facing::from_grid_direction(position - self.position) {
+            self.facing = facing;
+        }
+
+        self.position = position;
+    }
+
+    /// Faces the actor towards the grid point `target`.
+    pub fn face_towards(&mut self, target: Vec2) {
+        if let Some(facing) = Facing::from_grid_direction(target - self.position) {
+            self.facing = facing;
+        }
+    }
+
+    /// Marks the actor as moving (or not), switching
+    /// between walking and idling on the next update.
+    pub fn set_moving(&mut self, moving: bool) {
+        self.animator.moving = moving;
+    }
+
+    /// Starts a jump, unless already airborne.
+    pub fn jump(&mut self) {
+        self.animator.transition(AnimationState::Jump);
+    }
+
+    /// Advances the actor's animation by `frame_time` seconds.
+    pub fn update(&mut self, frame_time: f32) {
+        self.animator.update(frame_time);
+        self.sprite
+            .update(self.animator.state(), self.facing, frame_time);
+    }
+}
+
+impl TileMap {
+    /// Draws `actor` onto the map's tile space.
+    pub fn draw_actor(&mut self, actor: &Actor) {
+        let Some((texture, source, flip_x)) = actor.sprite.frame(actor.facing) else {
+            return;
+        };
+
+        self.draw_sprite(
+            texture,
+            source,
+            actor.position.extend(actor.z + actor.animator.lift()),
+            actor.layer,
+            flip_x,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facings_round_trip() {
+        for facing in Facing::ALL {
+            assert_eq!(
+                Some(facing),
+                Facing::from_grid_direction(facing.grid_direction())
+            );
+        }
+
+        assert_eq!(None, Facing::from_grid_direction(Vec2::ZERO));
+    }
+
+    #[test]
+    fn grid_diagonals_face_screen_axes() {
+        let facing = |x, y| Facing::from_grid_direction(Vec2::new(x, y)).unwrap();
+        assert_eq!(Facing::North, facing(-1.0, -1.0));
+        assert_eq!(Facing::South, facing(1.0, 1.0));
+        assert_eq!(Facing::West, facing(-1.0, 1.0));
+        assert_eq!(Facing::East, facing(1.0, -1.0));
+
+        assert!(Facing::North.is_away() && !Facing::North.is_flipped());
+        assert!(Facing::East.is_away() && Facing::East.is_flipped());
+        assert!(!Facing::South.is_away() && Facing::South.is_flipped());
+        assert!(!Facing::West.is_away() && !Facing::West.is_flipped());
+    }
+
+    #[test]
+    fn jumps_land() {
+        let mut animator = Animator {
+            moving: true,
+            ..Default::default()
+        };
+        animator.update(0.1);
+        assert_eq!(AnimationState::Walk, animator.state());
+
+        animator.transition(AnimationState::Jump);
+        animator.update(JUMP_SECONDS / 2.0);
+        assert!((animator.lift() - JUMP_HEIGHT).abs() < 1e-4);
+
+        animator.update(JUMP_SECONDS);
+        assert_eq!(AnimationState::Walk, animator.state());
+        assert_eq!(0.0, animator.lift());
+    }
+}
@@ -0,0 +1,449 @@
+//! Directional animation clips played from sprite sheets.
+//!
+//! A [`SpriteSheet`] pairs a texture with named [`Clip`]s
+//! described by a plain-text descriptor:
+//!
+//! ```text
+//! # Frames are 32x32 pixel cells, numbered
+//! # left-to-right, top-to-bottom from zero.
+//! cell 32 32
+//!
+//! # clip <name> <once|loop|pingpong> <seconds per frame>
+//! clip walk loop 0.1
+//!
+//! # <facing> <cell>[@seconds][!event]...
+//! s 0 1 2!step 3
+//! n 4 5 6!step 7
+//!
+//! # Facings without frames of their own use their
+//! # mirror image (flipped), or else the `*` frames.
+//! * 0
+//! ```
+//!
+//! Facings are `n`, `ne`, `e`, `se`, `s`, `sw`, `w` and `nw`,
+//! relative to the screen (see [`Facing`]).
+use std::{collections::HashMap, fmt::Display};
+
+use macroquad::{math::Rect, texture::Texture2D};
+
+use super::Facing;
+
+/// Shortest duration, in seconds, of a [`Frame`].
+const MIN_FRAME_SECONDS: f32 = 1e-3;
+
+/// How a [`Clip`] plays once it reaches its last frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Playback {
+    /// Holds the last frame.
+    Once,
+
+    /// Restarts from the first frame.
+    #[default]
+    Loop,
+
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong,
+}
+
+/// A single frame of a [`Clip`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// Region of the sprite sheet's texture, in pixels.
+    pub source: Rect,
+
+    /// Seconds the frame is shown for.
+    pub duration: f32,
+
+    /// Names of events fired when the frame is shown.
+    pub events: Vec<String>,
+}
+
+/// An animation with a list of [`Frame`]s per [`Facing`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Clip {
+    /// How the clip plays past its last frame.
+    pub playback: Playback,
+
+    /// Frames for each facing, indexed like [`Facing::ALL`].
+    directions: [Vec<Frame>; 8],
+
+    /// Frames for facings without frames of their own.
+    fallback: Vec<Frame>,
+}
+
+impl Clip {
+    /// Returns a new clip without any frames.
+    pub fn new(playback: Playback) -> Self {
+        Self {
+            playback,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the frames shown while `facing`.
+    pub fn set_frames(&mut self, facing: Facing, frames: Vec<Frame>) {
+        self.directions[facing.index()] = frames;
+    }
+
+    /// Sets the frames shown for facings
+    /// without frames (or a mirror) of their own.
+    pub fn set_fallback_frames(&mut self, frames: Vec<Frame>) {
+        self.fallback = frames;
+    }
+
+    /// Returns the frames shown while `facing`, and
+    /// true if the frames should be drawn flipped.
+    ///
+    /// Facings without frames of their own borrow the (flipped)
+    /// frames of their mirror image, or else the fallback frames.
+    pub fn frames(&self, facing: Facing) -> (&[Frame], bool) {
+        let frames = &self.directions[facing.index()];
+        if !frames.is_empty() {
+            return (frames, false);
+        }
+
+        let mirrored = &self.directions[facing.mirrored().index()];
+        if !mirrored.is_empty() {
+            return (mirrored, true);
+        }
+
+        (&self.fallback, false)
+    }
+}
+
+/// Textures and [`Clip`]s loaded from a sprite sheet.
+#[derive(Clone, PartialEq)]
+pub struct SpriteSheet {
+    /// Texture containing every frame.
+    pub texture: Texture2D,
+
+    /// Clips, by name.
+    pub clips: HashMap<String, Clip>,
+}
+
+impl SpriteSheet {
+    /// Loads a sprite sheet from the image in `bytes`,
+    /// with the clips described by `descriptor`.
+    ///
+    /// The format of the image will be auto-detected
+    /// so long as it is one of [ImageFormat][image::ImageFormat].
+    pub fn from_bytes(bytes: &[u8], descriptor: &str) -> Result<Self, SheetError> {
+        let texture = Texture2D::from_file_with_format(bytes, None);
+        texture.set_filter(macroquad::texture::FilterMode::Nearest);
+        let clips = parse_clips(descriptor, texture.width())?;
+
+        Ok(Self { texture, clips })
+    }
+
+    /// Returns the clip named `name`.
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+}
+
+/// Error in a sprite sheet descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SheetError {
+    /// Line (counting from one) the error is on.
+    pub line: usize,
+
+    /// Description of the error.
+    pub message: String,
+}
+
+impl Display for SheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SheetError {}
+
+/// Parses the clips in `descriptor`, for a
+/// sprite sheet `sheet_width` pixels wide.
+pub fn parse_clips(
+    descriptor: &str,
+    sheet_width: f32,
+) -> Result<HashMap<String, Clip>, SheetError> {
+    let mut clips = HashMap::new();
+    let mut cell = (32.0, 32.0);
+    let mut clip: Option<(String, Clip, f32)> = None;
+
+    for (number, line) in descriptor.lines().enumerate() {
+        let error = |message: String| SheetError {
+            line: number + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+
+        match keyword {
+            "cell" => {
+                let mut size = || {
+                    words
+                        .next()
+                        .and_then(|word| word.parse::<f32>().ok())
+                        .filter(|size| *size > 0.0)
+                        .ok_or_else(|| error("expected `cell <width> <height>`".into()))
+                };
+                cell = (size()?, size()?);
+            }
+            "clip" => {
+                let usage = || error("expected `clip <name> <playback> <seconds>`".into());
+                let name = words.next().ok_or_else(usage)?;
+                let playback = match words.next().ok_or_else(usage)? {
+                    "once" => Playback::Once,
+                    "loop" => Playback::Loop,
+                    "pingpong" => Playback::PingPong,
+                    other => return Err(error(format!("unknown playback `{other}`"))),
+                };
+                let duration = words
+                    .next()
+                    .and_then(|word| word.parse().ok())
+                    .ok_or_else(usage)?;
+
+                if let Some((name, clip, _)) = clip.take() {
+                    clips.insert(name, clip);
+                }
+                clip = Some((name.to_string(), Clip::new(playback), duration));
+            }
+            facing => {
+                let Some((_, clip, duration)) = &mut clip else {
+                    return Err(error(format!("`{facing}` frames outside of a clip")));
+                };
+
+                let columns = (sheet_width / cell.0).floor().max(1.0) as usize;
+                let frames = words
+                    .map(|word| parse_frame(word, cell, columns, *duration))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error(format!("malformed frames for `{facing}`")))?;
+
+                if facing == "*" {
+                    clip.set_fallback_frames(frames);
+                } else {
+                    let facing = Facing::from_abbreviation(facing)
+                        .ok_or_else(|| error(format!("unknown facing `{facing}`")))?;
+                    clip.set_frames(facing, frames);
+                }
+            }
+        }
+    }
+
+    if let Some((name, clip, _)) = clip {
+        clips.insert(name, clip);
+    }
+
+    Ok(clips)
+}
+
+/// Parses a `<cell>[@seconds][!event]...` frame.
+fn parse_frame(word: &str, cell: (f32, f32), columns: usize, duration: f32) -> Option<Frame> {
+    let mut events = word.split('!');
+    let mut timing = events.next()?.split('@');
+    let index: usize = timing.next()?.parse().ok()?;
+    let duration = match timing.next() {
+        Some(seconds) => seconds.parse().ok()?,
+        None => duration,
+    };
+
+    Some(Frame {
+        source: Rect::new(
+            (index % columns) as f32 * cell.0,
+            (index / columns) as f32 * cell.1,
+            cell.0,
+            cell.1,
+        ),
+        duration,
+        events: events.map(String::from).collect(),
+    })
+}
+
+/// Plays one [`Clip`] at a time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClipPlayer {
+    /// Name of the clip being played.
+    clip: Option<String>,
+
+    /// Seconds since the clip started.
+    elapsed: f32,
+
+    /// Step (see [`step_at`]) shown after the
+    /// last update, if the clip has been updated.
+    step: Option<usize>,
+
+    /// Events fired during the last update.
+    events: Vec<String>,
+}
+
+impl ClipPlayer {
+    /// Returns the name of the clip being played.
+    pub fn clip(&self) -> Option<&str> {
+        self.clip.as_deref()
+    }
+
+    /// Starts playing the clip named `name`
+    /// from its first frame, unless it's
+    /// already playing.
+    pub fn play(&mut self, name: &str) {
+        if self.clip.as_deref() != Some(name) {
+            self.clip = Some(name.to_string());
+            self.elapsed = 0.0;
+            self.step = None;
+        }
+    }
+
+    /// Advances the clip in `sheet` by `frame_time` seconds,
+    /// firing the events of every frame shown on the way.
+    pub fn update(&mut self, sheet: &SpriteSheet, facing: Facing, frame_time: f32) {
+        self.events.clear();
+        let Some(clip) = self.clip.as_deref().and_then(|name| sheet.clip(name)) else {
+            return;
+        };
+
+        let (frames, _) = clip.frames(facing);
+        self.elapsed += frame_time;
+
+        let step = step_at(frames, clip.playback, self.elapsed);
+        let first = self.step.map_or(0, |step| step + 1);
+        let sequence = sequence(frames.len(), clip.playback);
+        for step in first..=step {
+            if let Some(index) = frame_index(&sequence, step) {
+                self.events.extend(frames[index].events.iter().cloned());
+            }
+        }
+
+        self.step = Some(step);
+    }
+
+    /// Returns the frame of the clip in `sheet` currently shown
+    /// while `facing`, and true if it should be drawn flipped.
+    pub fn frame<'a>(&self, sheet: &'a SpriteSheet, facing: Facing) -> Option<(&'a Frame, bool)> {
+        let clip = sheet.clip(self.clip.as_deref()?)?;
+        let (frames, flip_x) = clip.frames(facing);
+        let sequence = sequence(frames.len(), clip.playback);
+        let step = step_at(frames, clip.playback, self.elapsed);
+
+        Some((&frames[frame_index(&sequence, step)?], flip_x))
+    }
+
+    /// Returns the events fired during the last update.
+    pub fn events(&self) -> &[String] {
+        &self.events
+    }
+}
+
+/// Returns the indices of the frames shown,
+/// in order, during one cycle of a clip.
+fn sequence(frames: usize, playback: Playback) -> Vec<usize> {
+    let mut sequence: Vec<usize> = (0..frames).collect();
+    if playback == Playback::PingPong && frames > 2 {
+        sequence.extend((1..frames - 1).rev());
+    }
+
+    sequence
+}
+
+/// Returns the index of the frame shown at `step`.
+fn frame_index(sequence: &[usize], step: usize) -> Option<usize> {
+    if sequence.is_empty() {
+        return None;
+    }
+
+    Some(sequence[step % sequence.len()])
+}
+
+/// Returns the number of frame changes between the
+/// start of a clip and `elapsed` seconds into it.
+fn step_at(frames: &[Frame], playback: Playback, elapsed: f32) -> usize {
+    let sequence = sequence(frames.len(), playback);
+    if sequence.is_empty() {
+        return 0;
+    }
+
+    let duration = |step: usize| {
+        frames[sequence[step % sequence.len()]]
+            .duration
+            .max(MIN_FRAME_SECONDS)
+    };
+    let cycle: f32 = (0..sequence.len()).map(duration).sum();
+
+    // Skip whole cycles of looping clips.
+    let (mut step, mut remaining) = match playback {
+        Playback::Once => (0, elapsed),
+        _ => {
+            let cycles = (elapsed / cycle).floor();
+            (cycles as usize * sequence.len(), elapsed - cycles * cycle)
+        }
+    };
+
+    while remaining >= duration(step) {
+        if playback == Playback::Once && step + 1 == sequence.len() {
+            break;
+        }
+
+        remaining -= duration(step);
+        step += 1;
+    }
+
+    step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTOR: &str = "
+        cell 16 16
+        clip walk pingpong 0.1
+        s 0 1!step 2@0.2
+        e 4
+        clip jump once 0.5 # no directions
+        * 5 6
+    ";
+
+    #[test]
+    fn parses_descriptors() {
+        let clips = parse_clips(DESCRIPTOR, 64.0).unwrap();
+
+        let walk = &clips["walk"];
+        assert_eq!(Playback::PingPong, walk.playback);
+        let (frames, flip_x) = walk.frames(Facing::South);
+        assert!(!flip_x);
+        assert_eq!(vec![String::from("step")], frames[1].events);
+        assert_eq!(0.2, frames[2].duration);
+
+        // Cells wrap at the sheet's width.
+        let (frames, flip_x) = walk.frames(Facing::West);
+        assert!(flip_x);
+        assert_eq!(Rect::new(0.0, 16.0, 16.0, 16.0), frames[0].source);
+
+        let (frames, _) = clips["jump"].frames(Facing::North);
+        assert_eq!(2, frames.len());
+
+        let error = parse_clips("clip a loop 0.1\nq 0", 64.0).unwrap_err();
+        assert_eq!(2, error.line);
+    }
+
+    #[test]
+    fn steps_through_playback_modes() {
+        let clips = parse_clips(DESCRIPTOR, 64.0).unwrap();
+        let (frames, _) = clips["walk"].frames(Facing::South);
+        let sequence = sequence(frames.len(), Playback::PingPong);
+        assert_eq!(vec![0, 1, 2, 1], sequence);
+
+        // Frames 0, 1, 2 (held twice as long), 1, then 0 again.
+        let frame = |elapsed| frame_index(&sequence, step_at(frames, Playback::PingPong, elapsed));
+        assert_eq!(Some(1), frame(0.15));
+        assert_eq!(Some(2), frame(0.35));
+        assert_eq!(Some(1), frame(0.45));
+        assert_eq!(Some(0), frame(0.55));
+
+        // Clips played once hold their last frame.
+        assert_eq!(1, step_at(frames, Playback::Once, 0.15));
+        assert_eq!(2, step_at(frames, Playback::Once, 100.0));
+    }
+}
@@ -0,0 +1,288 @@
+//! Stack of game states, like menus, levels and results screens.
+//!
+//! Only the topmost [`State`] in a [`StateStack`] is updated;
+//! states beneath it are paused. Overlay states (like pause
+//! menus) are drawn over the states beneath them, while
+//! opaque states hide everything beneath them.
+//!
+//! States move between each other declaratively, by
+//! returning a [`Transition`] from [`State::update`].
+
+/// A state of a game with shared context `C`.
+pub trait State<C> {
+    /// Called when the state is added to a stack.
+    fn enter(&mut self, _context: &mut C) {}
+
+    /// Called when the state is removed from a stack.
+    fn exit(&mut self, _context: &mut C) {}
+
+    /// Called when another state is pushed above this one.
+    fn pause(&mut self, _context: &mut C) {}
+
+    /// Called when this state becomes
+    /// the topmost state again.
+    fn resume(&mut self, _context: &mut C) {}
+
+    /// Advances the state by `frame_time` seconds, returning
+    /// the transition the stack should make (if any).
+    fn update(&mut self, context: &mut C, frame_time: f32) -> Transition<C>;
+
+    /// Draws the state.
+    fn draw(&mut self, _context: &mut C) {}
+
+    /// Returns true if the states beneath
+    /// this one should be drawn beneath it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// A change to a [`StateStack`].
+pub enum Transition<C> {
+    /// Keeps the current state.
+    None,
+
+    /// Pauses the current state and enters a new state above it.
+    Push(Box<dyn State<C>>),
+
+    /// Exits the current state, resuming the state beneath it.
+    Pop,
+
+    /// Exits the current state and enters a new state in its place.
+    Replace(Box<dyn State<C>>),
+
+    /// Exits every state and enters a new state.
+    Reset(Box<dyn State<C>>),
+}
+
+impl<C> Transition<C> {
+    /// Returns a transition pushing `state`.
+    pub fn push(state: impl State<C> + 'static) -> Self {
+        Self::Push(Box::new(state))
+    }
+
+    /// Returns a transition replacing the current state with `state`.
+    pub fn replace(state: impl State<C> + 'static) -> Self {
+        Self::Replace(Box::new(state))
+    }
+
+    /// Returns a transition replacing every state with `state`.
+    pub fn reset(state: impl State<C> + 'static) -> Self {
+        Self::Reset(Box::new(state))
+    }
+}
+
+/// A stack of [`State`]s sharing context `C`.
+pub struct StateStack<C> {
+    /// States, bottommost first.
+    states: Vec<Box<dyn State<C>>>,
+}
+
+impl<C> Default for StateStack<C> {
+    fn default() -> Self {
+        Self { states: vec![] }
+    }
+}
+
+impl<C> StateStack<C> {
+    /// Returns a stack entering `state`.
+    pub fn new(context: &mut C, state: impl State<C> + 'static) -> Self {
+        let mut stack = Self::default();
+        stack.push(context, Box::new(state));
+        stack
+    }
+
+    /// Pauses the topmost state and enters `state` above it.
+    pub fn push(&mut self, context: &mut C, mut state: Box<dyn State<C>>) {
+        if let Some(top) = self.states.last_mut() {
+            top.pause(context);
+        }
+
+        state.enter(context);
+        self.states.push(state);
+    }
+
+    /// Exits and returns the topmost state,
+    /// resuming the state beneath it.
+    pub fn pop(&mut self, context: &mut C) -> Option<Box<dyn State<C>>> {
+        let mut state = self.states.pop()?;
+        state.exit(context);
+
+        if let Some(top) = self.states.last_mut() {
+            top.resume(context);
+        }
+
+        Some(state)
+    }
+
+    /// Exits the topmost state and enters `state` in its place.
+    pub fn replace(&mut self, context: &mut C, mut state: Box<dyn State<C>>) {
+        if let Some(mut top) = self.states.pop() {
+            top.exit(context);
+        }
+
+        state.enter(context);
+        self.states.push(state);
+    }
+
+    /// Exits every state, topmost first, and enters `state`.
+    pub fn reset(&mut self, context: &mut C, mut state: Box<dyn State<C>>) {
+        while let Some(mut top) = self.states.pop() {
+            top.exit(context);
+        }
+
+        state.enter(context);
+        self.states.push(state);
+    }
+
+    /// Makes `transition`.
+    pub fn apply(&mut self, context: &mut C, transition: Transition<C>) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(state) => self.push(context, state),
+            Transition::Pop => {
+                self.pop(context);
+            }
+            Transition::Replace(state) => self.replace(context, state),
+            Transition::Reset(state) => self.reset(context, state),
+        }
+    }
+
+    /// Updates the topmost state by `frame_time`
+    /// seconds, making any transition it returns.
+    pub fn update(&mut self, context: &mut C, frame_time: f32) {
+        let Some(top) = self.states.last_mut() else {
+            return;
+        };
+
+        let transition = top.update(context, frame_time);
+        self.apply(context, transition);
+    }
+
+    /// Draws the topmost state, and the states beneath
+    /// it for as long as the states above are overlays.
+    pub fn draw(&mut self, context: &mut C) {
+        let bottom = self
+            .states
+            .iter()
+            .rposition(|state| !state.is_overlay())
+            .unwrap_or(0);
+
+        for state in &mut self.states[bottom..] {
+            state.draw(context);
+        }
+    }
+
+    /// Returns the number of states in the stack.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns true if there are no states in the stack.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// State logging its hooks, and returning
+    /// transitions queued in the log's context.
+    struct Logged {
+        name: &'static str,
+        overlay: bool,
+    }
+
+    #[derive(Default)]
+    struct Log {
+        events: Vec<String>,
+        next: Option<Transition<Log>>,
+    }
+
+    impl Logged {
+        fn new(name: &'static str, overlay: bool) -> Self {
+            Self { name, overlay }
+        }
+
+        fn log(&self, log: &mut Log, event: &str) {
+            log.events.push(format!("{} {event}", self.name));
+        }
+    }
+
+    impl State<Log> for Logged {
+        fn enter(&mut self, log: &mut Log) {
+            self.log(log, "enter");
+        }
+
+        fn exit(&mut self, log: &mut Log) {
+            self.log(log, "exit");
+        }
+
+        fn pause(&mut self, log: &mut Log) {
+            self.log(log, "pause");
+        }
+
+        fn resume(&mut self, log: &mut Log) {
+            self.log(log, "resume");
+        }
+
+        fn update(&mut self, log: &mut Log, _: f32) -> Transition<Log> {
+            self.log(log, "update");
+            log.next.take().unwrap_or(Transition::None)
+        }
+
+        fn draw(&mut self, log: &mut Log) {
+            self.log(log, "draw");
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+    }
+
+    #[test]
+    fn overlays_pause_states_beneath() {
+        let mut log = Log::default();
+        let mut stack = StateStack::new(&mut log, Logged::new("level", false));
+
+        log.next = Some(Transition::push(Logged::new("menu", true)));
+        stack.update(&mut log, 0.0);
+        log.events.clear();
+
+        stack.update(&mut log, 0.0);
+        stack.draw(&mut log);
+        assert_eq!(vec!["menu update", "level draw", "menu draw"], log.events);
+        log.events.clear();
+
+        log.next = Some(Transition::Pop);
+        stack.update(&mut log, 0.0);
+        assert_eq!(vec!["menu update", "menu exit", "level resume"], log.events);
+    }
+
+    #[test]
+    fn opaque_states_hide_states_beneath() {
+        let mut log = Log::default();
+        let mut stack = StateStack::new(&mut log, Logged::new("level", false));
+        stack.push(&mut log, Box::new(Logged::new("results", false)));
+        log.events.clear();
+
+        stack.draw(&mut log);
+        assert_eq!(vec!["results draw"], log.events);
+        log.events.clear();
+
+        log.next = Some(Transition::reset(Logged::new("title", false)));
+        stack.update(&mut log, 0.0);
+        assert_eq!(
+            vec![
+                "results update",
+                "results exit",
+                "level exit",
+                "title enter"
+            ],
+            log.events
+        );
+        assert_eq!(1, stack.len());
+    }
+}
@@ -0,0 +1,152 @@
+//! Storage of data persisting between sessions.
+//!
+//! [`Storage`] keeps text by key in a config directory on
+//! native builds, and in the browser's `localStorage` on
+//! the web (via the plugin in `web/storage.js`). Data kept
+//! in storage, like a player's [`Save`], is versioned so
+//! older data can be migrated as its format changes.
+pub mod save;
+
+pub use save::{Save, SaveError, Settings};
+
+/// Text stored by key for an app.
+#[derive(Clone, Debug)]
+pub struct Storage {
+    /// Directory the text of each key is stored in.
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: std::path::PathBuf,
+
+    /// Prefix of every key in `localStorage`.
+    #[cfg(target_arch = "wasm32")]
+    prefix: String,
+}
+
+impl Storage {
+    /// Returns storage for the app named `app`.
+    ///
+    /// Native builds store text in a directory named `app`
+    /// in the platform's config directory, or in the
+    /// working directory if there's no config directory.
+    pub fn new(app: &str) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Self::in_dir(config_dir().unwrap_or_default().join(app));
+
+        #[cfg(target_arch = "wasm32")]
+        return Self {
+            prefix: format!("{app}/"),
+        };
+    }
+
+    /// Returns storage keeping text in `dir`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn in_dir(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the text stored at `key`, if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(self.dir.join(key)).ok()
+    }
+
+    /// Stores `text` at `key`, replacing any stored text.
+    ///
+    /// Text is written beside the stored text and then moved
+    /// over it, so interrupted writes keep the stored text.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write(&self, key: &str, text: &str) -> std::io::Result<()> {
+        let path = self.dir.join(key);
+        let partial = self.dir.join(format!("{key}.partial"));
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&partial, text)?;
+        std::fs::rename(partial, path)
+    }
+
+    /// Returns the text stored at `key`, if any.
+    #[cfg(target_arch = "wasm32")]
+    pub fn read(&self, key: &str) -> Option<String> {
+        let key = format!("{}{key}", self.prefix);
+        unsafe {
+            let len = web::layered_storage_len(key.as_ptr(), key.len());
+            if len < 0 {
+                return None;
+            }
+
+            let mut bytes = vec![0; len as usize];
+            web::layered_storage_read(key.as_ptr(), key.len(), bytes.as_mut_ptr(), bytes.len());
+            String::from_utf8(bytes).ok()
+        }
+    }
+
+    /// Stores `text` at `key`, replacing any stored text.
+    #[cfg(target_arch = "wasm32")]
+    pub fn write(&self, key: &str, text: &str) -> std::io::Result<()> {
+        let key = format!("{}{key}", self.prefix);
+        let stored = unsafe {
+            web::layered_storage_write(key.as_ptr(), key.len(), text.as_ptr(), text.len())
+        };
+        if stored == 0 {
+            return Err(std::io::Error::other("localStorage is unavailable or full"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the platform's directory for per-user config.
+#[cfg(not(target_arch = "wasm32"))]
+fn config_dir() -> Option<std::path::PathBuf> {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(std::path::PathBuf::from)
+    };
+
+    if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    }
+}
+
+/// `localStorage` functions imported from `web/storage.js`.
+#[cfg(target_arch = "wasm32")]
+mod web {
+    extern "C" {
+        /// Returns the length, in UTF-8 bytes, of the
+        /// item at `key`, or `-1` if there's no item.
+        pub fn layered_storage_len(key: *const u8, key_len: usize) -> i32;
+
+        /// Copies the UTF-8 bytes of the item at `key` into `buf`.
+        pub fn layered_storage_read(key: *const u8, key_len: usize, buf: *mut u8, buf_len: usize);
+
+        /// Sets the item at `key` to `value`,
+        /// returning `0` if it couldn't be set.
+        pub fn layered_storage_write(
+            key: *const u8,
+            key_len: usize,
+            value: *const u8,
+            value_len: usize,
+        ) -> i32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_text_by_key() {
+        let dir = std::env::temp_dir().join(format!("layered-storage-{}", std::process::id()));
+        let storage = Storage::in_dir(&dir);
+        assert_eq!(None, storage.read("save"));
+
+        storage.write("save", "version 1").unwrap();
+        storage.write("save", "version 2").unwrap();
+        assert_eq!(Some("version 2".into()), storage.read("save"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
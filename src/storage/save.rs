@@ -0,0 +1,272 @@
+//! Versioned saves of a player's settings and progress.
+//!
+//! Saves are text, one setting per line, starting with the
+//! `version` of the format they were written in:
+//!
+//! ```text
+//! version 1
+//! master_volume 0.8
+//! muted false
+//! bus_volume music 0.5
+//! debug false
+//! bind mute key:M
+//! unlocked layer-2
+//! best_time layer-1 12.5
+//! ```
+//!
+//! Saves written in older versions are upgraded by
+//! [`MIGRATIONS`] as they're read, so changes to the format
+//! add a migration rather than breaking older saves.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+
+use crate::audio::Bus;
+use crate::input::Binding;
+
+/// Upgrades the lines of a save (after its `version`
+/// line) from one version of the format to the next.
+pub type Migration = fn(&str) -> String;
+
+/// Migrations of saves written in older versions, in order;
+/// the first migration upgrades version 1 saves to version 2.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Version of the format saves are written in.
+pub const VERSION: usize = MIGRATIONS.len() + 1;
+
+/// Error in a save.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveError {
+    /// Line (counting from one) the error is on,
+    /// or zero if the error isn't on a line.
+    pub line: usize,
+
+    /// Description of the error.
+    pub message: String,
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// A player's preferences.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// Master volume (`0.0..=1.0`).
+    pub master_volume: f32,
+
+    /// True if all audio is muted.
+    pub muted: bool,
+
+    /// Volumes (`0.0..=1.0`) of buses, by bus.
+    pub bus_volumes: HashMap<Bus, f32>,
+
+    /// True if the debugger is shown.
+    pub debug: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            muted: false,
+            bus_volumes: Bus::ALL.iter().map(|bus| (*bus, 1.0)).collect(),
+            debug: false,
+        }
+    }
+}
+
+/// A player's settings and progress.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Save {
+    pub settings: Settings,
+
+    /// Bindings of actions rebound from their defaults, by action name.
+    pub keybinds: BTreeMap<String, Vec<Binding>>,
+
+    /// Names of unlocked levels.
+    pub unlocked: BTreeSet<String>,
+
+    /// Fastest times, in seconds, levels were completed in, by level name.
+    pub best_times: BTreeMap<String, f64>,
+}
+
+impl Save {
+    /// Returns the save in `text`, migrating
+    /// it from older versions if needed.
+    pub fn from_text(text: &str) -> Result<Self, SaveError> {
+        let text = migrate(text, MIGRATIONS)?;
+        let mut save = Self::default();
+
+        for (number, line) in text.lines().enumerate().skip(1) {
+            let error = |message: String| SaveError {
+                line: number + 1,
+                message,
+            };
+
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(key) = words.next() else {
+                continue;
+            };
+            let values: Vec<&str> = words.collect();
+
+            match (key, &values[..]) {
+                ("master_volume", [volume]) => {
+                    save.settings.master_volume = parse(volume).map_err(error)?;
+                }
+                ("muted", [muted]) => {
+                    save.settings.muted = parse(muted).map_err(error)?;
+                }
+                ("bus_volume", [bus, volume]) => {
+                    let bus = Bus::ALL
+                        .into_iter()
+                        .find(|b| b.name() == *bus)
+                        .ok_or_else(|| error(format!("unknown bus `{bus}`")))?;
+                    let volume = parse(volume).map_err(error)?;
+                    save.settings.bus_volumes.insert(bus, volume);
+                }
+                ("debug", [debug]) => {
+                    save.settings.debug = parse(debug).map_err(error)?;
+                }
+                ("bind", [action, bindings @ ..]) => {
+                    let bindings = bindings
+                        .iter()
+                        .map(|word| {
+                            Binding::parse(word)
+                                .ok_or_else(|| error(format!("unknown binding `{word}`")))
+                        })
+                        .collect::<Result<_, _>>()?;
+                    save.keybinds.insert(action.to_string(), bindings);
+                }
+                ("unlocked", [level]) => {
+                    save.unlocked.insert(level.to_string());
+                }
+                ("best_time", [level, seconds]) => {
+                    let seconds = parse(seconds).map_err(error)?;
+                    save.best_times.insert(level.to_string(), seconds);
+                }
+                _ => return Err(error(format!("malformed line `{}`", line.trim()))),
+            }
+        }
+
+        Ok(save)
+    }
+
+    /// Returns the save as text read by [`Self::from_text`].
+    pub fn to_text(&self) -> String {
+        let settings = &self.settings;
+        let mut text = format!("version {VERSION}\n");
+        text.push_str(&format!("master_volume {}\n", settings.master_volume));
+        text.push_str(&format!("muted {}\n", settings.muted));
+        for bus in Bus::ALL {
+            if let Some(volume) = settings.bus_volumes.get(&bus) {
+                text.push_str(&format!("bus_volume {} {volume}\n", bus.name()));
+            }
+        }
+        text.push_str(&format!("debug {}\n", settings.debug));
+
+        for (action, bindings) in &self.keybinds {
+            text.push_str(&format!("bind {action}"));
+            for binding in bindings {
+                text.push_str(&format!(" {binding}"));
+            }
+            text.push('\n');
+        }
+        for level in &self.unlocked {
+            text.push_str(&format!("unlocked {level}\n"));
+        }
+        for (level, seconds) in &self.best_times {
+            text.push_str(&format!("best_time {level} {seconds}\n"));
+        }
+
+        text
+    }
+
+    /// Records that the level named `level` was completed in
+    /// `seconds`, returning true if it's the level's best time.
+    pub fn record_time(&mut self, level: &str, seconds: f64) -> bool {
+        match self.best_times.get(level) {
+            Some(best) if *best <= seconds => false,
+            _ => {
+                self.best_times.insert(level.to_string(), seconds);
+                true
+            }
+        }
+    }
+}
+
+/// Returns `save` upgraded by `migrations` to their latest
+/// version, with its `version` line at the latest version.
+fn migrate(save: &str, migrations: &[Migration]) -> Result<String, SaveError> {
+    let latest = migrations.len() + 1;
+    let (version, lines) = save.split_once('\n').unwrap_or((save, ""));
+
+    let version = match version.split_whitespace().collect::<Vec<_>>()[..] {
+        ["version", version] => parse::<usize>(version),
+        _ => Err("save doesn't start with a version".into()),
+    }
+    .map_err(|message| SaveError { line: 1, message })?;
+    if version == 0 || version > latest {
+        return Err(SaveError {
+            line: 1,
+            message: format!("unsupported version {version}; the latest is {latest}"),
+        });
+    }
+
+    let mut lines = lines.to_string();
+    for migration in &migrations[version - 1..] {
+        lines = migration(&lines);
+    }
+
+    Ok(format!("version {latest}\n{lines}"))
+}
+
+/// Parses `value`, describing any error.
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_round_trip_through_text() {
+        let mut save = Save::default();
+        save.settings.muted = true;
+        save.settings.bus_volumes.insert(Bus::Music, 0.5);
+        save.keybinds
+            .insert("mute".into(), vec![Binding::parse("key:N").unwrap()]);
+        save.unlocked.insert("layer-2".into());
+        assert!(save.record_time("layer-1", 12.5));
+        assert!(!save.record_time("layer-1", 13.0));
+
+        assert_eq!(save, Save::from_text(&save.to_text()).unwrap());
+
+        let newer = format!("version {}\n", VERSION + 1);
+        assert_eq!(1, Save::from_text(&newer).unwrap_err().line);
+    }
+
+    #[test]
+    fn migrates_older_saves_in_order() {
+        let migrations: &[Migration] = &[
+            |lines| lines.replace("volume", "master_volume"),
+            |lines| format!("{lines}debug false\n"),
+        ];
+
+        let save = "version 1\nvolume 0.5\n";
+        let migrated = "version 3\nmaster_volume 0.5\ndebug false\n";
+        assert_eq!(migrated, migrate(save, migrations).unwrap());
+
+        let save = "version 2\nmaster_volume 0.5\n";
+        assert_eq!(migrated, migrate(save, migrations).unwrap());
+        assert!(migrate("master_volume 0.5\n", migrations).is_err());
+    }
+}
@@ -1,8 +1,8 @@
 //! Tile-based, 2.5D dimetric grid system.
 use std::collections::BTreeMap;
 
-use glam::{Mat2, Vec2};
-use image::{DynamicImage, GenericImageView};
+use glam::{Mat2, Vec2, Vec3};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
 use macroquad::{
     color::{GRAY, WHITE},
     math::Rect,
@@ -13,6 +13,18 @@ use palette::WithAlpha;
 
 use crate::color::{self, *};
 
+pub mod collision;
+pub mod cursor;
+pub mod cutaway;
+pub mod history;
+pub mod layer;
+pub mod shape;
+
+pub use collision::{Collider, Sweep};
+pub use cursor::{EditorTool, Selection, TileEditor};
+pub use cutaway::{Cutaway, CutawayMode};
+pub use layer::Layer;
+
 // When tightly packed, tiles in dimetric
 // projections are, visually, twice as wide
 // and half as tall.
@@ -24,6 +36,22 @@ const ISO_Y_COEFF: f32 = 0.25;
 const I_HAT: Vec2 = Vec2::new(ISO_X_COEFF, ISO_Y_COEFF);
 const J_HAT: Vec2 = Vec2::new(-ISO_X_COEFF, ISO_Y_COEFF);
 
+// Symbols of the plain-text map format, paired with
+// the bitmap colors they stand in for; any other
+// symbol is a plain floor.
+const MAP_SYMBOLS: [(char, Color); 4] = [
+    ('#', color::BACKGROUND),
+    ('o', color::ACCENT_1),
+    ('x', color::ACCENT_2),
+    ('@', color::ACCENT_3),
+];
+const MAP_FLOOR_SYMBOL: char = '.';
+const MAP_EMPTY_SYMBOL: char = '_';
+
+// Bitmap colors of plain floors, and of empty tiles.
+const BITMAP_FLOOR_COLOR: [u8; 4] = [0, 0, 0, 0];
+const BITMAP_EMPTY_COLOR: [u8; 4] = [255, 255, 255, 255];
+
 /// 2D texture assigned to a [`Tile`].
 #[derive(PartialEq, Clone)]
 pub struct TileTexture {
@@ -67,6 +95,17 @@ impl TileTexture {
         Self { texture }
     }
 
+    /// Returns a tile texture drawing `texture`.
+    pub fn from_texture(texture: Texture2D) -> Self {
+        texture.set_filter(FilterMode::Linear);
+        Self { texture }
+    }
+
+    /// Returns the underlying texture.
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
     /// Draws the texture.
     pub fn draw(&self, x: f32, y: f32, size: Vec2, blend_color: Option<Color>) {
         let color = *blend_color.as_ref().unwrap_or(&color::DEFAULT);
@@ -87,6 +126,7 @@ impl TileTexture {
 }
 
 /// A tile in a [`TileMap`].
+#[derive(Clone, PartialEq)]
 pub enum Tile {
     /// A filled tile which may be rendered.
     Filled {
@@ -120,7 +160,22 @@ pub struct TileMap {
     ///
     /// Each layer contains a dense vector of
     /// [`Tile`]s of a length equal to [`Self::tiles_per_layer`]
-    layers: BTreeMap<i8, Vec<Tile>>,
+    layers: BTreeMap<i8, Layer>,
+
+    /// Layer the viewer is focused on, if any.
+    pub active_layer: Option<i8>,
+
+    /// Opacity applied to light-tinted tiles (like plain floors)
+    /// on layers below the [`Self::active_layer`] ("ghosting"),
+    /// unless overridden by [`Layer::ghost_opacity`]; darker
+    /// and untinted tiles, like walls, stay opaque.
+    pub ghost_opacity: Option<f32>,
+
+    /// Cutaway rendering settings, if enabled.
+    pub cutaway: Option<Cutaway>,
+
+    /// Focus and fading tiles tracked by the [`Self::cutaway`].
+    cutaway_state: cutaway::CutawayState,
 
     /// True if debugging info should be drawn.
     pub draw_debug_info: bool,
@@ -133,8 +188,28 @@ pub struct TileMap {
 }
 
 impl TileMap {
+    /// Returns the map's width, in tiles.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the map's height, in tiles.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     /// Returns a new map with `width` x `height` tiles.
     pub fn new(width: usize, height: usize) -> Self {
+        let view_size = Vec2::new(
+            macroquad::prelude::screen_width(),
+            macroquad::prelude::screen_height(),
+        );
+        Self::with_view_size(width, height, view_size)
+    }
+
+    /// Returns a new map with `width` x `height` tiles,
+    /// framed for a view of `view_size`.
+    pub fn with_view_size(width: usize, height: usize, view_size: Vec2) -> Self {
         let mut map = Self {
             width,
             height,
@@ -143,9 +218,12 @@ impl TileMap {
             viewport_scale: 1.0f32,
             viewport_offset: Vec2::default(),
             layers: Default::default(),
+            active_layer: None,
+            ghost_opacity: None,
+            cutaway: None,
+            cutaway_state: Default::default(),
         };
 
-        let view_size = map.calculate_view_size();
         map.viewport_offset.y -= (view_size.y / height as f32) * 3.0;
 
         map
@@ -159,9 +237,23 @@ impl TileMap {
         // Recalculate current viewport and tile sizes.
         let tile_size = self.calculate_tile_size();
 
+        // Track tiles hiding the cutaway focus.
+        let frame_time = macroquad::prelude::get_frame_time();
+        let focus_rect = self.cutaway_focus_rect();
+        let mut cutaway_state = std::mem::take(&mut self.cutaway_state);
+
         // Draw tiles.
         for (layer_height, layer) in &self.layers {
-            for (i, layer) in layer.iter().enumerate().take(self.tiles_per_layer) {
+            let opacity =
+                self.layer_opacity(*layer_height) * self.cutaway_layer_opacity(*layer_height);
+            if !layer.visible || opacity <= 0.0 {
+                continue;
+            }
+
+            let layer_offset = layer.offset * tile_size;
+            let ghost_opacity = self.ghost_opacity(*layer_height);
+
+            for (i, tile) in layer.tiles.iter().enumerate().take(self.tiles_per_layer) {
                 // Convert tile index into logical x/y coordinates.
                 let x = i / self.height;
                 let y = i % self.height;
@@ -171,24 +263,49 @@ impl TileMap {
                     texture,
                     height_offset,
                     blend_color,
-                } = layer
+                } = tile
                 {
-                    let view_point = self.grid_to_view(x as f32, y as f32, *layer_height);
+                    let view_point =
+                        self.grid_to_view(x as f32, y as f32, *layer_height) + layer_offset;
 
                     // Offset by any manual offsets specified for the tile.
-                    let height_offset = tile_size.y * height_offset.unwrap_or(0.0) * -1.0;
+                    let height_offset = -(tile_size.y * height_offset.unwrap_or(0.0));
+
+                    let view_point = view_point + Vec2::new(0.0, height_offset);
+
+                    // Fade tiles drawn over the cutaway focus.
+                    let cutaway_opacity = match (&self.cutaway, focus_rect) {
+                        (Some(cutaway), Some(focus_rect)) => {
+                            let tile_rect =
+                                Rect::new(view_point.x, view_point.y, tile_size.x, tile_size.y);
+                            let occluding =
+                                cutaway_state.occludes(x, y, *layer_height, tile_rect, focus_rect);
+                            cutaway_state.fade(cutaway, *layer_height, i, occluding, frame_time)
+                        }
+                        _ => 1.0,
+                    };
+
+                    // Apply the layer's tint and opacity,
+                    // ghosting light tiles.
+                    let ghost_opacity = if is_ghosted(*blend_color) {
+                        ghost_opacity
+                    } else {
+                        1.0
+                    };
+                    let blend_color = color::blend(
+                        blend_color.unwrap_or(color::DEFAULT),
+                        layer.tint,
+                        opacity * cutaway_opacity * ghost_opacity,
+                    );
 
                     // Draw the tile.
-                    texture.draw(
-                        view_point.x,
-                        view_point.y + height_offset,
-                        tile_size,
-                        *blend_color,
-                    );
+                    texture.draw(view_point.x, view_point.y, tile_size, Some(blend_color));
                 }
             }
         }
 
+        self.cutaway_state = cutaway_state;
+
         // Skip drawing debug info if not enabled.
         if !self.draw_debug_info {
             return;
@@ -214,7 +331,7 @@ impl TileMap {
         mouse_y = mouse_y.round();
         let mut max_layer = None;
         let mut cursor_point = None;
-        for layer in self.layers.keys() {
+        for (layer, _) in self.layers.iter().filter(|(_, l)| l.visible) {
             let candidate_point = self.view_to_grid(mouse_x, mouse_y, *layer).round();
 
             if candidate_point.x < 0.0 || candidate_point.y < 0.0 {
@@ -228,7 +345,11 @@ impl TileMap {
                 continue;
             }
 
-            match self.layers.get(layer).map(|l| &l[y + self.height * x]) {
+            match self
+                .layers
+                .get(layer)
+                .map(|l| &l.tiles[y + self.height * x])
+            {
                 Some(Tile::Filled { .. }) => {
                     max_layer = Some(*layer);
                     cursor_point = Some(candidate_point);
@@ -260,23 +381,34 @@ impl TileMap {
         }
     }
 
-    /// Draws a sprite onto the map's tile space.
+    /// Draws the `source` rectangle (in pixels) of `sprite`
+    /// onto the map's tile space, at grid point `x, y` and
+    /// height `z` (in tiles) of `position`.
     pub fn draw_sprite(
         &mut self,
         sprite: &Texture2D,
-        x: f32,
-        y: f32,
-        z: f32,
+        source: Rect,
+        position: Vec3,
         layer: i8,
         flip_x: bool,
     ) {
+        let Vec3 { x, y, z } = position;
+
+        // Reveal the sprite through any cutaway.
+        if self.cutaway.is_some() {
+            self.set_cutaway_focus(x, y, z, layer);
+        }
+
         // Convert grid point to isometric space.
         let iso_pixel = self.grid_to_view(x, y, layer);
 
+        // Scale the frame to a tile's width, keeping
+        // its bottom edge on the tile's bottom edge.
         let tile_size = self.calculate_tile_size();
+        let dest_size = Vec2::new(tile_size.x, tile_size.x * source.h / source.w);
         let draw_params = DrawTextureParams {
-            dest_size: Some(tile_size),
-            source: Some(Rect::new(0.0, 0.0, 32.0, 32.0)),
+            dest_size: Some(dest_size),
+            source: Some(source),
             flip_x,
             ..Default::default()
         };
@@ -284,7 +416,7 @@ impl TileMap {
         macroquad::prelude::draw_texture_ex(
             sprite,
             iso_pixel.x,
-            iso_pixel.y + (tile_size.y * z * -1.0),
+            iso_pixel.y - (tile_size.y * z) - (dest_size.y - tile_size.y),
             WHITE,
             draw_params,
         );
@@ -292,18 +424,22 @@ impl TileMap {
 
     /// Sets the `tile` at logical coordinate `x, y` in `layer`.
     pub fn set_tile(&mut self, x: usize, y: usize, layer: i8, tile: Tile) {
-        let layer = self.layers.entry(layer).or_insert_with(|| {
-            // Initialize layers with all-empty tiles.
-            let mut tiles = Vec::with_capacity(self.tiles_per_layer);
-            for _ in 0..self.tiles_per_layer {
-                tiles.push(Tile::Empty);
-            }
-            tiles
-        });
+        let height = self.height;
+        let layer = self.layer_or_default(layer);
 
         // Convert the X/Y coordinate to contiguous vector coordinates.
-        let index = y + self.height * x;
-        layer[index] = tile;
+        let index = y + height * x;
+        layer.tiles[index] = tile;
+    }
+
+    /// Returns the `tile` at logical coordinate `x, y` in `layer`.
+    pub fn tile(&self, x: usize, y: usize, layer: i8) -> Option<&Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let layer = self.layers.get(&layer)?;
+        layer.tiles.get(y + self.height * x)
     }
 
     /// Gets the `tile` at logical coordinate `x, y` in `layer`.
@@ -312,7 +448,7 @@ impl TileMap {
 
         // Convert the X/Y coordinate to contiguous vector coordinates.
         let index = y + self.height * x;
-        Some(&mut layer[index])
+        Some(&mut layer.tiles[index])
     }
 
     /// Calculates the current active view size.
@@ -416,7 +552,8 @@ impl TileMap {
     ///    in `bitmap` renders a an accent-blended `floor_texture`.
     /// 3. [`color::ACCENT_3`] in `bitmap` causes a coordinate to
     ///    be returned indicating an avatar spawn point.
-    /// 4. Any other color renders a `floor_texture` with no blended color.
+    /// 4. Opaque white in `bitmap` leaves the tile empty.
+    /// 5. Any other color renders a `floor_texture` with no blended color.
     pub fn set_tiles_from_bitmap(
         &mut self,
         bitmap: &DynamicImage,
@@ -440,7 +577,9 @@ impl TileMap {
             let y = y as usize;
 
             // Color match for tile rendering.
-            if color.0 == wall_color {
+            if color.0 == BITMAP_EMPTY_COLOR {
+                self.set_tile(x, y, layer, Tile::Empty);
+            } else if color.0 == wall_color {
                 self.set_tile(
                     x,
                     y,
@@ -485,9 +624,40 @@ impl TileMap {
         avatar_spawn_point
     }
 
+    /// Returns a bitmap of the tiles in `layer`, in the format
+    /// read by [`Self::set_tiles_from_bitmap`]; tiles with
+    /// `wall_texture` are walls, and all others are floors.
+    pub fn bitmap_from_tiles(&self, layer: i8, wall_texture: &TileTexture) -> DynamicImage {
+        let mut bitmap = RgbaImage::new(self.width as u32, self.height as u32);
+
+        for (x, y, pixel) in bitmap.enumerate_pixels_mut() {
+            let color = match self.tile(x as usize, y as usize, layer) {
+                Some(Tile::Filled { texture, .. }) if texture == wall_texture => {
+                    color::BACKGROUND.into()
+                }
+                Some(Tile::Filled {
+                    blend_color: Some(blend_color),
+                    ..
+                }) => MAP_SYMBOLS
+                    .iter()
+                    .map(|(_, color)| *color)
+                    .find(|color| color.without_alpha() == blend_color.without_alpha())
+                    .map(Into::into)
+                    .unwrap_or(BITMAP_FLOOR_COLOR),
+                Some(Tile::Filled { .. }) => BITMAP_FLOOR_COLOR,
+                Some(Tile::Empty) | None => BITMAP_EMPTY_COLOR,
+            };
+
+            *pixel = Rgba(color);
+        }
+
+        DynamicImage::ImageRgba8(bitmap)
+    }
+
     /// TODO:
     pub fn clear(&mut self) {
         self.layers.clear();
+        self.cutaway_state = Default::default();
     }
 
     /// TODO:
@@ -529,77 +699,47 @@ impl TileMap {
         }
     }
 
-    /// TODO: https://medium.com/geekculture/bresenhams-line-drawing-algorithm-2e0e953901b3.
-    pub fn tiles_on_line_between(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Vec<(usize, usize)> {
-        let ax = x1 as isize;
-        let ay = y1 as isize;
-        let bx = x2 as isize;
-        let by = y2 as isize;
+    /// Returns the coordinates of every tile in `layer` that is
+    /// identical to, and orthogonally connected to, the tile at `x, y`.
+    pub fn tiles_connected_to(&self, x: usize, y: usize, layer: i8) -> Vec<(usize, usize)> {
+        let Some(target) = self.tile(x, y, layer) else {
+            return vec![];
+        };
 
-        let dx = bx - ax;
-        let dy = by - ay;
-        let abs_dx = dx.abs();
-        let abs_dy = dy.abs();
+        let mut visited = vec![false; self.tiles_per_layer];
+        let mut pending = vec![(x, y)];
+        let mut connected = vec![];
 
-        let mut x = ax;
-        let mut y = ay;
+        while let Some((x, y)) = pending.pop() {
+            let index = y + self.height * x;
+            if visited[index] || self.tile(x, y, layer) != Some(target) {
+                continue;
+            }
 
-        let mut line_points = vec![(x as usize, y as usize)];
+            visited[index] = true;
+            connected.push((x, y));
 
-        let inc_x = |x| {
-            if dx < 0 {
-                x - 1
-            } else {
-                x + 1
+            if x > 0 {
+                pending.push((x - 1, y));
             }
-        };
-
-        let inc_y = |y| {
-            if dy < 0 {
-                y - 1
-            } else {
-                y + 1
+            if y > 0 {
+                pending.push((x, y - 1));
             }
-        };
-
-        // Small slope.
-        if abs_dx > abs_dy {
-            let mut d = 2 * abs_dy - abs_dx;
-
-            for _ in 0..abs_dx {
-                x = inc_x(x);
-
-                if d < 0 {
-                    d += 2 * abs_dy
-                } else {
-                    y = inc_y(y);
-
-                    d += (2 * abs_dy) - (2 * abs_dx);
-                }
-
-                line_points.push((x as usize, y as usize));
+            if x + 1 < self.width {
+                pending.push((x + 1, y));
             }
-
-        // Large slope.
-        } else {
-            let mut d = 2 * abs_dx - abs_dy;
-
-            for _ in 0..abs_dy {
-                y = inc_y(y);
-
-                if d < 0 {
-                    d += 2 * abs_dx;
-                } else {
-                    x = inc_x(x);
-
-                    d += (2 * abs_dx) - (2 * abs_dy);
-                }
-
-                line_points.push((x as usize, y as usize));
+            if y + 1 < self.height {
+                pending.push((x, y + 1));
             }
         }
 
-        line_points
+        connected
+    }
+
+    /// TODO: https://medium.com/geekculture/bresenhams-line-drawing-algorithm-2e0e953901b3.
+    pub fn tiles_on_line_between(&self, x1: f32, y1: f32, x2: f32, y2: f32) -> Vec<(usize, usize)> {
+        self.clip(shape::bresenham_line(x1, y1, x2, y2).into_iter())
+            .collect()
     }
 
     /// TODO: https://en.wikipedia.org/wiki/Midpoint_circle_algorithm
@@ -609,42 +749,99 @@ impl TileMap {
         center_y: isize,
         radius: isize,
     ) -> Vec<(usize, usize)> {
-        let mut diameter = (5 - radius * 4) / 4;
+        self.clip(shape::circle(center_x, center_y, radius).into_iter())
+            .collect()
+    }
+}
 
-        let mut radius_points = vec![];
-        let mut x = 0;
-        let mut y = radius;
+/// Converts a bitmap in the format read by [`TileMap::set_tiles_from_bitmap`]
+/// to the plain-text map format: one line per row of tiles, with
+/// `#` walls, `o` objectives, `x` threats, `@` spawns and `.` floors.
+pub fn map_from_bitmap(bitmap: &DynamicImage) -> String {
+    let mut map = String::new();
+
+    for y in 0..bitmap.height() {
+        for x in 0..bitmap.width() {
+            let pixel = bitmap.get_pixel(x, y).0;
+            let symbol = MAP_SYMBOLS
+                .iter()
+                .find(|(_, color)| <[u8; 4]>::from(*color) == pixel)
+                .map(|(symbol, _)| *symbol)
+                .unwrap_or(if pixel == BITMAP_EMPTY_COLOR {
+                    MAP_EMPTY_SYMBOL
+                } else {
+                    MAP_FLOOR_SYMBOL
+                });
+            map.push(symbol);
+        }
 
-        while x <= y {
-            radius_points.push((center_x + x, center_y + y));
-            radius_points.push((center_x + x, center_y - y));
-            radius_points.push((center_x - x, center_y + y));
-            radius_points.push((center_x - x, center_y - y));
+        map.push('\n');
+    }
 
-            radius_points.push((center_x + y, center_y + x));
-            radius_points.push((center_x + y, center_y - x));
-            radius_points.push((center_x - y, center_y + x));
-            radius_points.push((center_x - y, center_y - x));
+    map
+}
 
-            if diameter < 0 {
-                diameter += 2 * x + 1;
-            } else {
-                diameter += 2 * (x - y) + 1;
-                y -= 1;
+/// Converts a map in the plain-text format written by [`map_from_bitmap`]
+/// to a bitmap in the format read by [`TileMap::set_tiles_from_bitmap`].
+///
+/// The bitmap is as wide as the longest line in `map`;
+/// shorter lines are padded with floors.
+pub fn bitmap_from_map(map: &str) -> DynamicImage {
+    let rows: Vec<&str> = map.lines().collect();
+    let width = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut bitmap =
+        RgbaImage::from_pixel(width as u32, rows.len() as u32, Rgba(BITMAP_FLOOR_COLOR));
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, symbol) in row.chars().enumerate() {
+            if let Some((_, color)) = MAP_SYMBOLS.iter().find(|(s, _)| *s == symbol) {
+                bitmap.put_pixel(x as u32, y as u32, Rgba((*color).into()));
+            } else if symbol == MAP_EMPTY_SYMBOL {
+                bitmap.put_pixel(x as u32, y as u32, Rgba(BITMAP_EMPTY_COLOR));
             }
-
-            x += 1;
         }
+    }
 
-        radius_points
-            .into_iter()
-            .filter_map(|(x, y)| {
-                if x >= 0 && y >= 0 && x < self.width as isize && y < self.height as isize {
-                    Some((x as usize, y as usize))
-                } else {
-                    None
-                }
-            })
-            .collect()
+    DynamicImage::ImageRgba8(bitmap)
+}
+
+/// Returns true if tiles blended with `blend_color` are
+/// ghosted; untinted tiles, like walls, never are.
+fn is_ghosted(blend_color: Option<Color>) -> bool {
+    matches!(blend_color, Some(blend_color) if color::is_light(blend_color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_bitmap_round_trip() {
+        let map = "#.o\nx@#\n._\n";
+        let bitmap = bitmap_from_map(map);
+
+        assert_eq!((3, 3), bitmap.dimensions());
+        assert_eq!(<[u8; 4]>::from(color::ACCENT_1), bitmap.get_pixel(2, 0).0);
+        assert_eq!("#.o\nx@#\n._.\n", map_from_bitmap(&bitmap));
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_map() {
+        let map = TileMap::with_view_size(2, 2, Vec2::splat(64.0));
+        assert_eq!(
+            vec![(0, 1), (1, 1)],
+            map.tiles_on_line_between(-2.0, 1.0, 3.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn ghosts_only_light_tinted_tiles() {
+        assert!(is_ghosted(Some(color::DEFAULT)));
+        assert!(!is_ghosted(Some(color::BACKGROUND)));
+        assert!(!is_ghosted(None));
     }
 }
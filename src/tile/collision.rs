@@ -0,0 +1,251 @@
+//! Swept collision of moving bodies against a [`TileMap`]'s solid tiles.
+//!
+//! Like [shape queries](super::shape), collision treats
+//! tiles as unit squares centered on their grid coordinates.
+//! Movement is resolved one axis at a time, so a body
+//! blocked on one axis keeps sliding along the other.
+use glam::Vec2;
+
+use super::{Tile, TileMap};
+
+/// Distance, in tiles, below which a body is
+/// considered to be touching a tile.
+const CONTACT_EPSILON: f32 = 1e-4;
+
+/// Shape of a body colliding with solid tiles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collider {
+    /// Axis-aligned box centered on the body's
+    /// position, with `half_extents` in tiles.
+    Aabb { half_extents: Vec2 },
+
+    /// Circle centered on the body's
+    /// position, with `radius` in tiles.
+    Circle { radius: f32 },
+}
+
+impl Collider {
+    /// Returns the collider's extent from its
+    /// center along the `axis`-th axis.
+    fn extent(&self, axis: usize) -> f32 {
+        match self {
+            Self::Aabb { half_extents } => half_extents[axis],
+            Self::Circle { radius } => *radius,
+        }
+    }
+
+    /// Returns the distance along a movement axis between the
+    /// collider's center and the center of a tile it touches,
+    /// given the `perpendicular` distance between their centers;
+    /// returns `None` if they can't touch.
+    fn contact_distance(&self, perpendicular: f32, perpendicular_axis: usize) -> Option<f32> {
+        let perpendicular = perpendicular.abs();
+        let reach = 0.5 + self.extent(perpendicular_axis);
+
+        // Bodies flush against a tile's side slide past it.
+        if perpendicular >= reach - CONTACT_EPSILON {
+            return None;
+        }
+
+        match self {
+            Self::Aabb { half_extents } => Some(0.5 + half_extents[1 - perpendicular_axis]),
+            Self::Circle { radius } => {
+                // Distance from the circle's center to the tile's
+                // nearest edge (or corner) on the perpendicular axis.
+                let gap = (perpendicular - 0.5).max(0.0);
+                Some(0.5 + (radius * radius - gap * gap).sqrt())
+            }
+        }
+    }
+}
+
+/// Result of sweeping a [`Collider`] through a tile grid.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sweep {
+    /// Final position of the body.
+    pub position: Vec2,
+
+    /// Normals of the surfaces the body was
+    /// stopped by, at most one per axis.
+    pub normals: Vec<Vec2>,
+
+    /// Tiles the body was stopped by.
+    pub hits: Vec<(isize, isize)>,
+}
+
+impl Sweep {
+    /// Returns true if the body was stopped on any axis.
+    pub fn collided(&self) -> bool {
+        !self.normals.is_empty()
+    }
+}
+
+impl TileMap {
+    /// Sweeps `collider` from `position` by `delta` through
+    /// `layer`, stopping it at tiles for which `is_solid`
+    /// returns true; tiles outside the map are always solid.
+    pub fn sweep(
+        &self,
+        collider: Collider,
+        position: Vec2,
+        delta: Vec2,
+        layer: i8,
+        is_solid: impl Fn(&Tile) -> bool,
+    ) -> Sweep {
+        sweep(collider, position, delta, |x, y| {
+            if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                return true;
+            }
+
+            self.tile(x as usize, y as usize, layer)
+                .is_some_and(&is_solid)
+        })
+    }
+}
+
+/// Sweeps `collider` from `position` by `delta`, stopping
+/// it at tiles for which `is_solid` returns true.
+///
+/// The X axis is resolved before the Y axis. Solid tiles
+/// the body already overlaps are ignored, so bodies can
+/// always move out of walls they were placed inside.
+pub fn sweep(
+    collider: Collider,
+    position: Vec2,
+    delta: Vec2,
+    is_solid: impl Fn(isize, isize) -> bool,
+) -> Sweep {
+    let mut sweep = Sweep {
+        position,
+        ..Default::default()
+    };
+
+    for axis in 0..2 {
+        let distance = delta[axis];
+        if distance == 0.0 {
+            continue;
+        }
+
+        let perpendicular_axis = 1 - axis;
+        let direction = distance.signum();
+        let center = sweep.position[axis];
+        let perpendicular_center = sweep.position[perpendicular_axis];
+
+        // Every tile the body could touch on the way.
+        let extent = collider.extent(axis);
+        let (from, to) = (
+            center - direction * extent,
+            center + distance + direction * extent,
+        );
+        let (min, max) = (from.min(to), from.max(to));
+        let perpendicular_extent = collider.extent(perpendicular_axis) + 0.5;
+
+        let mut travel = distance.abs();
+        let mut hit = None;
+        for along in (min.round() as isize)..=(max.round() as isize) {
+            let across_min = (perpendicular_center - perpendicular_extent).round() as isize;
+            let across_max = (perpendicular_center + perpendicular_extent).round() as isize;
+
+            for across in across_min..=across_max {
+                let tile = if axis == 0 {
+                    (along, across)
+                } else {
+                    (across, along)
+                };
+
+                let Some(contact) = collider
+                    .contact_distance(across as f32 - perpendicular_center, perpendicular_axis)
+                else {
+                    continue;
+                };
+
+                // Only tiles ahead of the body can stop it.
+                let available = (along as f32 - center) * direction - contact;
+                if available < -CONTACT_EPSILON || available >= travel || !is_solid(tile.0, tile.1)
+                {
+                    continue;
+                }
+
+                travel = available.max(0.0);
+                hit = Some(tile);
+            }
+        }
+
+        sweep.position[axis] = center + direction * travel;
+        if let Some(tile) = hit {
+            let mut normal = Vec2::ZERO;
+            normal[axis] = -direction;
+            sweep.normals.push(normal);
+            sweep.hits.push(tile);
+        }
+    }
+
+    sweep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOX: Collider = Collider::Aabb {
+        half_extents: Vec2::splat(0.4),
+    };
+
+    const CIRCLE: Collider = Collider::Circle { radius: 0.4 };
+
+    /// Returns true for tiles in a wall at `x == 3`.
+    fn wall(x: isize, _: isize) -> bool {
+        x == 3
+    }
+
+    #[test]
+    fn stops_at_walls() {
+        for collider in [BOX, CIRCLE] {
+            let sweep = sweep(collider, Vec2::ZERO, Vec2::new(2.5, 0.0), wall);
+            assert!((sweep.position.x - 2.1).abs() < 1e-4, "{sweep:?}");
+            assert_eq!(vec![Vec2::NEG_X], sweep.normals);
+            assert_eq!(vec![(3, 0)], sweep.hits);
+        }
+    }
+
+    #[test]
+    fn slides_along_walls() {
+        let sweep = sweep(BOX, Vec2::ZERO, Vec2::new(5.0, 4.0), wall);
+        assert!((sweep.position.x - 2.1).abs() < 1e-4);
+        assert_eq!(4.0, sweep.position.y);
+        assert!(sweep.collided());
+
+        // Resting against a wall doesn't block perpendicular movement.
+        let sweep = super::sweep(BOX, Vec2::new(2.1, 0.0), Vec2::new(0.0, -3.0), wall);
+        assert_eq!(Vec2::new(2.1, -3.0), sweep.position);
+        assert!(!sweep.collided());
+    }
+
+    #[test]
+    fn does_not_tunnel() {
+        let sweep = sweep(BOX, Vec2::ZERO, Vec2::new(100.0, 0.0), wall);
+        assert!(sweep.position.x < 3.0);
+
+        let sweep = super::sweep(BOX, Vec2::new(10.0, 0.0), Vec2::new(-100.0, 0.0), wall);
+        assert!((sweep.position.x - 3.9).abs() < 1e-4);
+    }
+
+    #[test]
+    fn circles_round_corners() {
+        // A lone solid tile at (3, 0).
+        let pillar = |x, y| (x, y) == (3, 0);
+
+        // Clipping the pillar's corner, a circle
+        // gets closer to it than a box does.
+        let start = Vec2::new(0.0, 0.85);
+        let boxed = sweep(BOX, start, Vec2::new(5.0, 0.0), pillar);
+        let circle = sweep(CIRCLE, start, Vec2::new(5.0, 0.0), pillar);
+        assert!((boxed.position.x - 2.1).abs() < 1e-4);
+        assert!(circle.position.x > boxed.position.x && circle.position.x < 3.0);
+
+        // Beyond the pillar's corner, neither is stopped.
+        let start = Vec2::new(0.0, 0.95);
+        assert!(!sweep(BOX, start, Vec2::new(5.0, 0.0), pillar).collided());
+        assert!(!sweep(CIRCLE, start, Vec2::new(5.0, 0.0), pillar).collided());
+    }
+}
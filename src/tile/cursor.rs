@@ -1,163 +1,542 @@
+//! In-engine tile editor driven by a cursor.
+use glam::Vec2;
 use macroquad::prelude::*;
 
-use crate::color;
+use crate::{
+    color::{self, Color},
+    input::Input,
+};
 
-use super::{Tile, TileMap};
+#[cfg(not(target_arch = "wasm32"))]
+use super::TileTexture;
+use super::{
+    history::{History, TileEdit},
+    Tile, TileMap,
+};
 
-impl TileMap {
+/// Default memory retained by a [`TileEditor`]'s history.
+const HISTORY_MEMORY_CAP: usize = 8 * 1024 * 1024;
 
-    /// TODO: Utilities for interacting with tiles via a cursor.
-    pub fn cursor_interaction(&mut self) {
+/// Size, in pixels, of each swatch in the palette picker.
+const PALETTE_SWATCH_SIZE: f32 = 48.0;
 
-        // TODO: Track variables between calls.
-        let mut mouse_pos = Vec2::from(mouse_position());
-        let mut mouse_select_a = None;
-        let mut mouse_select_b = None;
-        let mut active_layer = 0;
+/// Tool applied by a [`TileEditor`] on click.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorTool {
+    /// Sets hovered tiles to the selected palette tile.
+    Paint,
 
-        // Capture mouse position in unit space.
-        let cursor_point = self
-            .view_to_grid(mouse_pos.x, mouse_pos.y, active_layer)
-            .round();
-        let (cursor_x, cursor_y) = (cursor_point.x as usize, cursor_point.y as usize);
-        let cursor_on_grid =
-            cursor_point.x >= 0.0 && cursor_x < self.width && cursor_point.y >= 0.0 && cursor_y < self.height;
-
-        // Highlight regions of tiles on click.
-        if is_mouse_button_released(MouseButton::Left) {
-            match (
-                cursor_on_grid,
-                mouse_select_a.as_mut(),
-                mouse_select_b.as_mut(),
-            ) {
-                // Set first point.
-                (true, None, None) | (true, Some(..), Some(..)) => {
-                    mouse_select_a = Some(Vec2::new(cursor_x as f32, cursor_y as f32));
-                    mouse_select_b = None;
-                }
+    /// Empties hovered tiles.
+    Erase,
 
-                // Set second point.
-                (true, Some(selection_a), None) => {
-                    let a_x = selection_a.x.min(cursor_x as f32);
-                    let a_y = selection_a.y.min(cursor_y as f32);
-                    let b_x = selection_a.x.max(cursor_x as f32);
-                    let b_y = selection_a.y.max(cursor_y as f32);
+    /// Flood-fills the hovered region
+    /// with the selected palette tile.
+    Fill,
 
-                    selection_a.x = a_x;
-                    selection_a.y = a_y;
-                    mouse_select_b = Some(Vec2::new(b_x, b_y));
-                }
+    /// Selects a rectangle of tiles.
+    Select,
+}
 
-                // Clear selection.
-                (false, _, _) => {
-                    mouse_select_a = None;
-                    mouse_select_b = None;
-                }
+/// Rectangular selection of tiles, with inclusive corners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+}
 
-                // Shouldn't be valid state.
-                (true, None, Some(_)) => unreachable!(),
-            }
+impl Selection {
+    /// Returns the selection spanning corners `a` and `b`.
+    pub fn new(a: (usize, usize), b: (usize, usize)) -> Self {
+        Self {
+            min: (a.0.min(b.0), a.1.min(b.1)),
+            max: (a.0.max(b.0), a.1.max(b.1)),
         }
+    }
 
-        // Delete tiles on right-click.
-        if is_mouse_button_released(MouseButton::Right) {
-            // Delete regions of tiles.
-            if let (Some(a), Some(b)) = (mouse_select_a, mouse_select_b) {
-                for x in a.x.floor() as usize..=b.x.ceil() as usize {
-                    if x >= self.width {
-                        continue;
-                    }
+    /// Returns true if `x, y` is in the selection.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.min.0 && x <= self.max.0 && y >= self.min.1 && y <= self.max.1
+    }
+}
 
-                    for y in a.y.floor() as usize..=b.y.floor() as usize {
-                        if y >= self.height {
-                            continue;
-                        }
+/// Tiles copied by a [`TileEditor`].
+struct Clipboard {
+    width: usize,
+    height: usize,
 
-                        self.set_tile(x, y, active_layer, Tile::Empty);
-                    }
+    /// Copied tiles, indexed by `y + height * x`.
+    tiles: Vec<Tile>,
+}
+
+/// Persistent state of an in-engine editor for a [`TileMap`].
+///
+/// Every edit is recorded in the editor's [`History`],
+/// so it can be undone and redone.
+pub struct TileEditor {
+    /// Layer being edited.
+    pub active_layer: i8,
+
+    /// Tool applied on click.
+    pub tool: EditorTool,
+
+    /// Tiles which may be painted.
+    pub palette: Vec<Tile>,
+
+    /// Index of the selected tile in [`Self::palette`].
+    pub selected_tile: usize,
+
+    /// History of edits made with the editor.
+    pub history: History<TileEdit>,
+
+    /// Tile under the cursor, if any.
+    hover: Option<(usize, usize)>,
+
+    /// First corner of an in-progress selection.
+    selection_anchor: Option<(usize, usize)>,
+
+    /// Completed selection, if any.
+    selection: Option<Selection>,
+
+    /// Most recently copied tiles.
+    clipboard: Option<Clipboard>,
+
+    /// True while a paint or erase stroke is in progress.
+    stroke: bool,
+}
+
+impl TileEditor {
+    /// Returns a new editor painting tiles from `palette`.
+    pub fn new(palette: Vec<Tile>) -> Self {
+        Self {
+            active_layer: 0,
+            tool: EditorTool::Paint,
+            palette,
+            selected_tile: 0,
+            history: History::new(HISTORY_MEMORY_CAP),
+            hover: None,
+            selection_anchor: None,
+            selection: None,
+            clipboard: None,
+            stroke: false,
+        }
+    }
+
+    /// Returns the tile under the cursor, if any.
+    pub fn hover(&self) -> Option<(usize, usize)> {
+        self.hover
+    }
+
+    /// Returns the current selection, if any.
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    /// Handles one frame of `input`'s `edit_*` actions,
+    /// applying any resulting edits to `map`.
+    pub fn update(&mut self, map: &mut TileMap, input: &Input) {
+        let modifier = input.down("edit_modifier");
+
+        // Undo and redo.
+        if modifier && input.pressed("edit_undo") {
+            if input.down("edit_alternate") {
+                self.redo(map);
+            } else {
+                self.undo(map);
+            }
+        } else if modifier && input.pressed("edit_redo") {
+            self.redo(map);
+        }
+
+        // Copy and paste.
+        if modifier && input.pressed("edit_copy") {
+            self.copy(map);
+        } else if modifier && input.pressed("edit_paste") {
+            if let Some((x, y)) = self.hover {
+                self.paste(map, x, y);
+            }
+        }
+
+        // Tool selection.
+        if !modifier {
+            for (action, tool) in [
+                ("edit_paint", EditorTool::Paint),
+                ("edit_erase", EditorTool::Erase),
+                ("edit_fill", EditorTool::Fill),
+                ("edit_select", EditorTool::Select),
+            ] {
+                if input.pressed(action) {
+                    self.tool = tool;
                 }
+            }
+        }
+
+        // Layer switching.
+        if input.pressed("edit_layer_up") {
+            self.set_active_layer(map, self.active_layer.saturating_add(1));
+        } else if input.pressed("edit_layer_down") {
+            self.set_active_layer(map, self.active_layer.saturating_sub(1));
+        }
+
+        // Palette selection by number.
+        for i in 0..self.palette.len().min(9) {
+            if input.pressed(&format!("edit_tile_{}", i + 1)) {
+                self.selected_tile = i;
+            }
+        }
 
-                mouse_select_a = None;
-                mouse_select_b = None;
+        // Erase selections.
+        if input.pressed("edit_erase_selection") {
+            self.erase_selection(map);
+        }
 
-            // Delete single tiles if no region is highlighted.
-            } else if cursor_on_grid {
-                self.set_tile(cursor_x, cursor_y, active_layer, Tile::Empty);
+        // Palette selection by clicking a swatch.
+        let pointer = input.pointer();
+        if let Some(swatch) = self.palette_swatch_at(pointer) {
+            if input.pressed("edit_apply") {
+                self.selected_tile = swatch;
             }
+
+            self.hover = None;
+            return;
         }
 
-        // Apply regional tile highlights.
-        match (mouse_select_a, mouse_select_b) {
-            // Selection in-progress.
-            (Some(a), None) => {
-                let a_x = a.x.min(cursor_x as f32);
-                let a_y = a.y.min(cursor_y as f32);
-                let b_x = a.x.max(cursor_x as f32);
-                let b_y = a.y.max(cursor_y as f32);
+        // Track the tile under the cursor.
+        let cursor_point = map
+            .view_to_grid(pointer.x, pointer.y, self.active_layer)
+            .round();
+        self.hover = if cursor_point.x >= 0.0
+            && cursor_point.y >= 0.0
+            && (cursor_point.x as usize) < map.width
+            && (cursor_point.y as usize) < map.height
+        {
+            Some((cursor_point.x as usize, cursor_point.y as usize))
+        } else {
+            None
+        };
 
-                for x in a_x.floor() as usize..=b_x.ceil() as usize {
-                    if x >= self.width {
-                        continue;
+        // Apply the active tool.
+        match self.tool {
+            EditorTool::Paint | EditorTool::Erase => {
+                if input.down("edit_apply") {
+                    if !self.stroke {
+                        self.history.begin();
+                        self.stroke = true;
                     }
 
-                    for y in a_y.floor() as usize..=b_y.floor() as usize {
-                        if y >= self.height {
-                            continue;
+                    if let Some((x, y)) = self.hover {
+                        if self.tool == EditorTool::Paint {
+                            self.paint(map, x, y);
+                        } else {
+                            self.erase(map, x, y);
                         }
+                    }
+                } else if self.stroke {
+                    self.history.commit();
+                    self.stroke = false;
+                }
+            }
 
-                        if let Some(Tile::Filled {
-                            texture,
-                            height_offset,
-                            blend_color,
-                        }) = self.get_tile(x, y, active_layer)
-                        {
-                            *height_offset = Some(0.05);
-                            *blend_color = Some(color::ACCENT_2);
-                        }
+            EditorTool::Fill => {
+                if input.pressed("edit_apply") {
+                    if let Some((x, y)) = self.hover {
+                        self.fill(map, x, y);
                     }
                 }
             }
 
-            // Selection complete.
-            (Some(a), Some(b)) => {
-                for x in a.x.floor() as usize..=b.x.ceil() as usize {
-                    if x >= self.width {
-                        continue;
+            EditorTool::Select => {
+                if input.pressed("edit_apply") {
+                    self.selection = None;
+                    self.selection_anchor = self.hover;
+                } else if input.released("edit_apply") {
+                    if let (Some(anchor), Some(hover)) = (self.selection_anchor, self.hover) {
+                        self.selection = Some(Selection::new(anchor, hover));
                     }
 
-                    for y in a.y.floor() as usize..=b.y.floor() as usize {
-                        if y >= self.height {
-                            continue;
-                        }
+                    self.selection_anchor = None;
+                }
+            }
+        }
 
-                        if let Some(Tile::Filled {
-                            texture,
-                            height_offset,
-                            blend_color,
-                        }) = self.get_tile(x, y, active_layer)
-                        {
-                            *height_offset = Some(0.1);
-                            *blend_color = Some(color::ACCENT_1);
-                        }
-                    }
+        // Clear selections on cancel.
+        if input.released("edit_cancel") {
+            self.selection = None;
+            self.selection_anchor = None;
+        }
+    }
+
+    /// Draws the editor's highlights and palette picker
+    /// over `map`, which should already be drawn.
+    pub fn draw(&self, map: &TileMap) {
+        // Highlight the in-progress or completed selection.
+        let selection = match (self.selection_anchor, self.hover) {
+            (Some(anchor), Some(hover)) => Some(Selection::new(anchor, hover)),
+            _ => self.selection,
+        };
+        if let Some(selection) = selection {
+            for x in selection.min.0..=selection.max.0 {
+                for y in selection.min.1..=selection.max.1 {
+                    self.draw_highlight(map, x, y, color::ACCENT_2, 0.35);
                 }
             }
+        }
 
-            _ => (),
+        // Highlight the hovered tile, or the paste
+        // footprint if the clipboard is non-empty.
+        if let Some((x, y)) = self.hover {
+            let (width, height) = match &self.clipboard {
+                Some(clipboard) if self.tool == EditorTool::Select => {
+                    (clipboard.width, clipboard.height)
+                }
+                _ => (1, 1),
+            };
+
+            for x in x..(x + width).min(map.width) {
+                for y in y..(y + height).min(map.height) {
+                    self.draw_highlight(map, x, y, color::ACCENT_1, 0.6);
+                }
+            }
         }
 
-        // Highlight the tile the mouse is hovering over and draw a sprite onto it.
-        if cursor_on_grid {
-            // Highlight the tile.
-            if let Some(Tile::Filled {
+        // Draw the palette picker.
+        let screen_width = screen_width();
+        let origin = self.palette_origin();
+        for (i, tile) in self.palette.iter().enumerate() {
+            let x = origin.x + i as f32 * PALETTE_SWATCH_SIZE;
+
+            if i == self.selected_tile {
+                draw_rectangle(
+                    x,
+                    origin.y,
+                    PALETTE_SWATCH_SIZE,
+                    PALETTE_SWATCH_SIZE,
+                    color::as_macroquad_color(color::ACCENT_1),
+                );
+            }
+
+            if let Tile::Filled {
                 texture,
-                height_offset,
                 blend_color,
-            }) = self.get_tile(cursor_x, cursor_y, active_layer)
+                ..
+            } = tile
             {
-                *height_offset = Some(0.2);
-                *blend_color = Some(color::ACCENT_1);
-            };
+                texture.draw(x, origin.y, Vec2::splat(PALETTE_SWATCH_SIZE), *blend_color);
+            }
+        }
+
+        // Draw the editor's status.
+        let tool = match self.tool {
+            EditorTool::Paint => "[b] paint",
+            EditorTool::Erase => "[x] erase",
+            EditorTool::Fill => "[g] fill",
+            EditorTool::Select => "[v] select",
+        };
+        draw_text(
+            &format!("{tool} @ layer {} [pgup/pgdn]", self.active_layer),
+            screen_width - 320.,
+            20.,
+            20.,
+            GRAY,
+        );
+    }
+
+    /// Sets the selected palette tile at `x, y`.
+    pub fn paint(&mut self, map: &mut TileMap, x: usize, y: usize) {
+        let Some(tile) = self.palette.get(self.selected_tile).cloned() else {
+            return;
+        };
+
+        self.set_tile(map, x, y, tile);
+    }
+
+    /// Empties the tile at `x, y`.
+    pub fn erase(&mut self, map: &mut TileMap, x: usize, y: usize) {
+        self.set_tile(map, x, y, Tile::Empty);
+    }
+
+    /// Flood-fills the region connected to `x, y`
+    /// with the selected palette tile.
+    pub fn fill(&mut self, map: &mut TileMap, x: usize, y: usize) {
+        let Some(tile) = self.palette.get(self.selected_tile) else {
+            return;
+        };
+
+        if map.tile(x, y, self.active_layer) == Some(tile) {
+            return;
+        }
+
+        let edit = TileEdit::fill(map, x, y, self.active_layer, tile);
+        self.history.execute(map, edit);
+    }
+
+    /// Empties every tile in the current selection.
+    pub fn erase_selection(&mut self, map: &mut TileMap) {
+        let Some(selection) = self.selection else {
+            return;
+        };
+
+        let edit = TileEdit::set_rect(
+            map,
+            selection.min.0 as isize,
+            selection.min.1 as isize,
+            selection.max.0 as isize,
+            selection.max.1 as isize,
+            self.active_layer,
+            &Tile::Empty,
+        );
+        self.history.execute(map, edit);
+    }
+
+    /// Copies the tiles in the current selection.
+    pub fn copy(&mut self, map: &TileMap) {
+        let Some(selection) = self.selection else {
+            return;
+        };
+
+        let width = selection.max.0 - selection.min.0 + 1;
+        let height = selection.max.1 - selection.min.1 + 1;
+        let mut tiles = Vec::with_capacity(width * height);
+        for x in selection.min.0..=selection.max.0 {
+            for y in selection.min.1..=selection.max.1 {
+                tiles.push(
+                    map.tile(x, y, self.active_layer)
+                        .cloned()
+                        .unwrap_or(Tile::Empty),
+                );
+            }
+        }
+
+        self.clipboard = Some(Clipboard {
+            width,
+            height,
+            tiles,
+        });
+    }
+
+    /// Pastes the most recently copied tiles
+    /// with their top-left corner at `x, y`.
+    pub fn paste(&mut self, map: &mut TileMap, x: usize, y: usize) {
+        let Some(clipboard) = &self.clipboard else {
+            return;
+        };
+
+        let mut tiles = vec![];
+        for dx in 0..clipboard.width {
+            for dy in 0..clipboard.height {
+                if x + dx < map.width && y + dy < map.height {
+                    let tile = clipboard.tiles[dy + clipboard.height * dx].clone();
+                    tiles.push((x + dx, y + dy, tile));
+                }
+            }
         }
+
+        let edit = TileEdit::set_tiles(self.active_layer, tiles);
+        self.history.execute(map, edit);
+    }
+
+    /// Switches editing to `layer`, adding it to `map` if needed.
+    pub fn set_active_layer(&mut self, map: &mut TileMap, layer: i8) {
+        if map.layer(layer).is_none() {
+            self.history
+                .execute(map, TileEdit::add_layer(layer, format!("Layer {layer}")));
+        }
+
+        self.active_layer = layer;
+        self.selection = None;
+        self.selection_anchor = None;
+    }
+
+    /// Undoes the most recent edit.
+    pub fn undo(&mut self, map: &mut TileMap) {
+        self.end_stroke();
+        self.history.undo(map);
+    }
+
+    /// Redoes the most recently undone edit.
+    pub fn redo(&mut self, map: &mut TileMap) {
+        self.end_stroke();
+        self.history.redo(map);
+    }
+
+    /// Writes the active layer of `map` to `path`, as
+    /// a bitmap if `path` ends in `.png`, or otherwise
+    /// in the plain-text map format.
+    ///
+    /// Tiles with `wall_texture` are written as walls.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(
+        &self,
+        map: &TileMap,
+        path: &std::path::Path,
+        wall_texture: &TileTexture,
+    ) -> Result<(), std::io::Error> {
+        let bitmap = map.bitmap_from_tiles(self.active_layer, wall_texture);
+
+        if path.extension().is_some_and(|ext| ext == "png") {
+            bitmap
+                .save_with_format(path, image::ImageFormat::Png)
+                .map_err(std::io::Error::other)
+        } else {
+            std::fs::write(path, super::map_from_bitmap(&bitmap))
+        }
+    }
+
+    /// Sets `tile` at `x, y`, skipping redundant edits.
+    fn set_tile(&mut self, map: &mut TileMap, x: usize, y: usize, tile: Tile) {
+        if map.tile(x, y, self.active_layer) == Some(&tile) {
+            return;
+        }
+
+        let edit = TileEdit::set_tile(x, y, self.active_layer, tile);
+        self.history.execute(map, edit);
+    }
+
+    /// Commits any in-progress paint stroke.
+    fn end_stroke(&mut self) {
+        if self.stroke {
+            self.history.commit();
+            self.stroke = false;
+        }
+    }
+
+    /// Returns the view point of the palette picker's top-left corner.
+    fn palette_origin(&self) -> Vec2 {
+        let width = self.palette.len() as f32 * PALETTE_SWATCH_SIZE;
+        Vec2::new(
+            (screen_width() - width) / 2.0,
+            screen_height() - PALETTE_SWATCH_SIZE - 10.0,
+        )
+    }
+
+    /// Returns the index of the palette swatch at the view `point`.
+    fn palette_swatch_at(&self, point: Vec2) -> Option<usize> {
+        let origin = self.palette_origin();
+        let offset = point - origin;
+
+        if offset.x < 0.0 || offset.y < 0.0 || offset.y >= PALETTE_SWATCH_SIZE {
+            return None;
+        }
+
+        let swatch = (offset.x / PALETTE_SWATCH_SIZE) as usize;
+        (swatch < self.palette.len()).then_some(swatch)
+    }
+
+    /// Draws a translucent diamond over the top face
+    /// of the tile at `x, y` on the active layer.
+    fn draw_highlight(&self, map: &TileMap, x: usize, y: usize, color: Color, opacity: f32) {
+        let tile_size = map.calculate_tile_size();
+        let view_point = map.grid_to_view(x as f32, y as f32, self.active_layer);
+
+        // Tile textures place their top face in their upper half.
+        let center = view_point + Vec2::new(tile_size.x * 0.5, tile_size.y * 0.25);
+        let half = Vec2::new(tile_size.x * 0.5, tile_size.y * 0.25);
+        let top = center - Vec2::new(0.0, half.y);
+        let bottom = center + Vec2::new(0.0, half.y);
+        let left = center - Vec2::new(half.x, 0.0);
+        let right = center + Vec2::new(half.x, 0.0);
+
+        let color = color::as_macroquad_color(color::blend(color, color::DEFAULT, opacity));
+        draw_triangle(top, right, bottom, color);
+        draw_triangle(top, bottom, left, color);
     }
-}
\ No newline at end of file
+}
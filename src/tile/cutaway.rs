@@ -0,0 +1,235 @@
+//! Cutaway rendering, which reveals a focus
+//! (like the player's sprite) buried beneath
+//! or behind a [`TileMap`]'s tiles.
+use std::collections::HashMap;
+
+use glam::Vec2;
+use macroquad::math::Rect;
+
+use super::TileMap;
+
+/// How a [`TileMap`] draws the layers above its cutaway focus.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CutawayMode {
+    /// Layers above the focus are not drawn.
+    Hide,
+
+    /// Layers above the focus are drawn
+    /// with the given opacity (`0.0..=1.0`).
+    Fade(f32),
+}
+
+/// Cutaway rendering settings for a [`TileMap`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cutaway {
+    /// How layers above the focus layer are drawn.
+    pub upper_layers: CutawayMode,
+
+    /// Opacity (`0.0..=1.0`) of tiles drawn in
+    /// front of the focus in screen space.
+    pub occluder_opacity: f32,
+
+    /// Seconds taken for an occluding tile to
+    /// fade out (or back in once it stops occluding).
+    pub fade_seconds: f32,
+}
+
+impl Default for Cutaway {
+    fn default() -> Self {
+        Self {
+            upper_layers: CutawayMode::Fade(0.15),
+            occluder_opacity: 0.35,
+            fade_seconds: 0.2,
+        }
+    }
+}
+
+/// Point of interest revealed by a [`Cutaway`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct CutawayFocus {
+    /// Grid position of the focus.
+    position: Vec2,
+
+    /// Height of the focus relative to its layer.
+    z: f32,
+
+    /// Layer containing the focus.
+    layer: i8,
+}
+
+/// Per-frame cutaway state of a [`TileMap`].
+#[derive(Default)]
+pub(super) struct CutawayState {
+    /// Current focus, if any.
+    pub(super) focus: Option<CutawayFocus>,
+
+    /// Fade progress (`0.0..=1.0`) of every tile that
+    /// is, or recently was, occluding the focus;
+    /// keyed by layer and tile index.
+    fades: HashMap<(i8, usize), f32>,
+}
+
+impl TileMap {
+    /// Sets the focus revealed by the map's [`Cutaway`]
+    /// to the grid point `x, y`, lifted by `z`, in `layer`.
+    ///
+    /// [`TileMap::draw_sprite`] calls this automatically
+    /// with the position of the sprite being drawn.
+    pub fn set_cutaway_focus(&mut self, x: f32, y: f32, z: f32, layer: i8) {
+        self.cutaway_state.focus = Some(CutawayFocus {
+            position: Vec2::new(x, y),
+            z,
+            layer,
+        });
+    }
+
+    /// Clears the focus revealed by the map's [`Cutaway`].
+    pub fn clear_cutaway_focus(&mut self) {
+        self.cutaway_state.focus = None;
+        self.cutaway_state.fades.clear();
+    }
+
+    /// Returns the opacity applied by the map's
+    /// [`Cutaway`] to every tile in `layer`.
+    pub(super) fn cutaway_layer_opacity(&self, layer: i8) -> f32 {
+        match (self.cutaway, self.cutaway_state.focus) {
+            (Some(cutaway), Some(focus)) if layer > focus.layer => match cutaway.upper_layers {
+                CutawayMode::Hide => 0.0,
+                CutawayMode::Fade(opacity) => opacity,
+            },
+            _ => 1.0,
+        }
+    }
+
+    /// Returns the view-space rectangle covered by the
+    /// cutaway focus, if a [`Cutaway`] is active.
+    pub(super) fn cutaway_focus_rect(&self) -> Option<Rect> {
+        self.cutaway?;
+        let focus = self.cutaway_state.focus?;
+
+        let tile_size = self.calculate_tile_size();
+        let view_point = self.grid_to_view(focus.position.x, focus.position.y, focus.layer);
+
+        Some(Rect::new(
+            view_point.x,
+            view_point.y - tile_size.y * focus.z,
+            tile_size.x,
+            tile_size.y,
+        ))
+    }
+}
+
+impl CutawayState {
+    /// Returns true if the tile at `x, y` in `layer`,
+    /// covering the view-space `tile_rect`, is drawn in
+    /// front of the focus covering `focus_rect`.
+    pub(super) fn occludes(
+        &self,
+        x: usize,
+        y: usize,
+        layer: i8,
+        tile_rect: Rect,
+        focus_rect: Rect,
+    ) -> bool {
+        let Some(focus) = self.focus else {
+            return false;
+        };
+
+        // Tiles occlude the focus if they're drawn over it,
+        // and either sit on a higher layer or nearer the
+        // viewer (further down-screen) on the same layer.
+        let in_front = layer > focus.layer
+            || (layer == focus.layer
+                && (x + y) as f32 > focus.position.x.round() + focus.position.y.round());
+
+        in_front && tile_rect.overlaps(&focus_rect)
+    }
+
+    /// Advances the fade of the tile at `index` in `layer`
+    /// by `frame_time` seconds towards fully faded (if
+    /// `occluding`) or fully opaque, returning the
+    /// opacity `cutaway` applies to the tile.
+    pub(super) fn fade(
+        &mut self,
+        cutaway: &Cutaway,
+        layer: i8,
+        index: usize,
+        occluding: bool,
+        frame_time: f32,
+    ) -> f32 {
+        let key = (layer, index);
+        let step = if cutaway.fade_seconds > 0.0 {
+            frame_time / cutaway.fade_seconds
+        } else {
+            1.0
+        };
+
+        let fade = match (self.fades.get(&key).copied(), occluding) {
+            (None, false) => return 1.0,
+            (current, true) => (current.unwrap_or(0.0) + step).min(1.0),
+            (Some(current), false) => current - step,
+        };
+
+        if fade <= 0.0 {
+            self.fades.remove(&key);
+            return 1.0;
+        }
+
+        self.fades.insert(key, fade);
+        1.0 + (cutaway.occluder_opacity - 1.0) * fade
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_in_front_of_the_focus_occlude_it() {
+        let mut state = CutawayState {
+            focus: Some(CutawayFocus {
+                position: Vec2::new(2.0, 2.0),
+                z: 0.0,
+                layer: 0,
+            }),
+            ..Default::default()
+        };
+        let focus_rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let over = Rect::new(5.0, 5.0, 10.0, 10.0);
+        let beside = Rect::new(20.0, 0.0, 10.0, 10.0);
+
+        // Nearer the viewer, or on a higher layer.
+        assert!(state.occludes(3, 2, 0, over, focus_rect));
+        assert!(state.occludes(0, 0, 1, over, focus_rect));
+
+        // Behind the focus, or not drawn over it.
+        assert!(!state.occludes(1, 2, 0, over, focus_rect));
+        assert!(!state.occludes(2, 2, 0, over, focus_rect));
+        assert!(!state.occludes(3, 2, 0, beside, focus_rect));
+
+        state.focus = None;
+        assert!(!state.occludes(3, 2, 0, over, focus_rect));
+    }
+
+    #[test]
+    fn occluding_tiles_fade_out_and_back_in() {
+        let cutaway = Cutaway {
+            occluder_opacity: 0.5,
+            fade_seconds: 1.0,
+            ..Default::default()
+        };
+        let mut state = CutawayState::default();
+
+        assert_eq!(1.0, state.fade(&cutaway, 0, 7, false, 0.5));
+        assert_eq!(0.75, state.fade(&cutaway, 0, 7, true, 0.5));
+        assert_eq!(0.5, state.fade(&cutaway, 0, 7, true, 0.5));
+
+        // Fades settle at the occluder opacity.
+        assert_eq!(0.5, state.fade(&cutaway, 0, 7, true, 10.0));
+
+        // Fades settle back at fully opaque, forgetting the tile.
+        assert_eq!(0.75, state.fade(&cutaway, 0, 7, false, 0.5));
+        assert_eq!(1.0, state.fade(&cutaway, 0, 7, false, 10.0));
+        assert!(state.fades.is_empty());
+    }
+}
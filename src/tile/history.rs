@@ -0,0 +1,574 @@
+//! Undo/redo history of reversible edits.
+//!
+//! A [`History`] records [`Command`]s, like the
+//! [`TileEdit`]s made to a [`TileMap`], so they
+//! can be undone and redone. History only touches
+//! map data, so it works with or without a renderer.
+use std::collections::VecDeque;
+
+use super::{Layer, Tile, TileMap};
+
+/// A reversible edit to a `T`.
+pub trait Command<T> {
+    /// Applies the edit to `target`.
+    fn apply(&mut self, target: &mut T);
+
+    /// Reverts a previous [`Self::apply`] to `target`.
+    fn revert(&mut self, target: &mut T);
+
+    /// Returns the approximate memory, in bytes,
+    /// retained by the command in a [`History`].
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+/// A group of commands undone and redone as one.
+struct Transaction<C> {
+    commands: Vec<C>,
+    size: usize,
+}
+
+impl<C> Transaction<C> {
+    fn new() -> Self {
+        Self {
+            commands: vec![],
+            size: 0,
+        }
+    }
+}
+
+/// Undo and redo stacks of [`Command`]s.
+pub struct History<C> {
+    /// Applied transactions, oldest first.
+    undo: VecDeque<Transaction<C>>,
+
+    /// Undone transactions, most recently undone last.
+    redo: Vec<Transaction<C>>,
+
+    /// Transaction being recorded, if any.
+    open: Option<Transaction<C>>,
+
+    /// Number of nested [`Self::begin`] calls.
+    depth: usize,
+
+    /// Memory, in bytes, retained by both stacks.
+    size: usize,
+
+    /// Maximum memory, in bytes, retained by both stacks
+    /// before the oldest transactions are forgotten.
+    pub memory_cap: usize,
+}
+
+impl<C> History<C> {
+    /// Returns an empty history retaining
+    /// at most `memory_cap` bytes of commands.
+    pub fn new(memory_cap: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            open: None,
+            depth: 0,
+            size: 0,
+            memory_cap,
+        }
+    }
+
+    /// Applies `command` to `target`, recording it
+    /// in the open transaction (if any) or as a new
+    /// transaction; clears the redo stack.
+    pub fn execute<T>(&mut self, target: &mut T, mut command: C)
+    where
+        C: Command<T>,
+    {
+        command.apply(target);
+
+        // Any new edit invalidates the redo stack.
+        for transaction in self.redo.drain(..) {
+            self.size -= transaction.size;
+        }
+
+        let size = command.size();
+        self.size += size;
+
+        match &mut self.open {
+            Some(transaction) => {
+                transaction.commands.push(command);
+                transaction.size += size;
+            }
+            None => {
+                self.undo.push_back(Transaction {
+                    commands: vec![command],
+                    size,
+                });
+                self.enforce_cap();
+            }
+        }
+    }
+
+    /// Begins a transaction: every command executed until
+    /// the matching [`Self::commit`] is undone and redone as one.
+    ///
+    /// Transactions may be nested; only the
+    /// outermost commit records the transaction.
+    pub fn begin(&mut self) {
+        if self.depth == 0 {
+            self.open = Some(Transaction::new());
+        }
+
+        self.depth += 1;
+    }
+
+    /// Commits the transaction opened by [`Self::begin`].
+    pub fn commit(&mut self) {
+        if self.depth == 0 {
+            return;
+        }
+
+        self.depth -= 1;
+        if self.depth > 0 {
+            return;
+        }
+
+        if let Some(transaction) = self.open.take() {
+            if !transaction.commands.is_empty() {
+                self.undo.push_back(transaction);
+                self.enforce_cap();
+            }
+        }
+    }
+
+    /// Reverts the most recent transaction in `target`,
+    /// returning false if there was nothing to undo.
+    ///
+    /// Any open transaction is committed first.
+    pub fn undo<T>(&mut self, target: &mut T) -> bool
+    where
+        C: Command<T>,
+    {
+        self.close();
+
+        let Some(mut transaction) = self.undo.pop_back() else {
+            return false;
+        };
+
+        for command in transaction.commands.iter_mut().rev() {
+            command.revert(target);
+        }
+
+        self.redo.push(transaction);
+        true
+    }
+
+    /// Re-applies the most recently undone transaction in
+    /// `target`, returning false if there was nothing to redo.
+    pub fn redo<T>(&mut self, target: &mut T) -> bool
+    where
+        C: Command<T>,
+    {
+        self.close();
+
+        let Some(mut transaction) = self.redo.pop() else {
+            return false;
+        };
+
+        for command in transaction.commands.iter_mut() {
+            command.apply(target);
+        }
+
+        self.undo.push_back(transaction);
+        true
+    }
+
+    /// Returns true if there are transactions to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.open.as_ref().is_some_and(|t| !t.commands.is_empty())
+    }
+
+    /// Returns true if there are transactions to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Returns the memory, in bytes, retained by the history.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Forgets every recorded transaction.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = None;
+        self.depth = 0;
+        self.size = 0;
+    }
+
+    /// Commits any open transaction, regardless of nesting.
+    fn close(&mut self) {
+        if self.depth > 0 {
+            self.depth = 1;
+            self.commit();
+        }
+    }
+
+    /// Forgets the oldest transactions until the history
+    /// fits within its [`Self::memory_cap`]; the most
+    /// recent transaction is always kept.
+    fn enforce_cap(&mut self) {
+        while self.size > self.memory_cap && self.undo.len() > 1 {
+            if let Some(oldest) = self.undo.pop_front() {
+                self.size -= oldest.size;
+            }
+        }
+    }
+}
+
+/// A reversible edit to a [`TileMap`].
+pub enum TileEdit {
+    /// Replaces tiles at `x, y` in `layer`.
+    ///
+    /// Applying (or reverting) the edit swaps each
+    /// stored tile with the tile in the map, so
+    /// the edit always holds the tiles to restore;
+    /// `created` tracks whether the edit created the layer.
+    SetTiles {
+        layer: i8,
+        tiles: Vec<(usize, usize, Tile)>,
+        created: bool,
+    },
+
+    /// Adds an empty layer named `name` at `layer`,
+    /// unless a layer is already there; `added` tracks
+    /// whether the edit created the layer.
+    AddLayer {
+        layer: i8,
+        name: String,
+        added: bool,
+    },
+
+    /// Removes the layer at `layer`, holding it
+    /// while removed so it can be restored.
+    RemoveLayer { layer: i8, removed: Option<Layer> },
+
+    /// Moves the layer at `from` to `to`, swapping
+    /// with any layer at `to`; `moved` tracks
+    /// whether there was a layer to move.
+    MoveLayer { from: i8, to: i8, moved: bool },
+}
+
+impl TileEdit {
+    /// Returns an edit adding an empty layer named `name` at `layer`.
+    pub fn add_layer(layer: i8, name: impl Into<String>) -> Self {
+        Self::AddLayer {
+            layer,
+            name: name.into(),
+            added: false,
+        }
+    }
+
+    /// Returns an edit moving the layer at `from` to `to`.
+    pub fn move_layer(from: i8, to: i8) -> Self {
+        Self::MoveLayer {
+            from,
+            to,
+            moved: false,
+        }
+    }
+
+    /// Returns an edit setting each of `tiles` at its `x, y` in `layer`.
+    pub fn set_tiles(layer: i8, tiles: Vec<(usize, usize, Tile)>) -> Self {
+        Self::SetTiles {
+            layer,
+            tiles,
+            created: false,
+        }
+    }
+
+    /// Returns an edit setting `tile` at `x, y` in `layer`.
+    pub fn set_tile(x: usize, y: usize, layer: i8, tile: Tile) -> Self {
+        Self::set_tiles(layer, vec![(x, y, tile)])
+    }
+
+    /// Returns an edit setting `tile` on every in-bounds tile in
+    /// the rectangle with (inclusive) corners `x1, y1` and `x2, y2`.
+    pub fn set_rect(
+        map: &TileMap,
+        x1: isize,
+        y1: isize,
+        x2: isize,
+        y2: isize,
+        layer: i8,
+        tile: &Tile,
+    ) -> Self {
+        let tiles = map
+            .tiles_in_rect(x1, y1, x2, y2)
+            .map(|(x, y)| (x, y, tile.clone()))
+            .collect();
+        Self::set_tiles(layer, tiles)
+    }
+
+    /// Returns an edit setting `tile` on every tile connected
+    /// to (and identical to) the tile at `x, y` in `layer`.
+    pub fn fill(map: &TileMap, x: usize, y: usize, layer: i8, tile: &Tile) -> Self {
+        let tiles = map
+            .tiles_connected_to(x, y, layer)
+            .into_iter()
+            .map(|(x, y)| (x, y, tile.clone()))
+            .collect();
+        Self::set_tiles(layer, tiles)
+    }
+
+    /// Swaps every tile in `tiles` with the tile in `map`.
+    fn swap_tiles(map: &mut TileMap, layer: i8, tiles: &mut [(usize, usize, Tile)]) {
+        for (x, y, tile) in tiles.iter_mut() {
+            let swapped = std::mem::replace(tile, Tile::Empty);
+            *tile = match map.get_tile(*x, *y, layer) {
+                Some(existing) => std::mem::replace(existing, swapped),
+                None => {
+                    map.set_tile(*x, *y, layer, swapped);
+                    Tile::Empty
+                }
+            };
+        }
+    }
+}
+
+impl Command<TileMap> for TileEdit {
+    fn apply(&mut self, map: &mut TileMap) {
+        match self {
+            Self::SetTiles {
+                layer,
+                tiles,
+                created,
+            } => {
+                *created = map.layer(*layer).is_none();
+                Self::swap_tiles(map, *layer, tiles);
+            }
+            Self::AddLayer { layer, name, added } => {
+                *added = map.layer(*layer).is_none();
+                if *added {
+                    map.add_layer(*layer, name.clone());
+                }
+            }
+            Self::RemoveLayer { layer, removed } => *removed = map.remove_layer(*layer),
+            Self::MoveLayer { from, to, moved } => *moved = map.move_layer(*from, *to),
+        }
+    }
+
+    fn revert(&mut self, map: &mut TileMap) {
+        match self {
+            Self::SetTiles {
+                layer,
+                tiles,
+                created,
+            } => {
+                Self::swap_tiles(map, *layer, tiles);
+                if *created {
+                    map.remove_layer(*layer);
+                }
+            }
+            Self::AddLayer { layer, added, .. } => {
+                if *added {
+                    map.remove_layer(*layer);
+                }
+            }
+            Self::RemoveLayer { layer, removed } => {
+                if let Some(removed) = removed.take() {
+                    map.insert_layer(*layer, removed);
+                }
+            }
+            Self::MoveLayer { from, to, moved } => {
+                if *moved {
+                    map.move_layer(*to, *from);
+                }
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        let tiles = match self {
+            Self::SetTiles { tiles, .. } => tiles.len(),
+            Self::RemoveLayer {
+                removed: Some(removed),
+                ..
+            } => removed.tiles.len(),
+            _ => 0,
+        };
+
+        std::mem::size_of::<Self>() + tiles * std::mem::size_of::<(usize, usize, Tile)>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::{miniquad, prelude::Vec2, texture::Texture2D};
+
+    use super::super::TileTexture;
+    use super::*;
+
+    /// Command appending a value to a vector.
+    struct Push(i32);
+
+    impl Command<Vec<i32>> for Push {
+        fn apply(&mut self, target: &mut Vec<i32>) {
+            target.push(self.0);
+        }
+
+        fn revert(&mut self, target: &mut Vec<i32>) {
+            assert_eq!(Some(self.0), target.pop());
+        }
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::new(usize::MAX);
+        let mut values = vec![];
+
+        history.execute(&mut values, Push(1));
+        history.execute(&mut values, Push(2));
+        assert!(history.undo(&mut values));
+        assert_eq!(vec![1], values);
+        assert!(history.redo(&mut values));
+        assert_eq!(vec![1, 2], values);
+        assert!(!history.redo(&mut values));
+
+        // New edits clear the redo stack.
+        history.undo(&mut values);
+        history.execute(&mut values, Push(3));
+        assert!(!history.can_redo());
+        assert_eq!(vec![1, 3], values);
+    }
+
+    #[test]
+    fn transactions() {
+        let mut history = History::new(usize::MAX);
+        let mut values = vec![];
+
+        history.begin();
+        history.execute(&mut values, Push(1));
+        history.begin();
+        history.execute(&mut values, Push(2));
+        history.commit();
+        history.execute(&mut values, Push(3));
+        history.commit();
+        history.execute(&mut values, Push(4));
+
+        assert!(history.undo(&mut values));
+        assert_eq!(vec![1, 2, 3], values);
+        assert!(history.undo(&mut values));
+        assert!(values.is_empty());
+        assert!(!history.undo(&mut values));
+    }
+
+    #[test]
+    fn memory_cap() {
+        let size = std::mem::size_of::<Push>();
+        let mut history = History::new(size * 2);
+        let mut values = vec![];
+
+        for i in 0..5 {
+            history.execute(&mut values, Push(i));
+        }
+
+        assert_eq!(size * 2, history.size());
+        assert!(history.undo(&mut values));
+        assert!(history.undo(&mut values));
+        assert!(!history.undo(&mut values));
+        assert_eq!(vec![0, 1, 2], values);
+    }
+
+    /// Returns a 2x2 map with one empty layer at height 0.
+    fn map() -> TileMap {
+        let mut map = TileMap::with_view_size(2, 2, Vec2::splat(64.0));
+        map.add_layer(0, "Ground");
+        map
+    }
+
+    /// Returns a filled tile, without a graphics context.
+    fn wall() -> Tile {
+        let texture = miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0));
+        Tile::Filled {
+            texture: TileTexture {
+                texture: Texture2D::from_miniquad_texture(texture),
+            },
+            height_offset: None,
+            blend_color: None,
+        }
+    }
+
+    /// Returns the name of the layer at `height` in `map`.
+    fn name(map: &TileMap, height: i8) -> Option<&str> {
+        map.layer(height).map(|layer| layer.name.as_str())
+    }
+
+    #[test]
+    fn set_tiles_round_trip() {
+        let mut map = map();
+
+        let mut edit = TileEdit::set_tile(1, 0, 0, wall());
+        edit.apply(&mut map);
+        assert!(map.tile(1, 0, 0) == Some(&wall()));
+        edit.revert(&mut map);
+        assert!(map.tile(1, 0, 0) == Some(&Tile::Empty));
+
+        // Edits on missing layers create, then remove, them.
+        let mut edit = TileEdit::set_tile(1, 0, 1, wall());
+        edit.apply(&mut map);
+        assert!(map.tile(1, 0, 1) == Some(&wall()));
+        edit.revert(&mut map);
+        assert!(map.layer(1).is_none());
+    }
+
+    #[test]
+    fn add_and_remove_layer_round_trip() {
+        let mut map = map();
+
+        let mut edit = TileEdit::add_layer(1, "Roof");
+        edit.apply(&mut map);
+        assert_eq!(Some("Roof"), name(&map, 1));
+        edit.revert(&mut map);
+        assert_eq!(None, name(&map, 1));
+
+        // Adding over an existing layer keeps it on revert.
+        let mut edit = TileEdit::add_layer(0, "Roof");
+        edit.apply(&mut map);
+        edit.revert(&mut map);
+        assert_eq!(Some("Ground"), name(&map, 0));
+
+        map.set_tile(0, 1, 0, wall());
+        let mut edit = TileEdit::RemoveLayer {
+            layer: 0,
+            removed: None,
+        };
+        edit.apply(&mut map);
+        assert_eq!(None, name(&map, 0));
+        edit.revert(&mut map);
+        assert_eq!(Some("Ground"), name(&map, 0));
+        assert!(map.tile(0, 1, 0) == Some(&wall()));
+    }
+
+    #[test]
+    fn move_layer_round_trip() {
+        let mut map = map();
+        map.add_layer(1, "Roof");
+
+        let mut edit = TileEdit::move_layer(0, 1);
+        edit.apply(&mut map);
+        assert_eq!(
+            (Some("Roof"), Some("Ground")),
+            (name(&map, 0), name(&map, 1))
+        );
+        edit.revert(&mut map);
+        assert_eq!(
+            (Some("Ground"), Some("Roof")),
+            (name(&map, 0), name(&map, 1))
+        );
+
+        // Moving a missing layer doesn't move the one at `to` back.
+        let mut edit = TileEdit::move_layer(2, 0);
+        edit.apply(&mut map);
+        edit.revert(&mut map);
+        assert_eq!((Some("Ground"), None), (name(&map, 0), name(&map, 2)));
+    }
+}
@@ -0,0 +1,163 @@
+//! Named, individually-styled layers of a [`TileMap`].
+use glam::Vec2;
+
+use crate::color::{self, Color};
+
+use super::{Tile, TileMap};
+
+/// A named layer of [`Tile`]s in a [`TileMap`].
+pub struct Layer {
+    /// Human-readable name of the layer.
+    pub name: String,
+
+    /// True if the layer should be drawn.
+    pub visible: bool,
+
+    /// Opacity (`0.0..=1.0`) applied to every
+    /// tile in the layer during drawing.
+    pub opacity: f32,
+
+    /// Color blended with every tile in
+    /// the layer during drawing.
+    pub tint: Color,
+
+    /// Offset, in tiles, applied to the layer's
+    /// view position during drawing.
+    pub offset: Vec2,
+
+    /// Opacity applied to the layer's light tiles in place
+    /// of the map's [`TileMap::ghost_opacity`] while this
+    /// layer is below the map's active layer.
+    pub ghost_opacity: Option<f32>,
+
+    /// Dense vector of the layer's tiles.
+    pub(super) tiles: Vec<Tile>,
+}
+
+impl Layer {
+    /// Returns a new layer named `name` with
+    /// `tiles_per_layer` empty tiles.
+    fn new(name: String, tiles_per_layer: usize) -> Self {
+        let mut tiles = Vec::with_capacity(tiles_per_layer);
+        for _ in 0..tiles_per_layer {
+            tiles.push(Tile::Empty);
+        }
+
+        Self {
+            name,
+            visible: true,
+            opacity: 1.0,
+            tint: color::DEFAULT,
+            offset: Vec2::ZERO,
+            ghost_opacity: None,
+            tiles,
+        }
+    }
+
+    /// Empties every tile in the layer.
+    pub fn clear(&mut self) {
+        self.tiles.fill(Tile::Empty);
+    }
+
+    /// Returns the layer's tiles, in map index order.
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+}
+
+impl TileMap {
+    /// Adds an empty layer named `name` at height
+    /// `layer`, returning the new layer.
+    ///
+    /// If a layer already exists at `layer`, it
+    /// is renamed and returned unchanged.
+    pub fn add_layer(&mut self, layer: i8, name: impl Into<String>) -> &mut Layer {
+        let name = name.into();
+        let tiles_per_layer = self.tiles_per_layer;
+
+        let layer = self
+            .layers
+            .entry(layer)
+            .or_insert_with(|| Layer::new(String::new(), tiles_per_layer));
+        layer.name = name;
+        layer
+    }
+
+    /// Removes and returns the layer at height `layer`.
+    pub fn remove_layer(&mut self, layer: i8) -> Option<Layer> {
+        self.layers.remove(&layer)
+    }
+
+    /// Inserts `layer` at height `height`, returning
+    /// any layer previously at `height`.
+    ///
+    /// Layers with a different number of tiles
+    /// than the map are rejected and returned.
+    pub fn insert_layer(&mut self, height: i8, layer: Layer) -> Option<Layer> {
+        if layer.tiles.len() != self.tiles_per_layer {
+            return Some(layer);
+        }
+
+        self.layers.insert(height, layer)
+    }
+
+    /// Moves the layer at height `from` to height `to`,
+    /// swapping it with any layer already at `to`.
+    ///
+    /// Returns false if there is no layer at `from`.
+    pub fn move_layer(&mut self, from: i8, to: i8) -> bool {
+        let Some(moved) = self.layers.remove(&from) else {
+            return false;
+        };
+
+        if let Some(displaced) = self.layers.insert(to, moved) {
+            self.layers.insert(from, displaced);
+        }
+
+        true
+    }
+
+    /// Returns the layer at height `layer`.
+    pub fn layer(&self, layer: i8) -> Option<&Layer> {
+        self.layers.get(&layer)
+    }
+
+    /// Returns the layer at height `layer`.
+    pub fn layer_mut(&mut self, layer: i8) -> Option<&mut Layer> {
+        self.layers.get_mut(&layer)
+    }
+
+    /// Returns all layers and their heights, lowest first.
+    pub fn layers(&self) -> impl Iterator<Item = (i8, &Layer)> {
+        self.layers.iter().map(|(height, layer)| (*height, layer))
+    }
+
+    /// Returns the layer at height `layer`,
+    /// adding a default layer if none exists.
+    pub(super) fn layer_or_default(&mut self, layer: i8) -> &mut Layer {
+        let tiles_per_layer = self.tiles_per_layer;
+
+        self.layers
+            .entry(layer)
+            .or_insert_with(|| Layer::new(format!("Layer {layer}"), tiles_per_layer))
+    }
+
+    /// Returns the opacity of the layer at height `layer`.
+    pub(super) fn layer_opacity(&self, layer: i8) -> f32 {
+        self.layers.get(&layer).map_or(0.0, |state| state.opacity)
+    }
+
+    /// Returns the opacity of light tiles in the layer at height
+    /// `layer`, accounting for ghosting below the active layer.
+    pub(super) fn ghost_opacity(&self, layer: i8) -> f32 {
+        let Some(state) = self.layers.get(&layer) else {
+            return 1.0;
+        };
+
+        let ghosted = matches!(self.active_layer, Some(active) if layer < active);
+        match (ghosted, state.ghost_opacity, self.ghost_opacity) {
+            (true, Some(opacity), _) | (true, None, Some(opacity)) => opacity,
+            _ => 1.0,
+        }
+    }
+}
@@ -0,0 +1,583 @@
+//! Rasterized shape queries over a [`TileMap`]'s grid.
+//!
+//! Tiles are treated as unit squares centered on
+//! their integer grid coordinates, so the tile at
+//! `(2, 3)` covers `[1.5, 2.5) x [2.5, 3.5)`. A tile
+//! is "inside" a filled shape when its center is.
+//!
+//! Every query yields each tile at most once.
+use glam::Vec2;
+
+use super::TileMap;
+
+impl TileMap {
+    /// Returns every in-bounds tile touched by the line
+    /// from `x1, y1` to `x2, y2`, ordered from start to end.
+    ///
+    /// See [`supercover_line`].
+    pub fn tiles_on_supercover_line(
+        &self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.clip(supercover_line(x1, y1, x2, y2))
+    }
+
+    /// Returns every in-bounds tile whose center is within
+    /// `radius` of `center_x, center_y`.
+    pub fn tiles_in_disc(
+        &self,
+        center_x: f32,
+        center_y: f32,
+        radius: f32,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.clip(disc(center_x, center_y, radius))
+    }
+
+    /// Returns every in-bounds tile whose center is between
+    /// `inner_radius` and `outer_radius` (inclusive) of
+    /// `center_x, center_y`.
+    pub fn tiles_in_annulus(
+        &self,
+        center_x: f32,
+        center_y: f32,
+        inner_radius: f32,
+        outer_radius: f32,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.clip(annulus(center_x, center_y, inner_radius, outer_radius))
+    }
+
+    /// Returns every in-bounds tile on the outline of the
+    /// rectangle with (inclusive) corners `x1, y1` and `x2, y2`.
+    pub fn tiles_on_rect(
+        &self,
+        x1: isize,
+        y1: isize,
+        x2: isize,
+        y2: isize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.clip(rect_outline(x1, y1, x2, y2))
+    }
+
+    /// Returns every in-bounds tile inside the rectangle
+    /// with (inclusive) corners `x1, y1` and `x2, y2`.
+    pub fn tiles_in_rect(
+        &self,
+        x1: isize,
+        y1: isize,
+        x2: isize,
+        y2: isize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.clip(rect_fill(x1, y1, x2, y2))
+    }
+
+    /// Returns every in-bounds tile whose center is
+    /// inside the convex polygon with `vertices`.
+    pub fn tiles_in_polygon(&self, vertices: &[Vec2]) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.clip(convex_polygon(vertices))
+    }
+
+    /// Returns every in-bounds tile in the cone starting at
+    /// `origin`, pointing towards `direction`, spreading
+    /// `half_angle` radians to each side and reaching `radius`.
+    pub fn tiles_in_cone(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        half_angle: f32,
+        radius: f32,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.clip(cone(origin, direction, half_angle, radius))
+    }
+
+    /// Filters `points` to those within the map's bounds.
+    pub(super) fn clip(
+        &self,
+        points: impl Iterator<Item = (isize, isize)>,
+    ) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width as isize;
+        let height = self.height as isize;
+
+        points.filter_map(move |(x, y)| {
+            if x >= 0 && y >= 0 && x < width && y < height {
+                Some((x as usize, y as usize))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Returns the tile containing the point `x, y`.
+fn tile_at(x: f32, y: f32) -> (isize, isize) {
+    ((x + 0.5).floor() as isize, (y + 0.5).floor() as isize)
+}
+
+/// Returns every tile touched by the line from
+/// `x1, y1` to `x2, y2`, ordered from start to end.
+///
+/// Unlike Bresenham's algorithm, a supercover line
+/// includes _every_ tile the line passes through; when
+/// the line crosses exactly through a tile corner, both
+/// tiles adjacent to that corner are included.
+///
+/// Related reading: http://www.cse.yorku.ca/~amana/research/grid.pdf
+pub fn supercover_line(x1: f32, y1: f32, x2: f32, y2: f32) -> SupercoverLine {
+    let start = tile_at(x1, y1);
+    let end = tile_at(x2, y2);
+
+    let dx = x2 - x1;
+    let dy = y2 - y1;
+    let step = (if dx < 0.0 { -1 } else { 1 }, if dy < 0.0 { -1 } else { 1 });
+
+    // Distance (in units of line length) to the first
+    // tile boundary on each axis, and between boundaries.
+    let boundary = |origin: f32, tile: isize, step: isize, delta: f32| {
+        if delta == 0.0 {
+            return (f32::INFINITY, f32::INFINITY);
+        }
+
+        let edge = tile as f32 + 0.5 * step as f32;
+        ((edge - origin) / delta, 1.0 / delta.abs())
+    };
+    let (max_x, delta_x) = boundary(x1, start.0, step.0, dx);
+    let (max_y, delta_y) = boundary(y1, start.1, step.1, dy);
+
+    SupercoverLine {
+        current: Some(start),
+        corners: [None, None],
+        end,
+        step,
+        max: (max_x, max_y),
+        delta: (delta_x, delta_y),
+        remaining: (end.0 - start.0).unsigned_abs() + (end.1 - start.1).unsigned_abs(),
+    }
+}
+
+/// Iterator returned by [`supercover_line`].
+pub struct SupercoverLine {
+    /// Next tile on the line.
+    current: Option<(isize, isize)>,
+
+    /// Extra tiles to yield before [`Self::current`]
+    /// when the line crossed a tile corner.
+    corners: [Option<(isize, isize)>; 2],
+
+    /// Final tile on the line.
+    end: (isize, isize),
+
+    /// Direction of travel on each axis.
+    step: (isize, isize),
+
+    /// Line distance to the next boundary on each axis.
+    max: (f32, f32),
+
+    /// Line distance between boundaries on each axis.
+    delta: (f32, f32),
+
+    /// Axis-aligned steps remaining until [`Self::end`].
+    remaining: usize,
+}
+
+impl Iterator for SupercoverLine {
+    type Item = (isize, isize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(corner) = self.corners.iter_mut().find_map(Option::take) {
+            return Some(corner);
+        }
+
+        let tile = self.current.take()?;
+        if tile == self.end || self.remaining == 0 {
+            return Some(tile);
+        }
+
+        let (x, y) = tile;
+        let (step_x, step_y) = self.step;
+        let (max_x, max_y) = self.max;
+
+        self.current = if max_x < max_y {
+            self.max.0 += self.delta.0;
+            self.remaining -= 1;
+            Some((x + step_x, y))
+        } else if max_y < max_x {
+            self.max.1 += self.delta.1;
+            self.remaining -= 1;
+            Some((x, y + step_y))
+
+        // Crossing a corner: cover both adjacent tiles
+        // before stepping diagonally.
+        } else {
+            self.max.0 += self.delta.0;
+            self.max.1 += self.delta.1;
+            self.remaining = self.remaining.saturating_sub(2);
+            self.corners = [Some((x + step_x, y)), Some((x, y + step_y))];
+            Some((x + step_x, y + step_y))
+        };
+
+        Some(tile)
+    }
+}
+
+/// Returns every tile whose center is within
+/// `radius` of `center_x, center_y`.
+pub fn disc(center_x: f32, center_y: f32, radius: f32) -> impl Iterator<Item = (isize, isize)> {
+    annulus(center_x, center_y, -1.0, radius)
+}
+
+/// Returns every tile whose center is between `inner_radius`
+/// and `outer_radius` (inclusive) of `center_x, center_y`.
+pub fn annulus(
+    center_x: f32,
+    center_y: f32,
+    inner_radius: f32,
+    outer_radius: f32,
+) -> impl Iterator<Item = (isize, isize)> {
+    let outer_squared = outer_radius * outer_radius;
+    let inner_squared = if inner_radius > 0.0 {
+        inner_radius * inner_radius
+    } else {
+        -1.0
+    };
+
+    let min_y = (center_y - outer_radius).ceil() as isize;
+    let max_y = (center_y + outer_radius).floor() as isize;
+
+    (min_y..=max_y).flat_map(move |y| {
+        // Only scan the columns this row's chord can reach.
+        let dy = y as f32 - center_y;
+        let half_chord = (outer_squared - dy * dy).max(0.0).sqrt();
+        let min_x = (center_x - half_chord).ceil() as isize;
+        let max_x = (center_x + half_chord).floor() as isize;
+
+        (min_x..=max_x).filter_map(move |x| {
+            let dx = x as f32 - center_x;
+            let distance_squared = dx * dx + dy * dy;
+
+            if distance_squared <= outer_squared && distance_squared >= inner_squared {
+                Some((x, y))
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// Returns every tile on the outline of the rectangle
+/// with (inclusive) corners `x1, y1` and `x2, y2`.
+pub fn rect_outline(
+    x1: isize,
+    y1: isize,
+    x2: isize,
+    y2: isize,
+) -> impl Iterator<Item = (isize, isize)> {
+    let (min_x, max_x) = (x1.min(x2), x1.max(x2));
+    let (min_y, max_y) = (y1.min(y2), y1.max(y2));
+
+    rect_fill(x1, y1, x2, y2)
+        .filter(move |&(x, y)| x == min_x || x == max_x || y == min_y || y == max_y)
+}
+
+/// Returns every tile inside the rectangle with
+/// (inclusive) corners `x1, y1` and `x2, y2`.
+pub fn rect_fill(
+    x1: isize,
+    y1: isize,
+    x2: isize,
+    y2: isize,
+) -> impl Iterator<Item = (isize, isize)> {
+    let (min_x, max_x) = (x1.min(x2), x1.max(x2));
+    let (min_y, max_y) = (y1.min(y2), y1.max(y2));
+
+    (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| (x, y)))
+}
+
+/// Returns every tile whose center is inside (or on the
+/// edge of) the convex polygon with `vertices`, which
+/// may be wound in either direction.
+pub fn convex_polygon(vertices: &[Vec2]) -> impl Iterator<Item = (isize, isize)> {
+    let vertices = vertices.to_vec();
+
+    // Scan the polygon's bounding box.
+    let (min, max) = if vertices.is_empty() {
+        (Vec2::ONE, Vec2::ZERO)
+    } else {
+        vertices.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), v| (min.min(*v), max.max(*v)),
+        )
+    };
+
+    rect_fill(
+        min.x.ceil() as isize,
+        min.y.ceil() as isize,
+        max.x.floor() as isize,
+        max.y.floor() as isize,
+    )
+    .filter(move |&(x, y)| !vertices.is_empty() && polygon_contains(&vertices, x, y))
+}
+
+/// Returns true if the point `x, y` is inside (or on
+/// the edge of) the convex polygon with `vertices`.
+fn polygon_contains(vertices: &[Vec2], x: isize, y: isize) -> bool {
+    let point = Vec2::new(x as f32, y as f32);
+    let mut winding = 0.0f32;
+
+    for (i, a) in vertices.iter().enumerate() {
+        let b = vertices[(i + 1) % vertices.len()];
+        let cross = (b - *a).perp_dot(point - *a);
+
+        // The point is outside as soon as it falls on
+        // the opposite side of any edge.
+        if cross * winding < 0.0 {
+            return false;
+        }
+
+        if cross != 0.0 {
+            winding = cross;
+        }
+    }
+
+    true
+}
+
+/// Returns every tile in the cone starting at `origin`,
+/// pointing towards `direction`, spreading `half_angle`
+/// radians to each side and reaching `radius`.
+///
+/// The tile containing `origin` is always included.
+pub fn cone(
+    origin: Vec2,
+    direction: Vec2,
+    half_angle: f32,
+    radius: f32,
+) -> impl Iterator<Item = (isize, isize)> {
+    let direction = direction.normalize_or_zero();
+    let min_cos = half_angle.clamp(0.0, std::f32::consts::PI).cos();
+    let origin_tile = tile_at(origin.x, origin.y);
+
+    disc(origin.x, origin.y, radius).filter(move |&(x, y)| {
+        if (x, y) == origin_tile {
+            return true;
+        }
+
+        let offset = Vec2::new(x as f32, y as f32) - origin;
+        let length = offset.length();
+
+        length == 0.0 || offset.dot(direction) >= min_cos * length
+    })
+}
+
+/// Returns the tiles on Bresenham's line from the tile at
+/// `x1, y1` to the tile at `x2, y2` (truncating each to
+/// integers), ordered from start to end.
+///
+/// Unlike a [`supercover_line`], the line only steps
+/// diagonally between tiles, never through both
+/// tiles beside a corner.
+pub fn bresenham_line(x1: f32, y1: f32, x2: f32, y2: f32) -> Vec<(isize, isize)> {
+    let ax = x1 as isize;
+    let ay = y1 as isize;
+    let bx = x2 as isize;
+    let by = y2 as isize;
+
+    let dx = bx - ax;
+    let dy = by - ay;
+    let abs_dx = dx.abs();
+    let abs_dy = dy.abs();
+
+    let mut x = ax;
+    let mut y = ay;
+
+    let mut line_points = vec![(x, y)];
+
+    let inc_x = |x| {
+        if dx < 0 {
+            x - 1
+        } else {
+            x + 1
+        }
+    };
+
+    let inc_y = |y| {
+        if dy < 0 {
+            y - 1
+        } else {
+            y + 1
+        }
+    };
+
+    // Small slope.
+    if abs_dx > abs_dy {
+        let mut d = 2 * abs_dy - abs_dx;
+
+        for _ in 0..abs_dx {
+            x = inc_x(x);
+
+            if d < 0 {
+                d += 2 * abs_dy
+            } else {
+                y = inc_y(y);
+
+                d += (2 * abs_dy) - (2 * abs_dx);
+            }
+
+            line_points.push((x, y));
+        }
+
+    // Large slope.
+    } else {
+        let mut d = 2 * abs_dx - abs_dy;
+
+        for _ in 0..abs_dy {
+            y = inc_y(y);
+
+            if d < 0 {
+                d += 2 * abs_dx;
+            } else {
+                x = inc_x(x);
+
+                d += (2 * abs_dx) - (2 * abs_dy);
+            }
+
+            line_points.push((x, y));
+        }
+    }
+
+    line_points
+}
+
+/// Returns the tiles on the midpoint circle of
+/// `radius` around `center_x, center_y`, sorted.
+pub fn circle(center_x: isize, center_y: isize, radius: isize) -> Vec<(isize, isize)> {
+    let mut diameter = (5 - radius * 4) / 4;
+
+    let mut radius_points = vec![];
+    let mut x = 0;
+    let mut y = radius;
+
+    while x <= y {
+        radius_points.push((center_x + x, center_y + y));
+        radius_points.push((center_x + x, center_y - y));
+        radius_points.push((center_x - x, center_y + y));
+        radius_points.push((center_x - x, center_y - y));
+
+        radius_points.push((center_x + y, center_y + x));
+        radius_points.push((center_x + y, center_y - x));
+        radius_points.push((center_x - y, center_y + x));
+        radius_points.push((center_x - y, center_y - x));
+
+        if diameter < 0 {
+            diameter += 2 * x + 1;
+        } else {
+            diameter += 2 * (x - y) + 1;
+            y -= 1;
+        }
+
+        x += 1;
+    }
+
+    // Octants overlap on the diagonals and axes.
+    radius_points.sort_unstable();
+    radius_points.dedup();
+
+    radius_points
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Asserts `points` contains no duplicates, returning them as a set.
+    fn unique(points: impl Iterator<Item = (isize, isize)>) -> HashSet<(isize, isize)> {
+        let points: Vec<_> = points.collect();
+        let set: HashSet<_> = points.iter().copied().collect();
+        assert_eq!(points.len(), set.len(), "duplicate points in {points:?}");
+        set
+    }
+
+    #[test]
+    fn supercover_line_is_contiguous() {
+        for (x1, y1, x2, y2) in [
+            (0.0, 0.0, 5.0, 2.0),
+            (3.2, 7.9, -4.1, 1.6),
+            (0.0, 0.0, 4.0, 4.0),
+            (2.0, 2.0, 2.0, 2.0),
+            (1.0, 5.0, 1.0, -3.0),
+        ] {
+            let points: Vec<_> = supercover_line(x1, y1, x2, y2).collect();
+            unique(points.iter().copied());
+
+            assert_eq!(Some(&tile_at(x1, y1)), points.first());
+            assert_eq!(Some(&tile_at(x2, y2)), points.last());
+
+            // Each tile must neighbor the previous one.
+            for pair in points.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                assert_eq!(1, (a.0 - b.0).abs().max((a.1 - b.1).abs()), "{points:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn supercover_line_covers_corners() {
+        let points = unique(supercover_line(0.0, 0.0, 2.0, 2.0));
+        assert_eq!(7, points.len());
+        assert!(points.contains(&(1, 0)));
+        assert!(points.contains(&(2, 1)));
+    }
+
+    #[test]
+    fn discs_and_annuli() {
+        let points = unique(disc(0.0, 0.0, 1.0));
+        assert_eq!(5, points.len());
+
+        let points = unique(disc(0.0, 0.0, 2.5));
+        assert_eq!(21, points.len());
+
+        let ring = unique(annulus(0.0, 0.0, 1.5, 2.5));
+        assert!(!ring.contains(&(0, 0)));
+        assert!(!ring.contains(&(1, 1)));
+        assert!(ring.contains(&(2, 0)));
+        assert!(ring.contains(&(2, 1)));
+    }
+
+    #[test]
+    fn rectangles() {
+        assert_eq!(12, unique(rect_fill(3, 1, 0, 3)).len());
+        assert_eq!(10, unique(rect_outline(0, 1, 3, 3)).len());
+        assert_eq!(1, unique(rect_outline(2, 2, 2, 2)).len());
+        assert_eq!(4, unique(rect_outline(0, 0, 3, 0)).len());
+    }
+
+    #[test]
+    fn polygons() {
+        let triangle = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(0.0, 4.0),
+        ];
+        let points = unique(convex_polygon(&triangle));
+        assert_eq!(15, points.len());
+
+        // Winding order shouldn't matter.
+        let reversed: Vec<_> = triangle.iter().rev().copied().collect();
+        assert_eq!(points, unique(convex_polygon(&reversed)));
+
+        assert_eq!(0, convex_polygon(&[]).count());
+    }
+
+    #[test]
+    fn cones() {
+        let points = unique(cone(Vec2::ZERO, Vec2::X, 0.2, 3.0));
+        assert_eq!(HashSet::from([(0, 0), (1, 0), (2, 0), (3, 0)]), points);
+
+        // A full half-angle is a disc.
+        let points = unique(cone(Vec2::ZERO, Vec2::Y, std::f32::consts::PI, 2.5));
+        assert_eq!(unique(disc(0.0, 0.0, 2.5)), points);
+    }
+}
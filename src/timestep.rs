@@ -0,0 +1,85 @@
+//! Fixed timesteps decoupling simulation from frame rate.
+//!
+//! A [`FixedTimestep`] accumulates frame times, handing
+//! out whole steps of a fixed duration to simulate; the
+//! time left over is how far drawing should interpolate
+//! between the last two simulated states.
+
+/// Accumulates frame time into fixed-duration steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedTimestep {
+    /// Duration, in seconds, of each step.
+    step: f32,
+
+    /// Most steps run for a single frame.
+    max_steps: u32,
+
+    /// Seconds of frame time not yet simulated.
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Returns a timestep of `step` seconds,
+    /// running at most eight steps a frame.
+    pub fn new(step: f32) -> Self {
+        Self {
+            step,
+            max_steps: 8,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Runs at most `max_steps` steps for a single frame,
+    /// dropping any time beyond them so that long frames
+    /// don't snowball into ever longer frames.
+    pub fn max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Returns the duration, in seconds, of each step.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Accumulates `frame_time` seconds, returning
+    /// the number of whole steps to simulate.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+
+        let steps = (self.accumulator / self.step) as u32;
+        self.accumulator -= steps as f32 * self.step;
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+            return self.max_steps;
+        }
+
+        steps
+    }
+
+    /// Returns how far (`0.0..1.0`) the time not yet
+    /// simulated is into the next step, for interpolating
+    /// between the previous and current simulated states.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_whole_steps() {
+        let mut timestep = FixedTimestep::new(0.25).max_steps(4);
+
+        assert_eq!(0, timestep.advance(0.125));
+        assert_eq!(0.5, timestep.alpha());
+        assert_eq!(2, timestep.advance(0.5));
+        assert_eq!(0.5, timestep.alpha());
+
+        // Long frames drop time beyond the most steps.
+        assert_eq!(4, timestep.advance(10.0));
+        assert_eq!(0.0, timestep.alpha());
+    }
+}
@@ -0,0 +1,477 @@
+//! Full-screen transitions between states.
+//!
+//! A [`ScreenTransition`] covers the screen with an [`Effect`],
+//! calls back at its midpoint (while the screen is fully
+//! covered), then uncovers the screen. As a [`State`], it
+//! draws the state it leaves until the midpoint, and the
+//! state it enters afterwards, replacing itself with the
+//! entered state once it completes.
+use std::f32::consts::TAU;
+
+use glam::Vec2;
+use macroquad::{
+    camera::Camera2D,
+    color::WHITE,
+    math::Rect,
+    texture::{DrawTextureParams, FilterMode, RenderTarget, Texture2D},
+};
+
+use crate::{
+    color::{self, Color},
+    easing::Easing,
+    state::{State, Transition},
+};
+
+// Ordered dithering thresholds for the dissolve effect.
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Segments in the iris effect's circle.
+const IRIS_SEGMENTS: usize = 64;
+
+/// Edge of the screen a wipe starts from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// A way of covering the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    /// Fades to `color`.
+    Fade { color: Color },
+
+    /// Fades to `color`, blended with
+    /// `tint` at `tint_opacity`.
+    Flash {
+        color: Color,
+        tint: Color,
+        tint_opacity: f32,
+    },
+
+    /// Slides `color` across the screen from `from`.
+    Wipe { color: Color, from: Edge },
+
+    /// Closes a circle of `color` around `center`
+    /// (in screen pixels), or the screen's center.
+    Iris { color: Color, center: Option<Vec2> },
+
+    /// Fills the screen with `color` in a dithered
+    /// pattern of `cell`-pixel squares.
+    Dissolve { color: Color, cell: f32 },
+
+    /// Pixelates the screen into squares up to `block` pixels wide.
+    ///
+    /// Pixelation doesn't hide the screen, so it's
+    /// usually paired with states that look alike.
+    Pixelate { block: f32 },
+}
+
+impl Effect {
+    /// Draws the effect covering `coverage`
+    /// (`0.0..=1.0`) of the screen.
+    fn draw(&self, coverage: f32, buffers: &mut Option<PixelBuffers>) {
+        let coverage = coverage.clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            return;
+        }
+
+        let screen = Vec2::new(
+            macroquad::window::screen_width(),
+            macroquad::window::screen_height(),
+        );
+
+        match *self {
+            Self::Fade { color } => draw_rect(Vec2::ZERO, screen, color, coverage),
+            Self::Flash {
+                color,
+                tint,
+                tint_opacity,
+            } => {
+                draw_rect(Vec2::ZERO, screen, color, coverage);
+                draw_rect(Vec2::ZERO, screen, tint, coverage * tint_opacity);
+            }
+            Self::Wipe { color, from } => {
+                let (position, size) = match from {
+                    Edge::Left => (Vec2::ZERO, Vec2::new(screen.x * coverage, screen.y)),
+                    Edge::Right => (
+                        Vec2::new(screen.x * (1.0 - coverage), 0.0),
+                        Vec2::new(screen.x * coverage, screen.y),
+                    ),
+                    Edge::Top => (Vec2::ZERO, Vec2::new(screen.x, screen.y * coverage)),
+                    Edge::Bottom => (
+                        Vec2::new(0.0, screen.y * (1.0 - coverage)),
+                        Vec2::new(screen.x, screen.y * coverage),
+                    ),
+                };
+                draw_rect(position, size, color, 1.0);
+            }
+            Self::Iris { color, center } => {
+                let center = center.unwrap_or(screen / 2.0);
+
+                // The circle opens wide enough to
+                // uncover every corner of the screen.
+                let reach = [
+                    Vec2::ZERO,
+                    screen,
+                    Vec2::new(screen.x, 0.0),
+                    Vec2::new(0.0, screen.y),
+                ]
+                .iter()
+                .map(|corner| corner.distance(center))
+                .fold(0.0, f32::max);
+                let radius = reach * (1.0 - coverage);
+                let outer = reach * 2.0;
+                let color = color::as_macroquad_color(color);
+
+                for segment in 0..IRIS_SEGMENTS {
+                    let angle = |segment: usize| {
+                        let angle = segment as f32 / IRIS_SEGMENTS as f32 * TAU;
+                        Vec2::new(angle.cos(), angle.sin())
+                    };
+                    let (a, b) = (angle(segment), angle(segment + 1));
+
+                    let (inner_a, inner_b) = (center + a * radius, center + b * radius);
+                    let (outer_a, outer_b) = (center + a * outer, center + b * outer);
+                    macroquad::shapes::draw_triangle(inner_a, outer_a, outer_b, color);
+                    macroquad::shapes::draw_triangle(inner_a, outer_b, inner_b, color);
+                }
+            }
+            Self::Dissolve { color, cell } => {
+                let cell = cell.max(1.0);
+                let threshold = (coverage * 16.0).round() as u8;
+                let color = color::as_macroquad_color(color);
+
+                for row in 0..(screen.y / cell).ceil() as usize {
+                    for column in 0..(screen.x / cell).ceil() as usize {
+                        if BAYER_4X4[row % 4][column % 4] < threshold {
+                            macroquad::shapes::draw_rectangle(
+                                column as f32 * cell,
+                                row as f32 * cell,
+                                cell,
+                                cell,
+                                color,
+                            );
+                        }
+                    }
+                }
+            }
+            Self::Pixelate { block } => {
+                let block = 1.0 + (block.max(1.0) - 1.0) * coverage;
+                if block < 2.0 {
+                    return;
+                }
+
+                let buffers = PixelBuffers::fit(buffers, screen, block);
+                buffers.screen.grab_screen();
+
+                // Downsample the screen into the small
+                // buffer, then stretch it back out; both
+                // passes flip, as GL textures are bottom-up.
+                let mut camera =
+                    Camera2D::from_display_rect(Rect::new(0.0, 0.0, screen.x, screen.y));
+                camera.render_target = Some(buffers.pixels.clone());
+                macroquad::camera::set_camera(&camera);
+                macroquad::texture::draw_texture_ex(
+                    &buffers.screen,
+                    0.0,
+                    0.0,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(screen),
+                        flip_y: true,
+                        ..Default::default()
+                    },
+                );
+
+                macroquad::camera::set_default_camera();
+                macroquad::texture::draw_texture_ex(
+                    &buffers.pixels.texture,
+                    0.0,
+                    0.0,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(screen),
+                        flip_y: true,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Draws a `size` rectangle at `position`
+/// with `color` and `opacity`.
+fn draw_rect(position: Vec2, size: Vec2, mut color: Color, opacity: f32) {
+    color.alpha = (opacity.clamp(0.0, 1.0) * color.alpha as f32) as u8;
+    macroquad::shapes::draw_rectangle(
+        position.x,
+        position.y,
+        size.x,
+        size.y,
+        color::as_macroquad_color(color),
+    );
+}
+
+/// Textures reused by [`Effect::Pixelate`].
+struct PixelBuffers {
+    /// Copy of the screen.
+    screen: Texture2D,
+
+    /// Downsampled copy of the screen.
+    pixels: RenderTarget,
+
+    /// Screen size and block size the buffers fit.
+    screen_size: Vec2,
+    block: u32,
+}
+
+impl PixelBuffers {
+    /// Returns `buffers`, recreated if they don't
+    /// fit a `screen`-sized screen and `block`.
+    fn fit(buffers: &mut Option<Self>, screen: Vec2, block: f32) -> &mut Self {
+        let block = block as u32;
+        if buffers
+            .as_ref()
+            .is_some_and(|b| b.screen_size != screen || b.block != block)
+        {
+            *buffers = None;
+        }
+
+        buffers.get_or_insert_with(|| {
+            let screen_texture = Texture2D::from_rgba8(
+                screen.x as u16,
+                screen.y as u16,
+                &vec![0; screen.x as usize * screen.y as usize * 4],
+            );
+            screen_texture.set_filter(FilterMode::Nearest);
+
+            let pixels = macroquad::texture::render_target(
+                (screen.x as u32 / block).max(1),
+                (screen.y as u32 / block).max(1),
+            );
+            pixels.texture.set_filter(FilterMode::Nearest);
+
+            Self {
+                screen: screen_texture,
+                pixels,
+                screen_size: screen,
+                block,
+            }
+        })
+    }
+}
+
+/// Callback run at a [`ScreenTransition`]'s midpoint.
+type Midpoint<C> = Box<dyn FnOnce(&mut C)>;
+
+/// A full-screen transition out of one state and into another.
+pub struct ScreenTransition<C> {
+    /// Effect covering the screen.
+    effect: Effect,
+
+    /// Seconds spent covering the screen.
+    out_duration: f32,
+
+    /// Seconds spent uncovering the screen.
+    in_duration: f32,
+
+    /// Easing of coverage while covering the screen.
+    out_easing: Easing,
+
+    /// Easing of coverage while uncovering the screen.
+    in_easing: Easing,
+
+    /// Seconds since the transition started.
+    elapsed: f32,
+
+    /// State drawn (and updated) until the midpoint.
+    from: Option<Box<dyn State<C>>>,
+
+    /// State drawn (and updated) after the midpoint,
+    /// which replaces the transition once it completes.
+    to: Option<Box<dyn State<C>>>,
+
+    /// Callback for the midpoint, until it's called.
+    midpoint: Option<Midpoint<C>>,
+
+    /// True once the midpoint has passed.
+    passed_midpoint: bool,
+
+    /// Buffers for pixelation.
+    buffers: Option<PixelBuffers>,
+}
+
+impl<C> ScreenTransition<C> {
+    /// Returns a transition covering the screen with
+    /// `effect` for `out_duration` seconds, then
+    /// uncovering it for `in_duration` seconds.
+    pub fn new(effect: Effect, out_duration: f32, in_duration: f32) -> Self {
+        Self {
+            effect,
+            out_duration,
+            in_duration,
+            out_easing: Easing::Linear,
+            in_easing: Easing::Linear,
+            elapsed: 0.0,
+            from: None,
+            to: None,
+            midpoint: None,
+            passed_midpoint: false,
+            buffers: None,
+        }
+    }
+
+    /// Eases coverage with `out_easing` while covering
+    /// the screen, and `in_easing` while uncovering it.
+    pub fn with_easing(mut self, out_easing: Easing, in_easing: Easing) -> Self {
+        self.out_easing = out_easing;
+        self.in_easing = in_easing;
+        self
+    }
+
+    /// Draws and updates `state` until the midpoint.
+    ///
+    /// Transitions returned by `state` are ignored.
+    pub fn from(mut self, state: impl State<C> + 'static) -> Self {
+        self.from = Some(Box::new(state));
+        self
+    }
+
+    /// Draws and updates `state` after the midpoint,
+    /// replacing the transition with it once complete.
+    ///
+    /// Transitions returned by `state` are ignored
+    /// until it replaces the transition.
+    pub fn to(mut self, state: impl State<C> + 'static) -> Self {
+        self.to = Some(Box::new(state));
+        self
+    }
+
+    /// Calls `callback` at the midpoint, while
+    /// the screen is fully covered.
+    pub fn on_midpoint(mut self, callback: impl FnOnce(&mut C) + 'static) -> Self {
+        self.midpoint = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns the fraction (`0.0..=1.0`) of
+    /// the screen covered by the effect.
+    pub fn coverage(&self) -> f32 {
+        if self.elapsed < self.out_duration {
+            self.out_easing.apply(self.elapsed / self.out_duration)
+        } else if self.in_duration > 0.0 {
+            1.0 - self
+                .in_easing
+                .apply((self.elapsed - self.out_duration) / self.in_duration)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns true once the transition is complete.
+    pub fn is_complete(&self) -> bool {
+        self.elapsed >= self.out_duration + self.in_duration
+    }
+
+    /// Advances the transition by `frame_time`
+    /// seconds, calling back at the midpoint.
+    pub fn advance(&mut self, context: &mut C, frame_time: f32) {
+        self.elapsed += frame_time;
+
+        if !self.passed_midpoint && self.elapsed >= self.out_duration {
+            self.passed_midpoint = true;
+            if let Some(midpoint) = self.midpoint.take() {
+                midpoint(context);
+            }
+        }
+    }
+
+    /// Draws the effect over the screen.
+    pub fn draw_effect(&mut self) {
+        self.effect.draw(self.coverage(), &mut self.buffers);
+    }
+
+    /// Returns the state shown at the current point
+    /// of the transition.
+    fn shown(&mut self) -> Option<&mut Box<dyn State<C>>> {
+        if self.passed_midpoint {
+            self.to.as_mut()
+        } else {
+            self.from.as_mut()
+        }
+    }
+}
+
+impl<C> State<C> for ScreenTransition<C> {
+    fn update(&mut self, context: &mut C, frame_time: f32) -> Transition<C> {
+        self.advance(context, frame_time);
+
+        if self.is_complete() {
+            return match self.to.take() {
+                Some(to) => Transition::Replace(to),
+                None => Transition::Pop,
+            };
+        }
+
+        if let Some(state) = self.shown() {
+            state.update(context, frame_time);
+        }
+
+        Transition::None
+    }
+
+    fn draw(&mut self, context: &mut C) {
+        if let Some(state) = self.shown() {
+            state.draw(context);
+        }
+
+        self.draw_effect();
+    }
+
+    fn is_overlay(&self) -> bool {
+        // Without a state to show, draw over the stack.
+        if self.passed_midpoint {
+            self.to.is_none()
+        } else {
+            self.from.is_none()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_back_at_midpoint() {
+        let mut transition = ScreenTransition::new(
+            Effect::Fade {
+                color: color::BACKGROUND,
+            },
+            1.0,
+            2.0,
+        )
+        .on_midpoint(|calls: &mut usize| *calls += 1);
+        let mut calls = 0;
+
+        transition.advance(&mut calls, 0.5);
+        assert_eq!(0.5, transition.coverage());
+        assert_eq!(0, calls);
+
+        transition.advance(&mut calls, 0.5);
+        assert_eq!(1.0, transition.coverage());
+        assert_eq!(1, calls);
+
+        transition.advance(&mut calls, 1.0);
+        assert_eq!(0.5, transition.coverage());
+        assert!(!transition.is_complete());
+
+        transition.advance(&mut calls, 1.0);
+        assert!(transition.is_complete());
+        assert_eq!(1, calls);
+    }
+}
@@ -0,0 +1,535 @@
+//! Tweens animating values over time, like
+//! sprite positions, colors and the camera.
+//!
+//! A [`Tween`] eases a [`Tweenable`] value between two
+//! endpoints, handing each new value to a callback along
+//! with some shared context `C` (like the game's state).
+//! Any [`Animation`] can be chained into a [`Sequence`]
+//! or run alongside others in a [`Parallel`] group.
+use glam::Vec2;
+
+use crate::{color::Color, easing::Easing};
+
+/// A value which can be interpolated.
+pub trait Tweenable: Copy {
+    /// Returns the value `t` (usually `0.0..=1.0`)
+    /// of the way from `self` to `to`.
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Tweenable for Vec2 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec2::lerp(self, to, t)
+    }
+}
+
+impl Tweenable for Color {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        let channel = |from: u8, to: u8| {
+            (from as f32)
+                .lerp(to as f32, t)
+                .round()
+                .clamp(0.0, u8::MAX as f32) as u8
+        };
+
+        Color::new(
+            channel(self.red, to.red),
+            channel(self.green, to.green),
+            channel(self.blue, to.blue),
+            channel(self.alpha, to.alpha),
+        )
+    }
+}
+
+/// Tuples tween each of their values, so related
+/// values (like a camera's offset and scale)
+/// can share one tween.
+impl<A: Tweenable, B: Tweenable> Tweenable for (A, B) {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        (self.0.lerp(to.0, t), self.1.lerp(to.1, t))
+    }
+}
+
+/// Number of times an animation plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Repeat {
+    /// Plays once, then `n` more times.
+    Times(u32),
+
+    /// Plays until dropped.
+    Forever,
+
+    /// Plays once.
+    #[default]
+    Never,
+}
+
+impl Repeat {
+    /// Consumes one repetition, returning
+    /// false if there were none left.
+    fn take(&mut self) -> bool {
+        match self {
+            Self::Forever => true,
+            Self::Times(0) | Self::Never => false,
+            Self::Times(n) => {
+                *n -= 1;
+                true
+            }
+        }
+    }
+}
+
+/// Something animated over time, with context `C`.
+pub trait Animation<C> {
+    /// Advances the animation by `frame_time` seconds, returning
+    /// the seconds left over if the animation completed.
+    fn advance(&mut self, context: &mut C, frame_time: f32) -> f32;
+
+    /// Returns true if the animation completed.
+    fn is_complete(&self) -> bool;
+
+    /// Restarts the animation from the beginning,
+    /// with all of its repetitions.
+    fn restart(&mut self);
+}
+
+/// Callback of an [`Animation`].
+type Callback<C, T> = Box<dyn FnMut(&mut C, T)>;
+
+/// Completion callbacks shared by every animation.
+struct Completion<C> {
+    callback: Option<Callback<C, ()>>,
+    repeat: Repeat,
+    remaining: Repeat,
+    complete: bool,
+}
+
+impl<C> Default for Completion<C> {
+    fn default() -> Self {
+        Self {
+            callback: None,
+            repeat: Repeat::Never,
+            remaining: Repeat::Never,
+            complete: false,
+        }
+    }
+}
+
+impl<C> Completion<C> {
+    /// Marks the animation complete and calls back.
+    fn complete(&mut self, context: &mut C) {
+        self.complete = true;
+        if let Some(callback) = &mut self.callback {
+            callback(context, ());
+        }
+    }
+
+    fn restart(&mut self) {
+        self.remaining = self.repeat;
+        self.complete = false;
+    }
+}
+
+/// Eases a value from one endpoint to another.
+pub struct Tween<C, T> {
+    from: T,
+    to: T,
+    duration: f32,
+    easing: Easing,
+
+    /// Seconds into the current play.
+    elapsed: f32,
+
+    /// True if every other play runs backwards.
+    yoyo: bool,
+
+    /// True if the current play runs backwards.
+    reversed: bool,
+
+    /// Value as of the last update.
+    value: T,
+
+    on_update: Option<Callback<C, T>>,
+    completion: Completion<C>,
+}
+
+impl<C, T: Tweenable> Tween<C, T> {
+    /// Returns a linear tween from `from` to `to` over `duration` seconds.
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: Easing::Linear,
+            elapsed: 0.0,
+            yoyo: false,
+            reversed: false,
+            value: from,
+            on_update: None,
+            completion: Completion::default(),
+        }
+    }
+
+    /// Eases the tween with `easing`.
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Repeats the tween.
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.completion.repeat = repeat;
+        self.completion.remaining = repeat;
+        self
+    }
+
+    /// Plays every other repetition of the tween backwards.
+    pub fn yoyo(mut self, yoyo: bool) -> Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    /// Calls `callback` with each new value of the tween.
+    pub fn on_update(mut self, callback: impl FnMut(&mut C, T) + 'static) -> Self {
+        self.on_update = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` when the tween completes.
+    pub fn on_complete(mut self, mut callback: impl FnMut(&mut C) + 'static) -> Self {
+        self.completion.callback = Some(Box::new(move |context, _| callback(context)));
+        self
+    }
+
+    /// Returns the tween's value as of the last update.
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    /// Returns the tween's endpoints.
+    pub fn endpoints(&self) -> (T, T) {
+        (self.from, self.to)
+    }
+
+    /// Restarts the tween from its current value towards `to`.
+    pub fn retarget(&mut self, to: T) {
+        self.from = self.value;
+        self.to = to;
+        self.restart();
+    }
+}
+
+impl<C, T: Tweenable> Animation<C> for Tween<C, T> {
+    fn advance(&mut self, context: &mut C, frame_time: f32) -> f32 {
+        if self.completion.complete {
+            return frame_time;
+        }
+
+        self.elapsed += frame_time;
+        let mut leftover = 0.0;
+        let mut completed = false;
+        while self.elapsed >= self.duration {
+            if self.duration > 0.0 && self.completion.remaining.take() {
+                self.elapsed -= self.duration;
+                self.reversed ^= self.yoyo;
+            } else {
+                leftover = self.elapsed - self.duration;
+                self.elapsed = self.duration;
+                completed = true;
+                break;
+            }
+        }
+
+        let progress = if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        };
+        let progress = if self.reversed {
+            1.0 - progress
+        } else {
+            progress
+        };
+        self.value = self.from.lerp(self.to, self.easing.apply(progress));
+
+        if let Some(on_update) = &mut self.on_update {
+            on_update(context, self.value);
+        }
+
+        if completed {
+            self.completion.complete(context);
+        }
+
+        leftover
+    }
+
+    fn is_complete(&self) -> bool {
+        self.completion.complete
+    }
+
+    fn restart(&mut self) {
+        self.elapsed = 0.0;
+        self.reversed = false;
+        self.value = self.from;
+        self.completion.restart();
+    }
+}
+
+/// Animations played one after another.
+pub struct Sequence<C> {
+    animations: Vec<Box<dyn Animation<C>>>,
+
+    /// Index of the playing animation.
+    index: usize,
+
+    /// Seconds the current pass through the sequence took so far.
+    elapsed: f32,
+
+    completion: Completion<C>,
+}
+
+impl<C> Default for Sequence<C> {
+    fn default() -> Self {
+        Self {
+            animations: vec![],
+            index: 0,
+            elapsed: 0.0,
+            completion: Completion::default(),
+        }
+    }
+}
+
+impl<C> Sequence<C> {
+    /// Returns an empty sequence.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plays `animation` after the sequence's other animations.
+    pub fn then(mut self, animation: impl Animation<C> + 'static) -> Self {
+        self.animations.push(Box::new(animation));
+        self
+    }
+
+    /// Repeats the sequence.
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.completion.repeat = repeat;
+        self.completion.remaining = repeat;
+        self
+    }
+
+    /// Calls `callback` when the sequence completes.
+    pub fn on_complete(mut self, mut callback: impl FnMut(&mut C) + 'static) -> Self {
+        self.completion.callback = Some(Box::new(move |context, _| callback(context)));
+        self
+    }
+}
+
+impl<C> Animation<C> for Sequence<C> {
+    fn advance(&mut self, context: &mut C, mut frame_time: f32) -> f32 {
+        if self.completion.complete {
+            return frame_time;
+        }
+
+        loop {
+            let Some(animation) = self.animations.get_mut(self.index) else {
+                // Restart repeating sequences, so long as each
+                // pass takes time; passes taking no time would
+                // otherwise repeat forever within a frame.
+                if self.elapsed > 0.0 && self.completion.remaining.take() {
+                    self.index = 0;
+                    self.elapsed = 0.0;
+                    self.animations.iter_mut().for_each(|a| a.restart());
+                    continue;
+                }
+
+                self.completion.complete(context);
+                return frame_time;
+            };
+
+            let leftover = animation.advance(context, frame_time);
+            self.elapsed += frame_time - leftover.max(0.0);
+            if !animation.is_complete() {
+                return 0.0;
+            }
+
+            self.index += 1;
+            if leftover <= 0.0 && self.index < self.animations.len() {
+                return 0.0;
+            }
+            frame_time = leftover;
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.completion.complete
+    }
+
+    fn restart(&mut self) {
+        self.index = 0;
+        self.elapsed = 0.0;
+        self.animations.iter_mut().for_each(|a| a.restart());
+        self.completion.restart();
+    }
+}
+
+/// Animations played at the same time.
+pub struct Parallel<C> {
+    animations: Vec<Box<dyn Animation<C>>>,
+    completion: Completion<C>,
+}
+
+impl<C> Default for Parallel<C> {
+    fn default() -> Self {
+        Self {
+            animations: vec![],
+            completion: Completion::default(),
+        }
+    }
+}
+
+impl<C> Parallel<C> {
+    /// Returns an empty group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plays `animation` alongside the group's other animations.
+    pub fn with(mut self, animation: impl Animation<C> + 'static) -> Self {
+        self.animations.push(Box::new(animation));
+        self
+    }
+
+    /// Repeats the group once all of its animations complete.
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.completion.repeat = repeat;
+        self.completion.remaining = repeat;
+        self
+    }
+
+    /// Calls `callback` when every animation in the group completes.
+    pub fn on_complete(mut self, mut callback: impl FnMut(&mut C) + 'static) -> Self {
+        self.completion.callback = Some(Box::new(move |context, _| callback(context)));
+        self
+    }
+}
+
+impl<C> Animation<C> for Parallel<C> {
+    fn advance(&mut self, context: &mut C, mut frame_time: f32) -> f32 {
+        if self.completion.complete {
+            return frame_time;
+        }
+
+        loop {
+            let leftover = self
+                .animations
+                .iter_mut()
+                .map(|animation| animation.advance(context, frame_time))
+                .fold(frame_time, f32::min);
+
+            if !self.animations.iter().all(|a| a.is_complete()) {
+                return 0.0;
+            }
+
+            // Restart repeating groups with the leftover time, so
+            // long as each pass takes time; passes taking no time
+            // would otherwise repeat forever within a frame.
+            if leftover < frame_time && self.completion.remaining.take() {
+                self.animations.iter_mut().for_each(|a| a.restart());
+                if leftover <= 0.0 {
+                    return 0.0;
+                }
+                frame_time = leftover;
+                continue;
+            }
+
+            self.completion.complete(context);
+            return leftover;
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.completion.complete
+    }
+
+    fn restart(&mut self) {
+        self.animations.iter_mut().for_each(|a| a.restart());
+        self.completion.restart();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tweens_repeat_and_yoyo() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0)
+            .repeat(Repeat::Times(1))
+            .yoyo(true)
+            .on_update(|values: &mut Vec<f32>, value| values.push(value));
+        let mut values = vec![];
+
+        tween.advance(&mut values, 0.25);
+        tween.advance(&mut values, 1.0);
+        assert!(!tween.is_complete());
+        assert_eq!(0.5, tween.advance(&mut values, 1.25));
+        assert!(tween.is_complete());
+
+        assert_eq!(vec![2.5, 7.5, 0.0], values);
+    }
+
+    #[test]
+    fn sequences_carry_leftover_time() {
+        let mut sequence = Sequence::new()
+            .then(Tween::new(0.0, 1.0, 1.0).on_update(|x: &mut (f32, f32), v| x.0 = v))
+            .then(Tween::new(0.0, 1.0, 1.0).on_update(|x: &mut (f32, f32), v| x.1 = v))
+            .on_complete(|x| *x = (-1.0, -1.0));
+        let mut x = (0.0, 0.0);
+
+        sequence.advance(&mut x, 1.5);
+        assert_eq!((1.0, 0.5), x);
+
+        sequence.advance(&mut x, 0.5);
+        assert!(sequence.is_complete());
+        assert_eq!((-1.0, -1.0), x);
+
+        // Sequences taking no time complete rather than repeating forever.
+        let mut instant = Sequence::new()
+            .then(Tween::new(0.0, 1.0, 0.0))
+            .repeat(Repeat::Forever);
+        assert_eq!(0.016, instant.advance(&mut (), 0.016));
+        assert!(instant.is_complete());
+    }
+
+    #[test]
+    fn parallel_groups_wait_for_every_animation() {
+        let mut group = Parallel::new()
+            .with(Tween::new(Vec2::ZERO, Vec2::ONE, 1.0))
+            .with(Tween::new(0.0, 1.0, 2.0))
+            .on_complete(|completions: &mut u32| *completions += 1);
+        let mut completions = 0;
+
+        group.advance(&mut completions, 1.5);
+        assert!(!group.is_complete());
+        group.advance(&mut completions, 1.0);
+        assert!(group.is_complete());
+        assert_eq!(1, completions);
+
+        // Repeating groups carry leftover time into the next pass.
+        let mut repeating = Parallel::new()
+            .with(Tween::new(0.0, 1.0, 1.0).on_update(|x: &mut f32, v| *x = v))
+            .repeat(Repeat::Times(1));
+        let mut x = 0.0;
+        repeating.advance(&mut x, 1.25);
+        assert_eq!(0.25, x);
+        assert_eq!(0.5, repeating.advance(&mut x, 1.25));
+        assert!(repeating.is_complete());
+    }
+}
@@ -0,0 +1,46 @@
+// Polls gamepads for Layered through the browser's Gamepad
+// API, as a miniquad plugin loaded after macroquad.js.
+(function () {
+    "use strict";
+
+    // Returns the first connected gamepad
+    // with the standard layout, if any.
+    function gamepad() {
+        const pads = navigator.getGamepads ? navigator.getGamepads() : [];
+        for (const pad of pads) {
+            if (pad && pad.connected && pad.mapping === "standard") {
+                return pad;
+            }
+        }
+        return null;
+    }
+
+    // Copies up to `buf_len` values into the f32s at `buf_ptr`,
+    // returning the number copied, or -1 without a gamepad.
+    function copy(values, buf_ptr, buf_len) {
+        if (values === null) {
+            return -1;
+        }
+
+        const buf = new Float32Array(wasm_memory.buffer, buf_ptr, buf_len);
+        const count = Math.min(buf_len, values.length);
+        for (let i = 0; i < count; i++) {
+            buf[i] = values[i];
+        }
+        return count;
+    }
+
+    function register_plugin(importObject) {
+        importObject.env.layered_gamepad_buttons = function (buf_ptr, buf_len) {
+            const pad = gamepad();
+            return copy(pad && pad.buttons.map((button) => button.value), buf_ptr, buf_len);
+        };
+
+        importObject.env.layered_gamepad_axes = function (buf_ptr, buf_len) {
+            const pad = gamepad();
+            return copy(pad && pad.axes, buf_ptr, buf_len);
+        };
+    }
+
+    miniquad_add_plugin({ register_plugin, version: 1, name: "layered_gamepad" });
+})();
@@ -0,0 +1,46 @@
+// Keeps Layered's saves in the browser's localStorage,
+// as a miniquad plugin loaded after macroquad.js.
+(function () {
+    "use strict";
+
+    const encoder = new TextEncoder();
+    const decoder = new TextDecoder();
+
+    const bytes = (ptr, len) => new Uint8Array(wasm_memory.buffer, ptr, len);
+    const string = (ptr, len) => decoder.decode(bytes(ptr, len));
+
+    // Returns the item at a key, or null if there's no
+    // item or localStorage is unavailable.
+    function item(key_ptr, key_len) {
+        try {
+            return localStorage.getItem(string(key_ptr, key_len));
+        } catch (e) {
+            return null;
+        }
+    }
+
+    function register_plugin(importObject) {
+        importObject.env.layered_storage_len = function (key_ptr, key_len) {
+            const value = item(key_ptr, key_len);
+            return value === null ? -1 : encoder.encode(value).length;
+        };
+
+        importObject.env.layered_storage_read = function (key_ptr, key_len, buf_ptr, buf_len) {
+            const value = item(key_ptr, key_len);
+            if (value !== null) {
+                bytes(buf_ptr, buf_len).set(encoder.encode(value).subarray(0, buf_len));
+            }
+        };
+
+        importObject.env.layered_storage_write = function (key_ptr, key_len, value_ptr, value_len) {
+            try {
+                localStorage.setItem(string(key_ptr, key_len), string(value_ptr, value_len));
+                return 1;
+            } catch (e) {
+                return 0;
+            }
+        };
+    }
+
+    miniquad_add_plugin({ register_plugin, version: 1, name: "layered_storage" });
+})();